@@ -0,0 +1,45 @@
+//! 強化学習向けの環境をランダムな行動で動かす
+//!
+//! `cargo run --example random_agent --no-default-features`で描画と音声なしに実行できる。
+//! 最後の画面を引数のパス(省略時は一時ディレクトリの`last_frame.pgm`)に書き出す。
+
+use rand::{Rng, SeedableRng};
+
+use breakout::game::breakout::env::{Action, BreakOutEnv, EnvParam, PixelParam};
+
+fn main() -> anyhow::Result<()> {
+    let out = std::env::args_os().nth(1)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("last_frame.pgm"));
+    let mut env = BreakOutEnv::new(EnvParam {
+        pixels: Some(PixelParam::default()), 
+        frame_skip: 4, 
+        max_steps: Some(5000), 
+        ..Default::default()
+    });
+    let mut rng = rand_pcg::Pcg32::seed_from_u64(0);
+    for episode in 0..3 {
+        env.reset(episode);
+        let mut total = 0.;
+        let obs = loop {
+            let action = Action::from_index(rng.gen_range(0..Action::COUNT)).unwrap();
+            let (obs, reward, done) = env.step(action);
+            total += reward;
+            if done { break obs }
+        };
+        let info = env.info();
+        println!(
+            "episode {episode}: reward {total}, steps {0}, bricks left {1}, state length {2}", 
+            info.steps, 
+            info.remain_brick, 
+            obs.state.as_ref().map_or(0, |s| s.len()), 
+        );
+        if let Some(frame) = obs.pixels.as_ref() {
+            let mut pgm = format!("P5\n{0} {1}\n255\n", frame.width, frame.height).into_bytes();
+            pgm.extend(&frame.data);
+            std::fs::write(&out, pgm)?;
+            println!("last frame written to {0}", out.display());
+        }
+    }
+    Ok(())
+}
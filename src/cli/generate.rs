@@ -0,0 +1,75 @@
+//! `generate`: シード値からステージを一括で生成する
+
+use std::path::PathBuf;
+
+use breakout::game::breakout::level::generator::{self, GeneratorParam};
+
+const USAGE: &str = "\
+usage: breakout generate [OPTIONS]
+
+options:
+  --seed N            first seed (default: 0)
+  --count N           number of levels to generate (default: 1)
+  --out DIR           output directory (default: ./generated)
+  --column N          number of brick lines
+  --row N             number of bricks per line
+  --symmetry S        none | mirror | rotational
+  --pattern P         random | pyramid | checker[:N] | text:WORD
+  --density F         probability of placing a brick (0.0 - 1.0)
+  --weights A,B,C     weights of Normal, Upper and Top bricks
+  --difficulty F      target difficulty (0.0 - 1.0), overrides --density
+  --preview           print the layout of each level";
+
+/// 生成の実行
+pub fn run(mut args: super::Args) -> anyhow::Result<i32> {
+    let mut param = GeneratorParam::default();
+    let mut seed = 0u64;
+    let mut count = 1u64;
+    let mut out = PathBuf::from("./generated");
+    let mut preview = false;
+    while let Some(flag) = args.next_flag() {
+        match flag {
+            "--seed" => seed = args.value(flag)?, 
+            "--count" => count = args.value(flag)?, 
+            "--out" => out = args.value(flag)?, 
+            "--column" => param.column = args.value(flag)?, 
+            "--row" => param.row = args.value(flag)?, 
+            "--symmetry" => param.symmetry = args.value(flag)?, 
+            "--pattern" => param.pattern = args.value(flag)?, 
+            "--density" => param.density = args.value(flag)?, 
+            "--weights" => {
+                let weights: String = args.value(flag)?;
+                let weights = weights.split(',')
+                    .map(|w| w.trim().parse::<u32>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                param.weights = weights.try_into()
+                    .map_err(|_| anyhow::anyhow!("--weights requires 3 values"))?;
+            }, 
+            "--difficulty" => param.difficulty = Some(args.value(flag)?), 
+            "--preview" => preview = true, 
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(0)
+            }, 
+            _ => anyhow::bail!("unknown option: {flag}\n{USAGE}"), 
+        }
+    }
+    param.check()?;
+
+    std::fs::create_dir_all(&out)?;
+    for seed in (0..count).map(|i| seed.wrapping_add(i)) {
+        let level = generator::generate(seed, &param);
+        let path = out.join(format!("gen_{seed}.ron"));
+        level.save(&path)?;
+        let bricks = level.bricks.iter().flatten().flatten().count();
+        println!(
+            "seed {seed:>6}  bricks {bricks:>4}  difficulty {0:.3}  {1}", 
+            generator::difficulty(&level), 
+            path.display(), 
+        );
+        if preview {
+            println!("{0}", generator::preview(&level));
+        }
+    }
+    Ok(0)
+}
@@ -0,0 +1,69 @@
+//! コマンドラインのサブコマンド
+//!
+//! サブコマンドが指定されなかった場合はゲームを起動する。
+
+/// ステージの自動生成
+mod generate;
+
+/// オンライン対戦の模擬
+mod netsim;
+
+/// コンピュータによるステージの耐久試験
+mod soak;
+
+/// ステージの検証
+mod validate;
+
+/// ステージの共有コード
+mod share;
+
+/// オプションの引数の読み取り
+pub struct Args<'a> {
+    args: std::slice::Iter<'a, String>, 
+}
+impl<'a> Args<'a> {
+    pub fn new(args: &'a [String]) -> Self { Self {
+        args: args.iter(), 
+    }}
+    /// 次のオプション名
+    pub fn next_flag(&mut self) -> Option<&'a str> {
+        self.args.next().map(|s| s.as_str())
+    }
+    /// オプションの値の読み取り
+    pub fn value<T>(&mut self, flag: &str) -> anyhow::Result<T> where
+        T: std::str::FromStr, 
+        T::Err: std::fmt::Display, 
+    {
+        let value = self.args.next()
+            .ok_or_else(|| anyhow::anyhow!("{flag} requires a value"))?;
+        value.parse()
+            .map_err(|e| anyhow::anyhow!("invalid value for {flag}: {value} ({e})"))
+    }
+}
+
+/// サブコマンドの実行
+///
+/// サブコマンドを実行した場合は終了コードを返す。
+pub fn run(args: &[String]) -> anyhow::Result<Option<i32>> {
+    let Some((command, rest)) = args.split_first() else { return Ok(None) };
+    match command.as_str() {
+        "generate" => generate::run(Args::new(rest)).map(Some), 
+        "netsim" => netsim::run(Args::new(rest)).map(Some), 
+        "soak" => soak::run(Args::new(rest)).map(Some), 
+        "validate" => validate::run(Args::new(rest)).map(Some), 
+        "share" => share::run(Args::new(rest)).map(Some), 
+        "help" | "--help" | "-h" => {
+            println!("usage: breakout [COMMAND]");
+            println!();
+            println!("commands:");
+            println!("  generate    generate levels from seeds");
+            println!("  netsim      run an online match over a simulated network");
+            println!("  soak        let the AI play levels and report whether they can be cleared");
+            println!("  validate    check level files for errors and report their stats");
+            println!("  share       convert between level files and shareable level codes");
+            println!("  help        show this message");
+            Ok(Some(0))
+        }, 
+        _ => anyhow::bail!("unknown command: {command} (see `breakout help`)"), 
+    }
+}
@@ -0,0 +1,144 @@
+//! `netsim`: 遅延とパケットロスを模擬した通信路でオンライン対戦を自動で試す
+//!
+//! 同じプロセス内で2つの接続を作り、決められた入力で対戦させた後に双方の状態を比べる。
+
+use std::time::{Duration, Instant};
+
+use breakout::game::breakout::entities::paddle::PaddleMoveFlag;
+use breakout::game::breakout::level::fnv1a;
+use breakout::game::net::{
+    NetMode, 
+    handshake::Handshake, 
+    rollback::NetPeer, 
+    transport::{ShimTransport, UdpTransport}, 
+};
+use breakout::sfx::MutedSfx;
+
+const USAGE: &str = "\
+usage: breakout netsim [OPTIONS]
+
+options:
+  --latency MS        one-way latency in milliseconds (default: 50)
+  --loss F            packet loss rate (0.0 - 1.0, default: 0.05)
+  --ticks N           number of ticks to simulate (default: 1200)
+  --frame-ms MS       wall-clock time per tick (default: 16)
+  --mode M            versus | coop (default: versus)
+  --port N            port of the host (default: 7000)";
+
+/// 接続を待つ最大の時間
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 各プレイヤーの決められた入力
+fn scripted_input(player: usize, tick: u32) -> PaddleMoveFlag {
+    // 一定の間隔で向きを変え、途中で入力を切り替えて予測を外させる
+    let phase = fnv1a(&[player as u8, (tick / 20) as u8, (tick / 5120) as u8]);
+    PaddleMoveFlag {
+        move_left: phase % 3 == 0, 
+        move_right: phase % 3 == 1, 
+        move_delta: 0., 
+        ball_shot: tick % 90 < 2, 
+    }
+}
+
+/// 模擬する通信路の作成
+fn shim(
+    addr: &str, 
+    latency: Duration, 
+    loss: f32, 
+    seed: u64, 
+) -> anyhow::Result<(Box<ShimTransport<UdpTransport>>, std::net::SocketAddr)> {
+    let udp = UdpTransport::bind(addr.parse()?)?;
+    let local = udp.local_addr()?;
+    Ok((Box::new(ShimTransport::new(udp, latency, loss, seed)), local))
+}
+
+/// 対戦の実行
+pub fn run(mut args: super::Args) -> anyhow::Result<i32> {
+    let mut latency = 50u64;
+    let mut loss = 0.05f32;
+    let mut ticks = 1200u32;
+    let mut frame_ms = 16u64;
+    let mut mode = NetMode::Versus;
+    let mut port = 7000u16;
+    while let Some(flag) = args.next_flag() {
+        match flag {
+            "--latency" => latency = args.value(flag)?, 
+            "--loss" => loss = args.value(flag)?, 
+            "--ticks" => ticks = args.value(flag)?, 
+            "--frame-ms" => frame_ms = args.value(flag)?, 
+            "--mode" => mode = args.value(flag)?, 
+            "--port" => port = args.value(flag)?, 
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(0)
+            }, 
+            _ => anyhow::bail!("unknown option: {flag}\n{USAGE}"), 
+        }
+    }
+    if !(0. ..1.).contains(&loss) {
+        anyhow::bail!("--loss must be in 0.0 - 1.0");
+    }
+    let latency = Duration::from_millis(latency);
+    let frame = Duration::from_millis(frame_ms);
+
+    let (host_transport, host_addr) = shim(&format!("127.0.0.1:{port}"), latency, loss, 1)?;
+    let (join_transport, _) = shim("127.0.0.1:0", latency, loss, 2)?;
+    let mut host = Handshake::host(host_transport, mode);
+    let mut join = Handshake::join(join_transport, host_addr);
+    let (mut host_peer, mut join_peer): (Option<NetPeer>, Option<NetPeer>) = (None, None);
+    let started = Instant::now();
+    while host_peer.is_none() || join_peer.is_none() {
+        if started.elapsed() > CONNECT_TIMEOUT { anyhow::bail!("failed to connect") }
+        // 確立済みの側も送受信を続け、遅らせたパケットや再送を処理する
+        match host_peer.as_mut() {
+            Some(peer) => peer.poll(None, &MutedSfx)?, 
+            None => host_peer = host.poll()?, 
+        }
+        match join_peer.as_mut() {
+            Some(peer) => peer.poll(None, &MutedSfx)?, 
+            None => join_peer = join.poll()?, 
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let mut peers = [host_peer.unwrap(), join_peer.unwrap()];
+    println!("connected in {0:?} ({1})", started.elapsed(), mode.name());
+
+    let started = Instant::now();
+    loop {
+        let frame_start = Instant::now();
+        for peer in peers.iter_mut() {
+            let session = peer.session();
+            let local = (session.tick() < ticks)
+                .then(|| scripted_input(session.local(), session.tick()));
+            peer.poll(local, &MutedSfx)?;
+        }
+        let done = peers.iter().all(|p| p.session().tick() >= ticks && p.session().confirmed() >= ticks);
+        if done { break }
+        if peers.iter().any(|p| p.timed_out()) {
+            println!("connection timed out");
+            return Ok(1)
+        }
+        std::thread::sleep(frame.saturating_sub(frame_start.elapsed()));
+    }
+
+    let mut ok = true;
+    for peer in peers.iter() {
+        let session = peer.session();
+        let stats = session.stats();
+        println!(
+            "{0}P: tick {1}, rollbacks {2}, resimulated {3}, max depth {4}, stalls {5}, checksum {6:016x}{7}", 
+            session.local() + 1, 
+            session.tick(), 
+            stats.rollbacks, 
+            stats.resimulated, 
+            stats.max_depth, 
+            stats.stalls, 
+            session.sim().checksum(), 
+            if session.desynced() { " (desync detected)" } else { "" }, 
+        );
+        ok &= !session.desynced();
+    }
+    ok &= peers[0].session().sim().checksum() == peers[1].session().sim().checksum();
+    println!("{0} in {1:?}", if ok { "in sync" } else { "DESYNC" }, started.elapsed());
+    Ok(if ok { 0 } else { 1 })
+}
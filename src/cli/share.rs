@@ -0,0 +1,53 @@
+//! `share`: ステージと共有コードを相互に変換する
+
+use std::path::PathBuf;
+
+use breakout::game::breakout::level::{LevelDef, code};
+
+const USAGE: &str = "\
+usage: breakout share export FILE
+       breakout share import CODE [--out FILE]
+
+commands:
+  export FILE         print the level code of a level file
+  import CODE         decode a level code and save it as a level file
+
+options:
+  --out FILE          output file of import (default: ./levels/imported.ron)";
+
+/// 変換の実行
+pub fn run(mut args: super::Args) -> anyhow::Result<i32> {
+    match args.next_flag() {
+        Some("export") => {
+            let path: PathBuf = args.value("export")?;
+            let level = LevelDef::load(&path)?;
+            println!("{0}", code::encode(&level)?);
+            Ok(0)
+        }, 
+        Some("import") => {
+            let code: String = args.value("import")?;
+            let mut out = PathBuf::from("./levels/imported.ron");
+            while let Some(flag) = args.next_flag() {
+                match flag {
+                    "--out" => out = args.value(flag)?, 
+                    _ => anyhow::bail!("unknown option: {flag}\n{USAGE}"), 
+                }
+            }
+            let level = match code::decode(&code) {
+                Ok(level) => level, 
+                Err(e) => {
+                    eprintln!("invalid level code: {e}");
+                    return Ok(1)
+                }, 
+            };
+            level.save(&out)?;
+            println!("{0:?} saved to {1}", level.name, out.display());
+            Ok(0)
+        }, 
+        Some("--help" | "-h") => {
+            println!("{USAGE}");
+            Ok(0)
+        }, 
+        _ => anyhow::bail!("expected export or import\n{USAGE}"), 
+    }
+}
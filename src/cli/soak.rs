@@ -0,0 +1,158 @@
+//! `soak`: コンピュータにステージを繰り返し遊ばせ、クリアできるかを確かめる
+//!
+//! 描画と音声を用いずにシミュレーションのみを行う。
+//! 一度もクリアできなかったステージや、処理中にパニックしたステージがあれば失敗とする。
+
+use std::path::PathBuf;
+
+use breakout::game::breakout::{
+    autopilot::{Autopilot, AutopilotSkill}, 
+    entities::BreakOutEntities, 
+    level::{BrickFeature, LevelDef}, 
+    campaign::{CampaignDef, DEFAULT_CAMPAIGN}, 
+    state::{BreakOutGameState, GameState}, 
+    time_attack::format_ticks, 
+};
+use breakout::sfx::MutedSfx;
+
+const USAGE: &str = "\
+usage: breakout soak [OPTIONS]
+
+options:
+  --level FILE        level to play (can be repeated)
+  --campaign FILE     play every stage of a campaign
+  --runs N            number of runs per level (default: 5)
+  --ticks N           tick limit per run (default: 36000)
+  --seed N            first seed of the AI (default: 0)
+  --skill S           easy | normal | hard (default: hard)
+  --reaction N        reaction delay in ticks, overrides --skill
+  --aim-error F       maximum aim error in pixels, overrides --skill
+
+without --level or --campaign, the built-in level and the default campaign are played.";
+
+/// シミュレーションに用いる画面の大きさ
+const DISP_SIZE: [f32; 2] = [640., 640.];
+
+/// 1回の結果
+enum RunResult {
+    /// クリアまでのティック数
+    Cleared(u32), 
+    GameOver, 
+    /// 制限時間内に終わらなかった
+    TimedOut, 
+}
+
+/// 1回の実行
+fn play(level: &LevelDef, autopilot: &mut Autopilot, ticks: u32) -> (RunResult, u32, usize) {
+    let disp_size = DISP_SIZE.into();
+    let mut entities = BreakOutEntities::<BrickFeature>::new(level.spawn_param(), disp_size);
+    let mut state = BreakOutGameState::new();
+    state.set_extra_life(level.extra_life);
+    let mut lost = 0;
+    for tick in 0..ticks {
+        let flag = autopilot.control(&entities, disp_size);
+        entities.set_input(autopilot.player(), flag);
+        let remain_ball = state.remain_ball();
+        entities.update(disp_size, &mut state, &MutedSfx);
+        if state.remain_ball() < remain_ball { lost += 1 }
+        state.award_extra_life();
+        match state.game_state() {
+            GameState::Yes => {}, 
+            GameState::GameClear => return (RunResult::Cleared(tick + 1), lost, 0), 
+            GameState::GameOver => return (RunResult::GameOver, lost, entities.remain_brick()), 
+        }
+    }
+    (RunResult::TimedOut, lost, entities.remain_brick())
+}
+
+/// 繰り返し遊ばせる
+pub fn run(mut args: super::Args) -> anyhow::Result<i32> {
+    let mut levels = Vec::new();
+    let mut runs = 5u64;
+    let mut ticks = 36000u32;
+    let mut seed = 0u64;
+    let mut skill = AutopilotSkill::Hard;
+    let mut reaction = None;
+    let mut aim_error = None;
+    while let Some(flag) = args.next_flag() {
+        match flag {
+            "--level" => {
+                let path: PathBuf = args.value(flag)?;
+                levels.push(LevelDef::load(&path)?);
+            }, 
+            "--campaign" => {
+                let path: PathBuf = args.value(flag)?;
+                levels.extend(CampaignDef::load(&path)?.stages);
+            }, 
+            "--runs" => runs = args.value(flag)?, 
+            "--ticks" => ticks = args.value(flag)?, 
+            "--seed" => seed = args.value(flag)?, 
+            "--skill" => skill = args.value(flag)?, 
+            "--reaction" => reaction = Some(args.value(flag)?), 
+            "--aim-error" => aim_error = Some(args.value(flag)?), 
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(0)
+            }, 
+            _ => anyhow::bail!("unknown option: {flag}\n{USAGE}"), 
+        }
+    }
+    if levels.is_empty() {
+        levels.push(LevelDef::builtin());
+        match CampaignDef::load(DEFAULT_CAMPAIGN) {
+            Ok(c) => levels.extend(c.stages), 
+            Err(e) => log::warn!("failed to load campaign: {e}"), 
+        }
+    }
+    let mut param = skill.param();
+    if let Some(r) = reaction { param.reaction_ticks = r }
+    if let Some(e) = aim_error { param.aim_error = e }
+
+    let mut failed = 0;
+    for level in levels.iter() {
+        let mut cleared = Vec::new();
+        let (mut game_over, mut timed_out, mut crashed, mut lost, mut worst) = (0, 0, 0, 0, 0);
+        for run in 0..runs {
+            let mut autopilot = Autopilot::new(0, skill, seed + run).with_param(param);
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(
+                || play(level, &mut autopilot, ticks)
+            ));
+            let Ok((result, run_lost, remain)) = result else {
+                crashed += 1;
+                continue
+            };
+            lost += run_lost;
+            worst = worst.max(remain);
+            match result {
+                RunResult::Cleared(t) => cleared.push(t), 
+                RunResult::GameOver => game_over += 1, 
+                RunResult::TimedOut => timed_out += 1, 
+            }
+        }
+        let ok = !cleared.is_empty() && crashed == 0;
+        if !ok { failed += 1 }
+        println!(
+            "{0:<24} cleared {1}/{2}  game over {3}  timed out {4}  crashed {5}", 
+            level.name, 
+            cleared.len(), 
+            runs, 
+            game_over, 
+            timed_out, 
+            crashed, 
+        );
+        if let Some(mean) = (!cleared.is_empty())
+            .then(|| cleared.iter().map(|t| *t as u64).sum::<u64>() / cleared.len() as u64)
+        {
+            println!("{0:<24} mean clear time {1}", "", format_ticks(mean));
+        }
+        println!(
+            "{0:<24} balls lost {1:.1}/run  most bricks left {2}{3}", 
+            "", 
+            lost as f32 / runs.max(1) as f32, 
+            worst, 
+            if ok { "" } else { "  FAILED" }, 
+        );
+    }
+    println!("{0} of {1} levels failed", failed, levels.len());
+    Ok(if failed == 0 { 0 } else { 1 })
+}
@@ -0,0 +1,112 @@
+//! `validate`: ステージの定義ファイルを検証する
+//!
+//! ディレクトリを指定した場合はその中の`.ron`ファイルを全て検証する。
+//! キャンペーンの定義ファイルは読み飛ばす。
+//! エラーのあるステージや読み込めないファイルがあれば失敗とする。
+
+use std::path::{Path, PathBuf};
+
+use breakout::game::breakout::{
+    campaign::CampaignDef, 
+    level::{LevelDef, validate::{self, Severity}}, 
+};
+
+const USAGE: &str = "\
+usage: breakout validate [OPTIONS] [PATH...]
+
+arguments:
+  PATH                level file or directory of level files (default: ./levels)
+
+options:
+  --strict            treat warnings as errors
+  --quiet             print only levels with problems";
+
+/// 検証するファイルの一覧
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(())
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    files.extend(entries.into_iter()
+        .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "ron"))
+    );
+    Ok(())
+}
+
+/// 検証
+pub fn run(mut args: super::Args) -> anyhow::Result<i32> {
+    let mut paths = Vec::new();
+    let mut strict = false;
+    let mut quiet = false;
+    while let Some(flag) = args.next_flag() {
+        match flag {
+            "--strict" => strict = true, 
+            "--quiet" => quiet = true, 
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(0)
+            }, 
+            _ if flag.starts_with("--") => anyhow::bail!("unknown option: {flag}\n{USAGE}"), 
+            _ => paths.push(PathBuf::from(flag)), 
+        }
+    }
+    if paths.is_empty() { paths.push("./levels".into()) }
+    let mut files = Vec::new();
+    for path in paths.iter() {
+        collect(path, &mut files)?;
+    }
+
+    let (mut checked, mut failed, mut warned) = (0, 0, 0);
+    for file in files.iter() {
+        let level = match LevelDef::load(file) {
+            Ok(level) => level, 
+            Err(_) if CampaignDef::load(file).is_ok() => {
+                if !quiet { println!("{0}: campaign (skipped)", file.display()) }
+                continue
+            }, 
+            Err(e) => {
+                println!("{0}: error: failed to load: {e}", file.display());
+                failed += 1;
+                continue
+            }, 
+        };
+        checked += 1;
+        let report = validate::validate(&level);
+        let errors = report.count(Severity::Error);
+        let warnings = report.count(Severity::Warning);
+        let fails = errors != 0 || (strict && warnings != 0);
+        if fails { failed += 1 } else if warnings != 0 { warned += 1 }
+        if quiet && report.issues.is_empty() { continue }
+
+        let stats = &report.stats;
+        println!(
+            "{0}: {1:?}  {2}", 
+            file.display(), 
+            level.name, 
+            if fails { "FAILED" } else if warnings != 0 { "WARN" } else { "OK" }, 
+        );
+        println!(
+            "  bricks {0} (clearable {1}, solid {2})  score {3}  lowest {4}  difficulty {5:.2} ({6})", 
+            stats.bricks, 
+            stats.clearable, 
+            stats.solid, 
+            stats.total_score, 
+            stats.lowest.map_or("-".into(), |l| format!("{l:.0}px")), 
+            stats.difficulty, 
+            stats.rating(), 
+        );
+        for issue in report.issues.iter() {
+            let label = match issue.severity() {
+                Severity::Error => "error", 
+                Severity::Warning => "warning", 
+            };
+            println!("  {label}: {issue}");
+        }
+    }
+    println!("{checked} levels checked, {failed} failed, {warned} with warnings");
+    Ok(if failed == 0 { 0 } else { 1 })
+}
@@ -0,0 +1,179 @@
+//! コンピュータによるパドルの操作
+//!
+//! ボールの位置と速度から落下地点を予測し、キー操作と同じ`PaddleMoveFlag`でパドルを動かす。
+//! 人間と同じく一定の遅れで状況を認識し、狙いには誤差が乗る。
+
+use std::collections::VecDeque;
+
+use rand::{Rng, SeedableRng};
+
+use super::entities::{
+    BreakOutEntities, 
+    brick::brick::BrickFeature, 
+    paddle::{Goal, PaddleMoveFlag, PADDLE_SPEED}, 
+};
+
+/// パドルのどこで打ち返すかの最大値(パドルの幅に対する割合)
+const AIM_RANGE: f32 = 0.25;
+
+/// 強さ
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AutopilotSkill {
+    Easy, 
+    #[default]
+    Normal, 
+    Hard, 
+}
+impl AutopilotSkill {
+    pub fn name(&self) -> &'static str { match self {
+        Self::Easy => "Easy", 
+        Self::Normal => "Normal", 
+        Self::Hard => "Hard", 
+    }}
+    /// 次の強さ
+    pub fn next(&self) -> Self { match self {
+        Self::Easy => Self::Normal, 
+        Self::Normal => Self::Hard, 
+        Self::Hard => Self::Easy, 
+    }}
+    pub fn param(&self) -> AutopilotParam { match self {
+        Self::Easy => AutopilotParam { reaction_ticks: 12, aim_error: 18., serve_ticks: 60 }, 
+        Self::Normal => AutopilotParam { reaction_ticks: 6, aim_error: 10., serve_ticks: 40 }, 
+        Self::Hard => AutopilotParam { reaction_ticks: 2, aim_error: 3., serve_ticks: 20 }, 
+    }}
+}
+impl std::str::FromStr for AutopilotSkill {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { match s {
+        "easy" => Ok(Self::Easy), 
+        "normal" => Ok(Self::Normal), 
+        "hard" => Ok(Self::Hard), 
+        _ => anyhow::bail!("unknown skill: {s}"), 
+    }}
+}
+
+/// 操作の癖
+#[derive(Clone, Copy, Debug)]
+pub struct AutopilotParam {
+    /// ボールの動きを認識するまでの遅れ(ティック数)
+    pub reaction_ticks: u32, 
+    /// 狙いの誤差の最大値
+    pub aim_error: f32, 
+    /// ボールを打ち出すまでに待つティック数
+    pub serve_ticks: u32, 
+}
+
+/// 画面の左右の端で反射しながら進んだ場合にゴールラインに達するx座標
+fn predict_landing(
+    position: nalgebra::Point2<f32>, 
+    velocity: nalgebra::Vector2<f32>, 
+    line: f32, 
+    width: f32, 
+) -> Option<f32> {
+    if velocity.y.abs() <= f32::EPSILON { return None }
+    let t = (line - position.y) / velocity.y;
+    if t < 0. { return None }
+    let x = (position.x + velocity.x * t).rem_euclid(width * 2.);
+    Some(if x > width { width * 2. - x } else { x })
+}
+
+/// コンピュータによるパドルの操作
+pub struct Autopilot {
+    player: usize, 
+    skill: AutopilotSkill, 
+    param: AutopilotParam, 
+    rng: rand_pcg::Pcg32, 
+    /// 認識の遅れの分だけ保持したボールの位置と速度
+    observed: VecDeque<Option<(nalgebra::Point2<f32>, nalgebra::Vector2<f32>)>>, 
+    /// ボールが自分のゴールラインに向かっているか
+    approaching: bool, 
+    /// パドルの中心から打ち返す位置までのずれ(誤差を含む)
+    offset: f32, 
+    /// 打ち出す位置
+    serve_x: Option<f32>, 
+    /// ボールが無くなってからのティック数
+    idle: u32, 
+}
+impl Autopilot {
+    pub fn new(player: usize, skill: AutopilotSkill, seed: u64) -> Self { Self {
+        player, 
+        skill, 
+        param: skill.param(), 
+        rng: rand_pcg::Pcg32::seed_from_u64(seed), 
+        observed: VecDeque::new(), 
+        approaching: false, 
+        offset: 0., 
+        serve_x: None, 
+        idle: 0, 
+    }}
+    /// 強さの既定値とは異なる癖の設定
+    pub fn with_param(self, param: AutopilotParam) -> Self { Self { param, ..self } }
+    pub fn player(&self) -> usize { self.player }
+    pub fn skill(&self) -> AutopilotSkill { self.skill }
+    pub fn set_skill(&mut self, skill: AutopilotSkill) {
+        self.skill = skill;
+        self.param = skill.param();
+    }
+
+    /// 現在の状況からの操作の決定
+    pub fn control<BF: BrickFeature>(
+        &mut self, 
+        entities: &BreakOutEntities<BF>, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) -> PaddleMoveFlag {
+        let mut flag = PaddleMoveFlag::default();
+        let Some(paddle) = entities.paddles().iter().find(|p| p.player == self.player) else {
+            return flag
+        };
+        self.observed.push_back(entities.ball().map(|b| (b.position(), b.velocity())));
+        while self.observed.len() > self.param.reaction_ticks as usize + 1 {
+            self.observed.pop_front();
+        }
+        let paddle_x = paddle.position().x;
+
+        let target = if entities.ball().is_none() {
+            // 打ち出す位置を決めて移動し、少し待ってから打ち出す
+            self.idle += 1;
+            let margin = paddle.width();
+            let serve_x = *self.serve_x.get_or_insert_with(|| {
+                self.rng.gen_range(margin..(disp_size.x - margin).max(margin + 1.))
+            });
+            if (serve_x - paddle_x).abs() < PADDLE_SPEED && self.idle >= self.param.serve_ticks {
+                flag.ball_shot = true;
+                self.serve_x = None;
+                self.idle = 0;
+            }
+            serve_x
+        } else if let Some(Some((position, velocity))) = self.observed.front().copied() {
+            self.idle = 0;
+            let approaching = match paddle.goal {
+                Goal::Bottom => velocity.y < 0., 
+                Goal::Top => velocity.y > 0., 
+            };
+            if approaching && !self.approaching {
+                // 打ち返すたびに狙いを変える
+                let aim = self.rng.gen_range(-AIM_RANGE..=AIM_RANGE) * paddle.width();
+                let error = match self.param.aim_error {
+                    e if e > 0. => self.rng.gen_range(-e..=e), 
+                    _ => 0., 
+                };
+                self.offset = aim + error;
+            }
+            self.approaching = approaching;
+            if approaching {
+                predict_landing(position, velocity, paddle.position().y, disp_size.x)
+                    .map_or(position.x, |x| x - self.offset)
+            } else {
+                // 離れていくボールは緩やかに追う
+                (position.x + disp_size.x / 2.) / 2.
+            }
+        } else {
+            paddle_x
+        };
+
+        let dx = target - paddle_x;
+        flag.move_right = dx > PADDLE_SPEED / 2.;
+        flag.move_left = dx < -PADDLE_SPEED / 2.;
+        flag
+    }
+}
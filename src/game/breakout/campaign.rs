@@ -0,0 +1,137 @@
+//! キャンペーン(複数ステージの連続プレイ)
+
+use std::{path::{Path, PathBuf}, sync::Arc};
+
+use serde::{Serialize, Deserialize};
+
+use super::level::{LevelDef, fnv1a};
+use super::super::util::persist;
+use super::super::highscore::{GameMode, ScoreTarget};
+
+/// 組み込みのキャンペーンの定義ファイル
+pub const DEFAULT_CAMPAIGN: &str = "./levels/campaign.ron";
+
+/// キャンペーンの定義ファイルの形式
+#[derive(Serialize, Deserialize)]
+struct CampaignFile {
+    name: String, 
+    /// 定義ファイルからの相対パスで記述したステージの並び
+    stages: Vec<PathBuf>, 
+}
+
+/// キャンペーンの定義
+pub struct CampaignDef {
+    pub name: String, 
+    pub stages: Vec<LevelDef>, 
+    /// ステージパックによる効果音の差し替え
+    pub sfx: crate::sfx::SfxTheme, 
+}
+impl CampaignDef {
+    /// 定義ファイルと各ステージの読み込み
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file: CampaignFile = ron::from_str(&std::fs::read_to_string(path)?)?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let stages = file.stages.iter()
+            .map(|s| LevelDef::load(dir.join(s))
+                .map_err(|e| anyhow::anyhow!("{0}: {e}", s.display()))
+            )
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if stages.is_empty() {
+            anyhow::bail!("campaign {0} has no stages", file.name);
+        }
+        Ok(Self {
+            name: file.name, 
+            stages, 
+            sfx: Default::default(), 
+        })
+    }
+
+    /// 全ステージの配置から求めたハッシュ値
+    pub fn hash(&self) -> u64 {
+        let hashes = self.stages.iter()
+            .flat_map(|s| s.hash().to_le_bytes())
+            .collect::<Vec<_>>();
+        fnv1a(&hashes)
+    }
+
+    /// ハイスコアの記録先
+    pub fn score_target(&self, mode: GameMode) -> ScoreTarget {
+        ScoreTarget {
+            mode, 
+            level_name: self.name.clone(), 
+            level_hash: self.hash(), 
+        }
+    }
+}
+
+/// 進行中のキャンペーン
+#[derive(Clone)]
+pub struct CampaignRun {
+    pub def: Arc<CampaignDef>, 
+    pub stage: usize, 
+}
+impl CampaignRun {
+    pub fn new(def: Arc<CampaignDef>, stage: usize) -> Self { Self {
+        stage: stage.min(def.stages.len() - 1), 
+        def, 
+    }}
+    pub fn level(&self) -> &LevelDef { &self.def.stages[self.stage] }
+    pub fn is_last(&self) -> bool { self.stage + 1 >= self.def.stages.len() }
+    /// 次のステージ
+    pub fn next(&self) -> Option<Self> {
+        if self.is_last() { None } else { Some(Self {
+            def: Arc::clone(&self.def), 
+            stage: self.stage + 1, 
+        })}
+    }
+}
+
+/// キャンペーンの進行状況
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CampaignProgress {
+    pub campaign_hash: u64, 
+    /// 到達したステージ
+    pub stage: usize, 
+    /// ステージ開始時のスコア
+    pub score: u64, 
+    /// ステージ開始時の残弾数
+    pub remain_ball: u32, 
+}
+
+/// キャンペーンの進行状況の記録
+#[derive(Default, Serialize, Deserialize)]
+pub struct CampaignProgressStore {
+    entries: Vec<CampaignProgress>, 
+    #[serde(skip)]
+    path: PathBuf, 
+}
+impl CampaignProgressStore {
+    /// ユーザーデータのディレクトリから読み込む
+    pub fn load() -> Self {
+        let path = persist::data_dir().join("campaign.ron");
+        let mut store: Self = persist::load_or_recover(&path);
+        store.path = path;
+        store
+    }
+
+    /// 記録の保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save_atomic(&self.path, self)
+    }
+
+    pub fn get(&self, campaign_hash: u64) -> Option<&CampaignProgress> {
+        self.entries.iter().find(|e| e.campaign_hash == campaign_hash)
+    }
+
+    /// 進行状況の更新
+    pub fn set(&mut self, progress: CampaignProgress) {
+        self.clear(progress.campaign_hash);
+        self.entries.push(progress);
+    }
+
+    /// 進行状況の消去
+    pub fn clear(&mut self, campaign_hash: u64) {
+        self.entries.retain(|e| e.campaign_hash != campaign_hash);
+    }
+}
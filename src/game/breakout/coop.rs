@@ -0,0 +1,32 @@
+//! 2人協力
+//!
+//! 同じ高さに幅の狭いパドルを2つ置き、残弾を共有してステージをクリアする。
+//! 1Pはキーボード(AD, Space)、2Pはマウスで操作する。
+
+use winit::event::VirtualKeyCode;
+
+use super::entities::paddle::{Paddle, Goal, PaddleInput, PADDLE_LINE};
+
+/// 共有する残弾数
+pub const COOP_BALLS: u32 = 5;
+
+/// 1人用と比べたパドルの幅の倍率
+pub const COOP_PADDLE_SCALE: f32 = 0.75;
+
+/// 2Pのパドルの色
+pub const P2_COLOR: [f32; 4] = [0.5, 0.8, 1., 1.];
+
+/// 下端に並べた2つのパドル
+pub fn paddles(disp_size: nalgebra::Vector2<f32>) -> Vec<Paddle> {
+    let mut p1 = Paddle::spawn([disp_size.x / 3., PADDLE_LINE].into(), [1., 1., 1., 1.])
+        .for_player(0, Goal::Bottom, PaddleInput::Keys {
+            left: VirtualKeyCode::A, 
+            right: VirtualKeyCode::D, 
+            shot: VirtualKeyCode::Space, 
+        });
+    let mut p2 = Paddle::spawn([disp_size.x * 2. / 3., PADDLE_LINE].into(), P2_COLOR)
+        .for_player(1, Goal::Bottom, PaddleInput::Mouse);
+    p1.width_scale = COOP_PADDLE_SCALE;
+    p2.width_scale = COOP_PADDLE_SCALE;
+    vec![p1, p2]
+}
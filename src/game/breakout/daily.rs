@@ -0,0 +1,154 @@
+//! 日替わりのチャレンジステージ
+//!
+//! その日の日付からシード値を求めるため、同じ日であれば誰でも同じ配置になる。
+//! 記録に残るのは1日1回の挑戦のみで、2回目以降は練習として扱う。
+
+use std::path::PathBuf;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Deserialize};
+
+use super::level::{LevelDef, fnv1a, generator::{self, GeneratorParam, Pattern, Symmetry}};
+use super::super::util::persist;
+use super::super::highscore::{GameMode, ScoreTarget};
+
+/// 日替わりのステージ
+pub struct DailyChallenge {
+    pub date: NaiveDate, 
+    pub level: LevelDef, 
+}
+impl DailyChallenge {
+    /// 指定した日のステージの生成
+    pub fn new(date: NaiveDate) -> Self {
+        let seed = Self::seed(date);
+        let mut level = generator::generate(seed, &Self::param(date, seed));
+        level.name = format!("Daily {0}", date.format("%Y-%m-%d"));
+        Self { date, level }
+    }
+
+    /// 今日のステージ
+    pub fn today() -> Self { Self::new(chrono::Local::now().date_naive()) }
+
+    /// 日付から求めたシード値
+    pub fn seed(date: NaiveDate) -> u64 {
+        fnv1a(date.format("%Y-%m-%d").to_string().as_bytes())
+    }
+
+    /// 生成のパラメータ
+    ///
+    /// 形状と対称性はシード値から選び、難易度は週の後半ほど高くする。
+    fn param(date: NaiveDate, seed: u64) -> GeneratorParam {
+        let weekday = date.weekday().num_days_from_monday();
+        GeneratorParam {
+            column: 6 + (seed % 3) as u32, 
+            symmetry: match (seed >> 8) % 3 {
+                0 => Symmetry::None, 
+                1 => Symmetry::Mirror, 
+                _ => Symmetry::Rotational, 
+            }, 
+            pattern: match (seed >> 16) % 3 {
+                0 => Pattern::Random, 
+                1 => Pattern::Pyramid, 
+                _ => Pattern::Checkerboard { cell: 1 + ((seed >> 24) % 2) as u32 }, 
+            }, 
+            difficulty: Some(0.2 + weekday as f32 * 0.05), 
+            ..Default::default()
+        }
+    }
+
+    /// ハイスコアの記録先
+    ///
+    /// 日付ごとに配置が異なるため、日付ごとに別の表となる。
+    pub fn score_target(&self) -> ScoreTarget {
+        ScoreTarget::new(GameMode::Daily, &self.level)
+    }
+}
+
+/// 日替わりステージへの挑戦の記録
+#[derive(Default, Serialize, Deserialize)]
+pub struct DailyStore {
+    /// 最後に挑戦した日
+    last_date: Option<NaiveDate>, 
+    /// 連続で挑戦した日数
+    streak: u32, 
+    /// 連続で挑戦した日数の最高記録
+    best_streak: u32, 
+    #[serde(skip)]
+    path: PathBuf, 
+}
+impl DailyStore {
+    /// ユーザーデータのディレクトリから読み込む
+    pub fn load() -> Self {
+        let path = persist::data_dir().join("daily.ron");
+        let mut store: Self = persist::load_or_recover(&path);
+        store.path = path;
+        store
+    }
+
+    /// 記録の保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save_atomic(&self.path, self)
+    }
+
+    /// 指定した日に既に挑戦したか
+    pub fn attempted(&self, date: NaiveDate) -> bool {
+        self.last_date == Some(date)
+    }
+
+    /// 指定した日の時点で続いている連続日数
+    pub fn streak(&self, date: NaiveDate) -> u32 {
+        match self.last_date {
+            Some(last) if last == date || last.succ_opt() == Some(date) => self.streak, 
+            _ => 0, 
+        }
+    }
+
+    pub fn best_streak(&self) -> u32 { self.best_streak }
+
+    /// 挑戦の記録
+    ///
+    /// 記録に残る挑戦であれば`true`を返す。
+    pub fn attempt(&mut self, date: NaiveDate) -> bool {
+        if self.attempted(date) { return false }
+        self.streak = self.streak(date) + 1;
+        self.best_streak = self.best_streak.max(self.streak);
+        self.last_date = Some(date);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate { NaiveDate::from_ymd_opt(y, m, d).unwrap() }
+
+    #[test]
+    fn seed_is_derived_from_the_date_only() {
+        // 日付の文字列のFNV-1aで、実行環境によらず固定の値になる
+        assert_eq!(DailyChallenge::seed(date(2024, 1, 1)), 0x3763_7d7a_01d6_21a9);
+        assert_ne!(DailyChallenge::seed(date(2024, 1, 1)), DailyChallenge::seed(date(2024, 1, 2)));
+    }
+
+    #[test]
+    fn same_date_gives_same_level_and_table() {
+        let (a, b) = (DailyChallenge::new(date(2024, 3, 5)), DailyChallenge::new(date(2024, 3, 5)));
+        assert_eq!(a.level, b.level);
+        assert_eq!(a.level.name, "Daily 2024-03-05");
+        let other = DailyChallenge::new(date(2024, 3, 6));
+        assert_ne!(a.score_target().level_hash, other.score_target().level_hash);
+    }
+
+    #[test]
+    fn streak_counts_consecutive_days() {
+        let mut store = DailyStore::default();
+        assert!(store.attempt(date(2024, 1, 1)));
+        assert!(!store.attempt(date(2024, 1, 1)));
+        assert!(store.attempt(date(2024, 1, 2)));
+        assert_eq!(store.streak(date(2024, 1, 3)), 2);
+        assert_eq!(store.streak(date(2024, 1, 4)), 0);
+        assert!(store.attempt(date(2024, 1, 5)));
+        assert_eq!(store.streak(date(2024, 1, 5)), 1);
+        assert_eq!(store.best_streak(), 2);
+    }
+}
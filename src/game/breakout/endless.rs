@@ -0,0 +1,249 @@
+//! エンドレスモード
+//!
+//! ブロックの列が時間と共に下がり続け、上から新しい行が生成され続ける。
+//! ブロックがパドルの高さまで降りてきたらゲームオーバーとなる。
+
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, distributions::{Distribution, WeightedIndex}};
+use serde::{Serialize, Deserialize};
+
+use super::entities::brick::{Brick, BrickRow, BrickCollection, BrickSpawnParam, BrickSpawnFn};
+use super::level::{BrickDef, BrickType, BrickFeature, fnv1a};
+use super::state::{BreakOutGameState, GameState};
+use super::super::highscore::{GameMode, ScoreTarget, ScoreDetail};
+
+/// エンドレスモードの設定
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndlessParam {
+    /// 1行あたりのブロック数
+    pub row: u32, 
+    /// 開始時の行数
+    pub initial_rows: u32, 
+    pub margin_top: f32, 
+    pub brick_margin: [f32; 2], 
+    pub brick_size: [f32; 2], 
+    /// 開始時の降下速度[px/tick]
+    pub speed: f32, 
+    /// 降下した距離あたりの速度の上昇量
+    pub accel: f32, 
+    /// 降下速度の上限[px/tick]
+    pub max_speed: f32, 
+    /// ブロックの密度(開始時, 最大)
+    pub density: [f32; 2], 
+    /// 密度が最大になるまでの行数
+    pub density_rows: u32, 
+    /// ブロックの種類ごとの出現の重み(Normal, Upper, Top)
+    pub weights: [u32; 3], 
+    /// 1行生き延びるごとのボーナス
+    pub row_bonus: u64, 
+}
+impl Default for EndlessParam {
+    fn default() -> Self { Self {
+        row: 20, 
+        initial_rows: 5, 
+        margin_top: 48., 
+        brick_margin: [4., 4.], 
+        brick_size: [24., 12.], 
+        speed: 0.05, 
+        accel: 0.00002, 
+        max_speed: 0.4, 
+        density: [0.5, 0.9], 
+        density_rows: 60, 
+        weights: [70, 20, 10], 
+        row_bonus: 50, 
+    }}
+}
+
+/// シード値から行を生成する生成器
+pub struct RowGenerator {
+    rng: rand_pcg::Pcg32, 
+    param: EndlessParam, 
+}
+impl RowGenerator {
+    pub fn new(seed: u64, param: EndlessParam) -> Self { Self {
+        rng: rand_pcg::Pcg32::seed_from_u64(seed), 
+        param, 
+    }}
+
+    /// `index`行目を生成する
+    pub fn row(&mut self, index: u32) -> Vec<Option<BrickDef>> {
+        let t = (index as f32 / self.param.density_rows.max(1) as f32).min(1.);
+        let [d0, d1] = self.param.density;
+        let density = d0 + (d1 - d0) * t;
+        let types = WeightedIndex::new(self.param.weights).ok();
+        (0..self.param.row).map(|x| {
+            if self.rng.gen::<f32>() >= density { return None }
+            let blk_type = match types.as_ref().map(|t| t.sample(&mut self.rng)) {
+                Some(1) => BrickType::Upper, 
+                Some(2) => BrickType::Top, 
+                _ => BrickType::Normal, 
+            };
+            let wave = ((index as f32 * 0.3) + x as f32 * 0.2).sin() * 0.2;
+            Some(BrickDef {
+                score: match blk_type {
+                    BrickType::Normal => 100, 
+                    BrickType::Upper => 200, 
+                    BrickType::Top => 300, 
+                    BrickType::Solid => 0, 
+                } + index as u64 * 10, 
+                blk_type, 
+                color: match blk_type {
+                    BrickType::Normal => [0.3 + wave, 0.8, 0.4 - wave, 1.], 
+                    BrickType::Upper => [0.9, 0.7 + wave, 0.2, 1.], 
+                    BrickType::Top => [0.9, 0.2, 0.3 + wave, 1.], 
+                    BrickType::Solid => [0.5, 0.5, 0.5, 1.], 
+                }, 
+                script: None, 
+                shape: Default::default(), 
+                angle: 0., 
+                motion: None, 
+            })
+        }).collect()
+    }
+}
+
+/// エンドレスモードの進行
+pub struct Endless<BF: super::entities::brick::brick::BrickFeature> {
+    param: EndlessParam, 
+    seed: u64, 
+    spawn_f: Arc<Mutex<BrickSpawnFn<BF>>>, 
+    /// 次に生成する行の番号
+    next_row: u32, 
+    /// 最も上の行の高さ
+    top_y: f32, 
+    /// 最後に行を生成してから降下した距離
+    scroll: f32, 
+    /// 降下した距離の合計
+    distance: f32, 
+    /// 生き延びた行数
+    rows: u32, 
+}
+impl Endless<BrickFeature> {
+    pub fn new(
+        seed: u64, 
+        param: EndlessParam, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) -> Self {
+        let mut generator = RowGenerator::new(seed, param.clone());
+        let mut cache: (Option<u32>, Vec<Option<BrickDef>>) = (None, Vec::new());
+        let spawn_f: BrickSpawnFn<BrickFeature> = Box::new(move |
+            pos: [u32; 2], 
+            blk_pos: nalgebra::Point2<f32>, 
+            blk_size: nalgebra::Vector2<f32>, 
+        | {
+            if cache.0 != Some(pos[1]) {
+                cache = (Some(pos[1]), generator.row(pos[1]));
+            }
+            cache.1.get(pos[0] as usize)
+                .and_then(|b| b.as_ref())
+                .map(|b| Brick::spawn(
+                    BrickFeature::new(b.score, b.blk_type), 
+                    blk_pos, 
+                    blk_size, 
+                    b.color, 
+                ))
+        });
+        Self {
+            top_y: disp_size.y - param.margin_top - param.brick_size[1] / 2., 
+            next_row: param.initial_rows, 
+            param, 
+            seed, 
+            spawn_f: Arc::new(Mutex::new(spawn_f)), 
+            scroll: 0., 
+            distance: 0., 
+            rows: 0, 
+        }
+    }
+}
+impl<BF: super::entities::brick::brick::BrickFeature> Endless<BF> {
+    /// 開始時のブロックのスポーン用パラメータ
+    pub fn spawn_param(&self) -> BrickSpawnParam<
+        [f32; 2], 
+        [f32; 2], 
+        BrickSpawnFn<BF>, 
+        BF, 
+    > {
+        BrickSpawnParam {
+            column: self.param.initial_rows, 
+            row: self.param.row, 
+            margin_top: self.param.margin_top, 
+            brick_margin: self.param.brick_margin, 
+            brick_size: self.param.brick_size, 
+            spawn_f: Arc::clone(&self.spawn_f), 
+            obstacles: Vec::new(), 
+            enemies: Vec::new(), 
+            boss: None, 
+        }
+    }
+
+    pub fn seed(&self) -> u64 { self.seed }
+    pub fn distance(&self) -> f32 { self.distance }
+    pub fn rows(&self) -> u32 { self.rows }
+
+    /// ハイスコアの記録先
+    ///
+    /// シード値は記録先に含めず、設定が同じであれば同じ表に記録する。
+    pub fn score_target(&self) -> ScoreTarget {
+        let param = ron::to_string(&self.param).expect("endless param serialize");
+        ScoreTarget {
+            mode: GameMode::Endless, 
+            level_name: "Endless".into(), 
+            level_hash: fnv1a(param.as_bytes()), 
+        }
+    }
+
+    /// ハイスコアに添える記録
+    pub fn detail(&self) -> ScoreDetail {
+        ScoreDetail::Endless {
+            rows: self.rows, 
+            distance: self.distance as u32, 
+        }
+    }
+
+    /// ブロックの降下と新しい行の生成
+    pub fn update(
+        &mut self, 
+        bricks: &mut BrickCollection<BF>, 
+        state: &mut BreakOutGameState, 
+        disp_size: nalgebra::Vector2<f32>, 
+        paddle_line: f32, 
+    ) {
+        if state.state != GameState::Yes { return }
+        let pitch = self.param.brick_size[1] + self.param.brick_margin[1];
+        let mut step = (self.param.speed + self.distance * self.param.accel)
+            .min(self.param.max_speed);
+        // ブロックが無くなったらすぐに次の行を降ろす
+        if bricks.get().count() == 0 {
+            step = step.max(pitch - self.scroll);
+        }
+
+        bricks.get_mut().shift([0., -step].into());
+        self.distance += step;
+        self.scroll += step;
+        self.top_y -= step;
+
+        while self.scroll >= pitch {
+            self.scroll -= pitch;
+            self.top_y += pitch;
+            bricks.get_mut().push_row(BrickRow::spawn(
+                self.param.row, 
+                self.next_row, 
+                self.top_y, 
+                self.param.brick_margin[0], 
+                self.param.brick_size.into(), 
+                disp_size, 
+                Arc::clone(&self.spawn_f), 
+            ));
+            self.next_row += 1;
+            self.rows += 1;
+            *state.score.lock() += self.param.row_bonus;
+        }
+        bricks.get_mut().remove_empty_rows();
+
+        if bricks.get().bottom().is_some_and(|b| b <= paddle_line) {
+            state.state = GameState::GameOver;
+        }
+    }
+}
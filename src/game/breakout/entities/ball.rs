@@ -1,8 +1,22 @@
+/// バンパーによる加速の1ティックあたりの減衰率
+const BOOST_DECAY: f32 = 0.98;
+
+/// バンパーによる加速の上限
+const MAX_BOOST: f32 = 4.;
+
+/// ボールの大きさ
+pub const BALL_SIZE: f32 = 6.;
+
 /// ボール
+#[derive(Clone)]
 pub struct Ball {
     pub(super) model: super::Instance, 
     pub(super) angle: nalgebra::Vector2<f32>, 
     pub(super) speed: f32, 
+    /// 最後に触れたパドルのプレイヤー
+    pub(super) owner: usize, 
+    /// バンパーによる難易度ごとの速さへの上乗せ
+    pub(super) boost: f32, 
 }
 impl Ball {
     pub fn spawn(
@@ -13,13 +27,43 @@ impl Ball {
     ) -> Self { Self {
         model: super::Instance {
             position,
-            size: nalgebra::Vector2::new(6., 6.),
+            size: nalgebra::Vector2::new(BALL_SIZE, BALL_SIZE),
             angle: 0.,
             color,
+            shape: super::Shape::Rect,
         },
         angle,
         speed,
+        owner: 0, 
+        boost: 0., 
     } }
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    pub fn model(&self) -> &super::Instance { &self.model }
+    /// 1ティックあたりの移動量
+    pub fn velocity(&self) -> nalgebra::Vector2<f32> { self.angle.normalize() * self.speed }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::BallSave {
+        super::super::save::BallSave {
+            position: self.model.position.into(), 
+            color: self.model.color, 
+            angle: self.angle.into(), 
+            speed: self.speed, 
+            owner: self.owner, 
+            boost: self.boost, 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::super::save::BallSave) -> Self {
+        let mut ball = Self::spawn(
+            save.position.into(), 
+            save.color, 
+            save.angle.into(), 
+            save.speed, 
+        );
+        ball.owner = save.owner;
+        ball.boost = save.boost;
+        ball
+    }
     pub fn update(
         &mut self, 
         state: &super::super::state::BreakOutGameState, 
@@ -30,6 +74,8 @@ impl Ball {
             crate::game::breakout::state::BreakOutDifficulity::Normal => self.speed = 300. / 60.,
             crate::game::breakout::state::BreakOutDifficulity::Hard => self.speed = 350. / 60.,
         }
+        self.speed += self.boost;
+        self.boost *= BOOST_DECAY;
         match state.state {
             super::super::state::GameState::Yes => {},
             super::super::state::GameState::GameOver => self.model.color = [1., 0., 0., 0.],
@@ -45,10 +91,14 @@ impl Ball {
             _ => {}
         }
     }
+    /// 画面端での反射
+    ///
+    /// `goals`に含まれる端はゴールラインとして扱い、反射しない。
     pub fn refle_edge(
         &mut self, 
         disp_size: nalgebra::Vector2<f32>, 
-        sfx_ctx: &crate::sfx::SfxModule
+        goals: &[super::paddle::Goal], 
+        sfx_ctx: &dyn crate::sfx::SfxSink
     ) {
         let wvx = if 
             disp_size.x <= self.model.position.x 
@@ -65,8 +115,15 @@ impl Ball {
         let wvy = if 
             disp_size.y <= self.model.position.y 
             && self.angle.y.is_sign_positive()
+            && !goals.contains(&super::paddle::Goal::Top)
         {
             Some(nalgebra::Vector2::new(0., -1.))
+        } else if 
+            self.model.position.y <= 0. 
+            && self.angle.y.is_sign_negative()
+            && !goals.contains(&super::paddle::Goal::Bottom)
+        {
+            Some(nalgebra::Vector2::new(0., 1.))
         } else {
             None
         };
@@ -78,14 +135,14 @@ impl Ball {
         };
         let d = -self.angle.dot(&wv);
         self.angle += (d * wv) * 2.;
-        sfx_ctx.play_resource("reflection", |r| r);
+        sfx_ctx.play_effect("reflection");
     }
     pub fn refle_paddle(
         &mut self, 
         paddle: &super::paddle::Paddle, 
         pointer: &mut super::pointer::Pointer, 
-        sfx_ctx: &crate::sfx::SfxModule, 
-    ) {
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) -> bool {
         let a = &self.model.position;
         let b = self.model.position + self.angle * self.speed;
         let paddle_half_width = nalgebra::Vector2::new(paddle.model.size.x / 2., 0.);
@@ -96,7 +153,7 @@ impl Ball {
         let bb = (b.x - a.x) * (d.y - c.y) - (b.y - a.y) * (d.x - c.x);
 
         // BBがほぼゼロの場合はパドルに当たらない
-        if bb.abs() <= std::f32::EPSILON { return }
+        if bb.abs() <= std::f32::EPSILON { return false }
 
         let r = ((d.y - c.y) * ac.x - (d.x - c.x) * ac.y) / bb;
         let s = ((b.y - a.y) * ac.x - (b.x - a.x) * ac.y) / bb;
@@ -106,8 +163,6 @@ impl Ball {
             let cd = (d - c) * s;
             pointer.model.position = c + cd;
             pointer.visible = true;
-        } else {
-            pointer.visible = false;
         }
 
         // 接触
@@ -130,45 +185,111 @@ impl Ball {
             )
                 .normalize();
             self.angle = nv;
-            sfx_ctx.play_resource("reflection", |r| r);
+            self.owner = paddle.player;
+            sfx_ctx.play_effect("reflection");
+            true
+        } else {
+            false
         }
     }
     pub fn refle_brick<BF: super::brick::brick::BrickFeature>(
         &mut self, 
         brick: &mut super::brick::BrickColumn<BF>, 
         state: &mut super::super::state::BreakOutGameState, 
-        sfx_ctx: &crate::sfx::SfxModule, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
     ) {
-        if let Some(rv) = brick.collision(self, state)
-            .map(|r| match r {
-                super::brick::BBCollisionPoint::Top => if self.angle.y.is_sign_negative() {
-                    Some(nalgebra::Vector2::new(0., 1.))
-                } else { None },
-                super::brick::BBCollisionPoint::Bottom => if self.angle.y.is_sign_positive() {
-                    Some(nalgebra::Vector2::new(0., -1.))
-                } else { None },
-                super::brick::BBCollisionPoint::Left => if self.angle.x.is_sign_positive() {
-                    Some(nalgebra::Vector2::new(-1., 0.))
-                } else { None },
-                super::brick::BBCollisionPoint::Right => if self.angle.x.is_sign_negative() {
-                    Some(nalgebra::Vector2::new(1., 0.))
-                } else { None }
-            })
-            .flatten()
-        {
-            sfx_ctx.play_resource("break", |r|r);
-            let d = -self.angle.dot(&rv);
-            self.angle += (d * rv) * 2.;
-        }
+        let Some(contact) = brick.collision(self, state) else { return };
+        if self.reflect(&contact) { sfx_ctx.play_effect("break") }
     }
-    pub fn despawnable(&mut self, sfx_ctx: &crate::sfx::SfxModule) -> bool { 
-        match self.model.position.y.is_sign_negative() {
-            a @ true => {
-                sfx_ctx.play_resource("miss", |r| r);
-                a
-            },
-            f @ _ => f,
+    /// 敵での反射
+    ///
+    /// 倒した敵のスコアを加える。
+    pub fn refle_enemy(
+        &mut self, 
+        enemies: &mut super::enemy::Enemies, 
+        state: &mut super::super::state::BreakOutGameState, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) {
+        let a = self.model.position;
+        let Some((contact, score)) = enemies.hit(a, a + self.velocity()) else { return };
+        sfx_ctx.play_effect("break");
+        if let Some(score) = score { *state.score.lock() += score }
+        self.reflect(&contact);
+    }
+    /// 体力を持つ相手での反射
+    ///
+    /// 弱点に当たった場合は体力を減らし、倒した場合はスコアを加えて`true`を返す。
+    pub fn refle_damageable<D: super::damage::Damageable>(
+        &mut self, 
+        target: &mut D, 
+        state: &mut super::super::state::BreakOutGameState, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) -> bool {
+        let a = self.model.position;
+        let Some(hit) = target.contact(a, a + self.velocity()) else { return false };
+        if !self.reflect(&hit.contact) { return false }
+        if hit.damage == 0 {
+            sfx_ctx.play_effect("reflection");
+            return false
         }
+        sfx_ctx.play_effect("break");
+        if !target.health_mut().damage(hit.damage) { return false }
+        *state.score.lock() += target.score();
+        true
+    }
+    /// 動いている相手からの相対的な速度での反射
+    ///
+    /// 相手に向かっていなかった場合は反射せず`false`を返す。
+    fn reflect(&mut self, contact: &super::brick::BBContact) -> bool {
+        let relative = self.velocity() - contact.velocity;
+        let d = -relative.dot(&contact.normal);
+        if d <= 0. { return false }
+        let reflected = relative + (d * contact.normal) * 2.;
+        // 相手の速度を打ち消して止まる場合は相対的な速度の向きとする
+        let moved = reflected + contact.velocity;
+        self.angle = if moved.norm() <= f32::EPSILON { reflected.normalize() }
+            else { moved.normalize() };
+        true
+    }
+    /// 障害物での反射
+    ///
+    /// 最も手前で接触した障害物で反射し、バンパーの場合は加速する。
+    pub fn refle_obstacle(
+        &mut self, 
+        obstacles: &[super::obstacle::Obstacle], 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) {
+        let (a, b) = (self.model.position, self.model.position + self.velocity());
+        let Some((obstacle, normal)) = obstacles.iter()
+            .filter_map(|o| o.sweep(a, b).map(|(r, n)| (r, o, n)))
+            .min_by(|x, y| x.0.total_cmp(&y.0))
+            .map(|(_, o, n)| (o, n))
+        else { return };
+        let d = -self.angle.normalize().dot(&normal);
+        if d <= 0. { return }
+        self.angle = (self.angle.normalize() + (d * normal) * 2.).normalize();
+        self.boost = (self.boost + obstacle.kick()).min(MAX_BOOST);
+        sfx_ctx.play_effect("reflection");
+    }
+    /// ゴールラインを越えたか
+    ///
+    /// 越えた場合はそのゴールラインを返す。
+    pub fn out_of_goal(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        goals: &[super::paddle::Goal], 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) -> Option<super::paddle::Goal> { 
+        let goal = if self.model.position.y.is_sign_negative() {
+            super::paddle::Goal::Bottom
+        } else if disp_size.y < self.model.position.y {
+            super::paddle::Goal::Top
+        } else {
+            return None
+        };
+        if !goals.contains(&goal) { return None }
+        sfx_ctx.play_effect("miss");
+        Some(goal)
     }
 }
 impl super::AsInstance for Ball {
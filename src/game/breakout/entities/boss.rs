@@ -0,0 +1,340 @@
+//! 体力が尽きるまで動きと攻撃を変えながら戦うボス
+
+use std::sync::Arc;
+
+use serde::{Serialize, Deserialize};
+
+use super::TICKS_PER_SEC;
+use super::brick::motion::{Motion, BrickMotion};
+use super::damage::{Damageable, DamageContact, Health};
+use super::super::obj_renderer::model::sweep_circle;
+
+/// 弱点の色
+const WEAK_POINT_COLOR: [f32; 4] = [1., 0.9, 0.2, 1.];
+
+/// 弾の大きさ
+const PROJECTILE_SIZE: [f32; 2] = [8., 8.];
+
+fn one() -> u32 { 1 }
+
+/// 弱点
+///
+/// ボールが当たると`damage`だけ体力が減る。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WeakPoint {
+    /// 回転前の体の中心からの位置
+    pub offset: [f32; 2], 
+    pub radius: f32, 
+    #[serde(default = "one")]
+    pub damage: u32, 
+}
+
+/// 弾がパドルに当たった時の効果
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectileEffect {
+    /// パドルを縮める
+    Shrink, 
+    /// パドルを動けなくする
+    Stun, 
+}
+impl ProjectileEffect {
+    fn color(&self) -> [f32; 4] { match self {
+        Self::Shrink => [1., 0.4, 0.1, 1.], 
+        Self::Stun => [0.4, 0.8, 1., 1.], 
+    }}
+}
+
+/// パドルを狙って弾を撃つ攻撃
+///
+/// 時間は秒、速さは1秒あたりのピクセルで指定する。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BossAttack {
+    /// 撃つ間隔
+    pub every: f32, 
+    pub effect: ProjectileEffect, 
+    /// 効果の続く時間
+    pub duration: f32, 
+    pub speed: f32, 
+    /// 一度に撃つ数
+    #[serde(default = "one")]
+    pub count: u32, 
+    /// 狙った向きを中心に広げる弾同士の角度(ラジアン)
+    #[serde(default)]
+    pub spread: f32, 
+}
+
+/// 動きと攻撃の段階
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BossPhase {
+    /// 体力の割合がこの値以下になると移る(最初の段階は1.0)
+    pub below: f32, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<Motion>, 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attacks: Vec<BossAttack>, 
+}
+
+/// ボスの定義
+///
+/// 位置は画面の左下を原点としたピクセルで指定する。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BossDef {
+    pub hp: u32, 
+    pub position: [f32; 2], 
+    pub size: [f32; 2], 
+    pub color: [f32; 4], 
+    /// 倒した時のスコア
+    pub score: u64, 
+    pub weak_points: Vec<WeakPoint>, 
+    /// 体力の多い順の段階
+    pub phases: Vec<BossPhase>, 
+}
+
+/// ボスの撃った弾
+#[derive(Clone)]
+pub struct Projectile {
+    model: super::Instance, 
+    velocity: nalgebra::Vector2<f32>, 
+    effect: ProjectileEffect, 
+    /// 効果の続くティック数
+    ticks: u32, 
+}
+impl Projectile {
+    pub fn spawn(
+        position: nalgebra::Point2<f32>, 
+        velocity: nalgebra::Vector2<f32>, 
+        effect: ProjectileEffect, 
+        ticks: u32, 
+    ) -> Self { Self {
+        model: super::Instance {
+            position, 
+            size: PROJECTILE_SIZE.into(), 
+            angle: 0., 
+            color: effect.color(), 
+            shape: super::Shape::Circle, 
+        }, 
+        velocity, 
+        effect, 
+        ticks, 
+    }}
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    /// 移動
+    ///
+    /// 画面の外に出た場合は`false`を返す。
+    fn fly(&mut self, disp_size: nalgebra::Vector2<f32>) -> bool {
+        self.model.position += self.velocity;
+        let (p, s) = (self.model.position, self.model.size);
+        -s.x < p.x && p.x < disp_size.x + s.x && -s.y < p.y && p.y < disp_size.y + s.y
+    }
+    /// パドルへの効果
+    fn apply(&self, paddle: &mut super::paddle::Paddle) { match self.effect {
+        ProjectileEffect::Shrink => paddle.shrink(self.ticks), 
+        ProjectileEffect::Stun => paddle.stun(self.ticks), 
+    }}
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::ProjectileSave {
+        super::super::save::ProjectileSave {
+            position: self.model.position.into(), 
+            velocity: self.velocity.into(), 
+            effect: self.effect, 
+            ticks: self.ticks, 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::super::save::ProjectileSave) -> Self {
+        Self::spawn(save.position.into(), save.velocity.into(), save.effect, save.ticks)
+    }
+}
+
+/// ボス
+#[derive(Clone)]
+pub struct Boss {
+    def: Arc<BossDef>, 
+    model: super::Instance, 
+    health: Health, 
+    /// 現在の段階の番号
+    phase: usize, 
+    motion: Option<BrickMotion>, 
+    /// 直前のティックでの移動量
+    velocity: nalgebra::Vector2<f32>, 
+    /// 攻撃ごとの次に撃つまでのティック数
+    cooldowns: Vec<u32>, 
+    projectiles: Vec<Projectile>, 
+}
+impl Boss {
+    pub fn spawn(def: Arc<BossDef>) -> Self {
+        let mut boss = Self {
+            model: super::Instance {
+                position: def.position.into(), 
+                size: def.size.into(), 
+                angle: 0., 
+                color: def.color, 
+                shape: super::Shape::Rect, 
+            }, 
+            health: Health::new(def.hp), 
+            phase: 0, 
+            motion: None, 
+            velocity: nalgebra::Vector2::zeros(), 
+            cooldowns: Vec::new(), 
+            projectiles: Vec::new(), 
+            def, 
+        };
+        boss.enter_phase(0);
+        boss
+    }
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    pub fn phase(&self) -> usize { self.phase }
+    pub fn projectiles(&self) -> &[Projectile] { &self.projectiles }
+    /// 段階の切り替え
+    ///
+    /// 動きは現在の位置を基準にやり直し、攻撃は間隔を空けてから撃ち始める。
+    fn enter_phase(&mut self, phase: usize) {
+        self.phase = phase;
+        let Some(def) = self.def.phases.get(phase) else {
+            self.motion = None;
+            self.cooldowns.clear();
+            return
+        };
+        let p = self.model.position;
+        self.motion = def.motion.map(|m| BrickMotion::new(m, p, p, self.model.angle));
+        self.cooldowns = def.attacks.iter()
+            .map(|a| ((a.every * TICKS_PER_SEC).round() as u32).max(1))
+            .collect();
+    }
+    /// 体力の割合に応じた段階
+    fn current_phase(&self) -> usize {
+        let ratio = self.health.ratio();
+        self.def.phases.iter().rposition(|p| ratio <= p.below).unwrap_or(0)
+    }
+    /// 弱点の中心
+    fn weak_point_center(&self, weak_point: &WeakPoint) -> nalgebra::Point2<f32> {
+        self.model.position
+            + nalgebra::Rotation2::new(self.model.angle) * nalgebra::Vector2::from(weak_point.offset)
+    }
+    /// 段階の更新、移動と攻撃
+    pub fn tick(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        paddles: &[super::paddle::Paddle], 
+    ) {
+        self.health.tick();
+        let phase = self.current_phase();
+        if phase != self.phase { self.enter_phase(phase) }
+        if let Some(motion) = self.motion.as_mut() {
+            let (position, angle) = motion.advance(disp_size, self.model.size);
+            self.velocity = position - self.model.position;
+            self.model.position = position;
+            self.model.angle = angle;
+        } else {
+            self.velocity = nalgebra::Vector2::zeros();
+        }
+        let origin = self.model.position;
+        // 最も近いパドルを狙う
+        let aim = paddles.iter()
+            .map(|p| p.position() - origin)
+            .min_by(|a, b| a.norm().total_cmp(&b.norm()))
+            .and_then(|d| d.try_normalize(f32::EPSILON))
+            .unwrap_or(nalgebra::Vector2::new(0., -1.));
+        let attacks = self.def.phases.get(self.phase).map(|p| p.attacks.as_slice()).unwrap_or_default();
+        for (attack, cooldown) in attacks.iter().zip(self.cooldowns.iter_mut()) {
+            *cooldown = cooldown.saturating_sub(1);
+            if *cooldown != 0 { continue }
+            *cooldown = ((attack.every * TICKS_PER_SEC).round() as u32).max(1);
+            let ticks = (attack.duration * TICKS_PER_SEC).round() as u32;
+            for i in 0..attack.count {
+                let offset = (i as f32 - (attack.count as f32 - 1.) / 2.) * attack.spread;
+                self.projectiles.push(Projectile::spawn(
+                    origin, 
+                    nalgebra::Rotation2::new(offset) * aim * (attack.speed / TICKS_PER_SEC), 
+                    attack.effect, 
+                    ticks, 
+                ));
+            }
+        }
+        self.projectiles.retain_mut(|p| p.fly(disp_size));
+    }
+    /// パドルに当たった弾の効果
+    pub fn strike(
+        &mut self, 
+        paddles: &mut [super::paddle::Paddle], 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) {
+        self.projectiles.retain(|p| {
+            let Some(paddle) = paddles.iter_mut().find(|paddle| paddle.model().collision_aabb(&p.model))
+            else { return true };
+            sfx_ctx.play_effect("miss");
+            p.apply(paddle);
+            false
+        })
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::BossSave {
+        super::super::save::BossSave {
+            hp: self.health.hp(), 
+            flash: self.health.flash(), 
+            phase: self.phase, 
+            position: self.model.position.into(), 
+            angle: self.model.angle, 
+            motion: self.motion, 
+            cooldowns: self.cooldowns.clone(), 
+            projectiles: self.projectiles.iter().map(|p| p.to_save()).collect(), 
+        }
+    }
+    /// セーブデータからの復元
+    ///
+    /// 定義は現在のステージのものを引き継ぐ。
+    pub fn restore(&mut self, save: &super::super::save::BossSave) {
+        self.health = Health::restore(self.def.hp, save.hp, save.flash);
+        self.phase = save.phase;
+        self.model.position = save.position.into();
+        self.model.angle = save.angle;
+        self.motion = save.motion;
+        self.cooldowns = save.cooldowns.clone();
+        self.projectiles = save.projectiles.iter().map(Projectile::from_save).collect();
+    }
+}
+impl Damageable for Boss {
+    fn health(&self) -> &Health { &self.health }
+    fn health_mut(&mut self) -> &mut Health { &mut self.health }
+    /// 体と弱点のうち最初に当たったもの
+    ///
+    /// 動いているボスからの相対的な移動で判定し、体に当たってもダメージは受けない。
+    fn contact(
+        &self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<DamageContact> {
+        let b = b - self.velocity;
+        let body = self.model.sweep(a, b).map(|(r, n)| (r, n, 0));
+        self.def.weak_points.iter()
+            .filter_map(|w| sweep_circle(self.weak_point_center(w), w.radius, a, b)
+                .map(|(r, n)| (r, n, w.damage)))
+            .chain(body)
+            // 同時に当たった場合は弱点を優先する
+            .min_by(|x, y| x.0.total_cmp(&y.0).then(y.2.cmp(&x.2)))
+            .map(|(at, normal, damage)| DamageContact {
+                at, 
+                contact: super::brick::BBContact { normal, velocity: self.velocity }, 
+                damage, 
+            })
+    }
+    fn score(&self) -> u64 { self.def.score }
+}
+impl super::AsInstance for Boss {
+    fn as_instance(&self, instances: &mut super::RawInstArray) {
+        instances.push(&super::Instance {
+            color: self.health.tint(self.model.color), 
+            ..self.model
+        });
+        for w in self.def.weak_points.iter() {
+            instances.push(&super::Instance {
+                position: self.weak_point_center(w), 
+                size: nalgebra::Vector2::repeat(w.radius * 2.), 
+                angle: 0., 
+                color: self.health.tint(WEAK_POINT_COLOR), 
+                shape: super::Shape::Circle, 
+            });
+        }
+        self.projectiles.iter().for_each(|p| instances.push(&p.model));
+    }
+}
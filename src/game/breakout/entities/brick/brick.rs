@@ -1,4 +1,26 @@
-use super::BBCollisionPoint;
+use super::{BBContact, BrickEffect, motion::BrickMotion};
+
+/// ブロックの振る舞いのきっかけ
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BrickEvent {
+    /// ボールが当たった
+    Hit, 
+    /// 壊れた
+    Destroy, 
+    /// 毎ティック
+    Tick, 
+}
+
+/// 振る舞いを起こしたブロックの状況
+#[derive(Clone, Copy, Debug)]
+pub struct BrickCtx {
+    /// 段の中の位置と段(`[x, y]`)
+    pub cell: [usize; 2], 
+    /// ステージ開始からのティック数
+    pub tick: u64, 
+    /// クリアまでに壊す残りのブロックの数
+    pub remaining: usize, 
+}
 
 /// ブロックの機能実装用トレイト
 pub trait BrickFeature {
@@ -6,12 +28,33 @@ pub trait BrickFeature {
         &self, 
         state: &mut super::super::super::state::BreakOutGameState, 
     );
+    /// 当たると壊れるか
+    ///
+    /// 壊れないブロックはボールを反射するのみで、クリアの条件にも含めない。
+    fn destructible(&self) -> bool { true }
+    /// スクリプトなどによる振る舞い
+    ///
+    /// スコアや難易度は`state`を直接変え、周囲のブロックなどへの作用を返す。
+    fn behave(
+        &self, 
+        _event: BrickEvent, 
+        _ctx: &BrickCtx, 
+        _state: &mut super::super::super::state::BreakOutGameState, 
+    ) -> Vec<BrickEffect> { Vec::new() }
+    /// セーブデータへの書き出し
+    fn save_data(&self) -> anyhow::Result<String>;
+    /// セーブデータからの復元
+    fn load_data(data: &str) -> anyhow::Result<Self> where Self: Sized;
 }
 
 /// ブロック
+#[derive(Clone)]
 pub struct Brick<BF: BrickFeature> {
     feature: BF, 
     model: super::Instance, 
+    motion: Option<BrickMotion>, 
+    /// 直前のティックでの移動量
+    velocity: nalgebra::Vector2<f32>, 
 }
 impl<BF: BrickFeature> Brick<BF> {
     pub fn spawn(
@@ -26,61 +69,83 @@ impl<BF: BrickFeature> Brick<BF> {
             size,
             angle: 0.,
             color,
+            shape: super::Shape::Rect,
         }, 
+        motion: None, 
+        velocity: nalgebra::Vector2::zeros(), 
     } }
+    /// 形と回転角の設定
+    pub fn with_shape(mut self, shape: super::Shape, angle: f32) -> Self {
+        self.model.shape = shape;
+        self.model.angle = angle;
+        self
+    }
+    /// 動きの設定
+    pub fn with_motion(mut self, motion: Option<BrickMotion>) -> Self {
+        self.motion = motion;
+        self
+    }
+    /// 動きによる1ティック分の移動
+    pub fn advance(&mut self, disp_size: nalgebra::Vector2<f32>) {
+        let Some(motion) = self.motion.as_mut() else { return };
+        let (position, angle) = motion.advance(disp_size, self.model.size);
+        self.velocity = position - self.model.position;
+        // 画面の端で反対側に戻った場合は流れる向きの移動とする
+        if self.velocity.x.abs() > disp_size.x / 2. {
+            self.velocity.x -= (disp_size.x + self.model.size.x).copysign(self.velocity.x);
+        }
+        self.model.position = position;
+        self.model.angle = angle;
+    }
+    /// ボールとの接触
+    ///
+    /// ボールの移動をブロックから見た相対的な移動として判定する。
     pub fn collision(
         &self, 
         ball: &super::super::ball::Ball, 
         _state: &mut super::super::super::state::BreakOutGameState
-    ) -> Option<BBCollisionPoint> {
-        let ball_delta = [
+    ) -> Option<BBContact> {
+        let (a, b) = (
             ball.model.position, 
-            ball.model.position + ball.angle * ball.speed, 
-        ];
-
-        {
-            let mut coll_delta = None;
-            let (a, b) = (
-                &ball_delta[0], 
-                &ball_delta[1], 
-            );
-            
-            let mut length = 1.0;
-
-            for (
-                count, brick_delta
-            ) in self.model.edges().into_iter()
-                .enumerate()
-            {
-                let (c, d) = (
-                    &brick_delta[0], 
-                    &brick_delta[1], 
-                );
-                let v_ab = b - a;
-                let v_cd = d - c;
-                let v_ac = c - a;
-
-                let bunbo = (v_ab.x * v_cd.y) - (v_ab.y * v_cd.x);
-                if bunbo.abs() <= f32::EPSILON { continue; }
-                let r = (v_cd.y * v_ac.x - v_cd.x * v_ac.y) / bunbo;
-                let s = (v_ab.y * v_ac.x - v_ab.x * v_ac.y) / bunbo;
-
-                if 0. <= r && r <= 1. && 0. <= s && s <= 1. {
-                    if r < length {
-                        length = r;
-                        coll_delta = Some(match count {
-                            0 => BBCollisionPoint::Bottom, 
-                            1 => BBCollisionPoint::Top, 
-                            2 => BBCollisionPoint::Left, 
-                            _ => BBCollisionPoint::Right, 
-                        });
-                    }
-                }}
-
-            coll_delta
-        }
+            ball.model.position + ball.angle * ball.speed - self.velocity, 
+        );
+        self.model.sweep(a, b).map(|(_, normal)| BBContact {
+            normal, 
+            velocity: self.velocity, 
+        })
     }
     pub fn hit(&self, mut f: impl FnMut(&Self)) { f(self) }
+    pub fn model(&self) -> &super::Instance { &self.model }
+    pub fn feature(&self) -> &BF { &self.feature }
+    /// 直前のティックでの移動量
+    pub fn velocity(&self) -> nalgebra::Vector2<f32> { self.velocity }
+    pub fn set_color(&mut self, color: [f32; 4]) { self.model.color = color }
+    /// ブロックの移動
+    pub fn shift(&mut self, delta: nalgebra::Vector2<f32>) {
+        self.model.position += delta;
+        if let Some(m) = self.motion.as_mut() { m.shift(delta) }
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> anyhow::Result<super::super::super::save::BrickSave> {
+        Ok(super::super::super::save::BrickSave {
+            position: self.model.position.into(), 
+            size: self.model.size.into(), 
+            color: self.model.color, 
+            feature: self.feature.save_data()?, 
+            angle: self.model.angle, 
+            shape: self.model.shape, 
+            motion: self.motion, 
+        })
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::super::super::save::BrickSave) -> anyhow::Result<Self> {
+        Ok(Self::spawn(
+            BF::load_data(&save.feature)?, 
+            save.position.into(), 
+            save.size.into(), 
+            save.color, 
+        ).with_shape(save.shape, save.angle).with_motion(save.motion))
+    }
 }
 impl<BF: BrickFeature> super::AsInstance for Brick<BF> {
     fn as_instance(&self, instances: &mut super::super::RawInstArray) {
@@ -1,21 +1,52 @@
 type PMutex<T> = parking_lot::Mutex<T>;
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc};
 
-use super::{Instance, AsInstance};
+use super::{Instance, AsInstance, Shape};
 
 /// ブロックそのものの実装
 pub mod brick;
+/// ブロックの動き
+pub mod motion;
 pub use brick::Brick;
 
-/// ブロックとボールのだいたいの接触位置
-pub enum BBCollisionPoint {
-    Top, 
-    Bottom, 
-    Left, 
-    Right, 
+/// 共有して使い回すブロックのスポーン関数
+pub type BrickSpawnFn<BF> = Box<dyn FnMut(
+    [u32; 2], 
+    nalgebra::Point2<f32>, 
+    nalgebra::Vector2<f32>, 
+) -> Option<Brick<BF>> + Send>;
+
+/// ブロックの振る舞いによる周囲への作用
+///
+/// 位置は振る舞いを起こしたブロックからの相対位置(`[x, y]`、yは上の段が正)。
+#[derive(Clone, Debug, PartialEq)]
+pub enum BrickEffect {
+    /// ブロックを壊す(スコアの加算と壊れた時の振る舞いを伴う)
+    Destroy([i32; 2]), 
+    /// ブロックの色を変える
+    Recolor([i32; 2], [f32; 4]), 
+    /// 振る舞いを起こしたブロックの位置からパワーアップを落とす
+    PowerUp(super::powerup::PowerUpKind), 
+}
+
+/// 適用を待つ作用
+struct PendingEffect {
+    /// 作用を起こしたブロックの位置
+    cell: [usize; 2], 
+    position: nalgebra::Point2<f32>, 
+    effect: BrickEffect, 
+}
+
+/// ブロックや敵とボールの接触
+pub struct BBContact {
+    /// 接触した面の外向きの法線
+    pub normal: nalgebra::Vector2<f32>, 
+    /// ブロックの直前のティックでの移動量
+    pub velocity: nalgebra::Vector2<f32>, 
 }
 
 /// ブロック配列のラップ型
+#[derive(Clone)]
 pub struct BrickCollection<BF: brick::BrickFeature>(BrickColumn<BF>);
 impl<BF: brick::BrickFeature> BrickCollection<BF> {
     pub fn spawn<BM, BS, SF>(
@@ -50,8 +81,13 @@ impl<BF: brick::BrickFeature> super::AsInstance for BrickCollection<BF> {
 }
 
 /// ブロックの行
+#[derive(Clone)]
 pub struct BrickColumn<BF: brick::BrickFeature> {
     bricks: Vec<BrickRow<BF>>, 
+    /// 経過ティック数
+    tick: u64, 
+    /// 落とすパワーアップ
+    drops: Vec<(super::powerup::PowerUpKind, nalgebra::Point2<f32>)>, 
 }
 impl<BF: brick::BrickFeature> BrickColumn<BF> {
     pub fn spawn(
@@ -83,20 +119,113 @@ impl<BF: brick::BrickFeature> BrickColumn<BF> {
                 )
             ).collect();
 
-        Self { bricks }
+        Self { bricks, tick: 0, drops: Vec::new() }
     }
+    /// ボールとの接触
     pub fn collision(
         &mut self, 
         ball: &super::ball::Ball, 
         state: &mut super::super::state::BreakOutGameState, 
-    ) -> Option<BBCollisionPoint> {
-        for b in self.bricks.iter_mut()
-            .map(|b| b.collision(ball, state))
-        {
-            if let Some(r) = b { return Some(r) }
+    ) -> Option<BBContact> {
+        let (contact, cell) = self.bricks.iter()
+            .enumerate()
+            .find_map(|(y, r)| r.collision(ball, state).map(|(c, x)| (c, [x, y])))?;
+        let mut pending = self.behave(cell, brick::BrickEvent::Hit, state);
+        pending.extend(self.destroy(cell, state));
+        self.apply(pending, state);
+        Some(contact)
+    }
+    /// 毎ティックの動きと振る舞い
+    pub fn tick(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        state: &mut super::super::state::BreakOutGameState, 
+    ) {
+        self.tick += 1;
+        self.bricks.iter_mut().for_each(|r| r.advance(disp_size));
+        let cells = self.bricks.iter()
+            .enumerate()
+            .flat_map(|(y, r)| r.bricks.iter()
+                .enumerate()
+                .filter(|(_, b)| b.is_some())
+                .map(move |(x, _)| [x, y])
+            )
+            .collect::<Vec<_>>();
+        let pending = cells.into_iter()
+            .flat_map(|cell| self.behave(cell, brick::BrickEvent::Tick, state))
+            .collect();
+        self.apply(pending, state);
+    }
+    /// ステージ開始からのティック数
+    pub fn tick_count(&self) -> u64 { self.tick }
+    /// 落とすパワーアップの取り出し
+    pub fn take_drops(&mut self) -> Vec<(super::powerup::PowerUpKind, nalgebra::Point2<f32>)> {
+        std::mem::take(&mut self.drops)
+    }
+    fn brick(&self, cell: [usize; 2]) -> Option<&Brick<BF>> {
+        self.bricks.get(cell[1]).and_then(|r| r.bricks.get(cell[0])).and_then(|b| b.as_ref())
+    }
+    /// ブロックの振る舞いの呼び出し
+    fn behave(
+        &self, 
+        cell: [usize; 2], 
+        event: brick::BrickEvent, 
+        state: &mut super::super::state::BreakOutGameState, 
+    ) -> Vec<PendingEffect> {
+        let Some(brick) = self.brick(cell) else { return Vec::new() };
+        let ctx = brick::BrickCtx { cell, tick: self.tick, remaining: self.count() };
+        let position = brick.model().position;
+        brick.feature().behave(event, &ctx, state).into_iter()
+            .map(|effect| PendingEffect { cell, position, effect })
+            .collect()
+    }
+    /// ブロックを壊す
+    ///
+    /// 壊れないブロックは何もしない。壊れた時の振る舞いによる作用を返す。
+    fn destroy(
+        &mut self, 
+        cell: [usize; 2], 
+        state: &mut super::super::state::BreakOutGameState, 
+    ) -> Vec<PendingEffect> {
+        if !self.brick(cell).is_some_and(|b| b.feature().destructible()) { return Vec::new() }
+        let pending = self.behave(cell, brick::BrickEvent::Destroy, state);
+        let row = &mut self.bricks[cell[1]];
+        row.count -= 1;
+        if let Some(brick) = row.bricks[cell[0]].take() {
+            brick.hit(|b| b.feature().hitted_process(state));
         }
-        None
+        pending
     }
+    /// 作用の適用
+    ///
+    /// 壊したブロックの作用も続けて適用する。
+    fn apply(
+        &mut self, 
+        pending: Vec<PendingEffect>, 
+        state: &mut super::super::state::BreakOutGameState, 
+    ) {
+        let mut queue = VecDeque::from(pending);
+        while let Some(PendingEffect { cell, position, effect }) = queue.pop_front() {
+            let target = |offset: [i32; 2]| {
+                let x = usize::try_from(cell[0] as i64 + offset[0] as i64).ok()?;
+                let y = usize::try_from(cell[1] as i64 + offset[1] as i64).ok()?;
+                Some([x, y])
+            };
+            match effect {
+                BrickEffect::Destroy(offset) => if let Some(t) = target(offset) {
+                    queue.extend(self.destroy(t, state))
+                }, 
+                BrickEffect::Recolor(offset, color) => if let Some(b) = target(offset)
+                    .and_then(|[x, y]| self.bricks.get_mut(y)?.bricks.get_mut(x)?.as_mut())
+                {
+                    b.set_color(color)
+                }, 
+                BrickEffect::PowerUp(kind) => self.drops.push((kind, position)), 
+            }
+        }
+    }
+    /// 下の段から順の各段
+    pub fn rows(&self) -> &[BrickRow<BF>] { &self.bricks }
     pub fn count(&self) -> usize {
         let mut count = 0;
         self.bricks.iter()
@@ -104,6 +233,45 @@ impl<BF: brick::BrickFeature> BrickColumn<BF> {
             .for_each(|c| count += c);
         count
     }
+    /// 全てのブロックの移動
+    pub fn shift(&mut self, delta: nalgebra::Vector2<f32>) {
+        self.bricks.iter_mut().for_each(|r| r.shift(delta))
+    }
+    /// 行の追加
+    pub fn push_row(&mut self, row: BrickRow<BF>) {
+        self.bricks.push(row)
+    }
+    /// ブロックの残っていない行の除去
+    pub fn remove_empty_rows(&mut self) {
+        self.bricks.retain(|r| r.count != 0)
+    }
+    /// 残っているブロックの下端
+    pub fn bottom(&self) -> Option<f32> {
+        self.bricks.iter()
+            .filter_map(|r| r.bottom())
+            .reduce(f32::min)
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> anyhow::Result<Vec<Vec<Option<super::super::save::BrickSave>>>> {
+        self.bricks.iter()
+            .map(|r| r.to_save())
+            .collect()
+    }
+    /// セーブデータからの復元
+    ///
+    /// `tick`は保存時のステージ開始からのティック数で、スクリプトの`on_tick`に引き継ぐ。
+    pub fn from_save(
+        rows: &[Vec<Option<super::super::save::BrickSave>>], 
+        tick: u64, 
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            bricks: rows.iter()
+                .map(|r| BrickRow::from_save(r))
+                .collect::<anyhow::Result<_>>()?, 
+            tick, 
+            drops: Vec::new(), 
+        })
+    }
 }
 impl<BF: brick::BrickFeature> super::AsInstance for BrickColumn<BF> {
     fn as_instance(&self, instances: &mut super::RawInstArray) {
@@ -112,6 +280,7 @@ impl<BF: brick::BrickFeature> super::AsInstance for BrickColumn<BF> {
 }
 
 /// ブロックの列
+#[derive(Clone)]
 pub struct BrickRow<BF: brick::BrickFeature> {
     bricks: Vec<Option<Brick<BF>>>, 
     count: usize, 
@@ -130,43 +299,76 @@ impl<BF: brick::BrickFeature> BrickRow<BF> {
     ) -> Self {
         let width = row as f32 * brick_size.x + (row - 1) as f32 * brick_margin;
         let margin_left = (disp_size.x - width) / 2.;
-        let mut count = 0;
-        let bricks = (0..row).into_iter()
+        let bricks: Vec<_> = (0..row).into_iter()
             .map(|x| (x, brick_margin * x as f32, brick_size.x * x as f32))
             .map(|(row, padding_left, pos_left)| (spawn_f.lock())(
                 [row, column], 
                 [padding_left + margin_left + pos_left + brick_size.x / 2., pos_y].into(), 
                 brick_size, 
             ))
-            .map(|e| {
-                if let Some(_) = e { count += 1; };
-                e
-            })
             .collect();
+        let count = bricks.iter()
+            .filter(|b| b.as_ref().is_some_and(|b| b.feature().destructible()))
+            .count();
 
         Self { 
             bricks, 
             count, 
         }
     }
+    pub fn count(&self) -> usize { self.count }
+    /// 左から順の各位置にブロックが残っているか
+    pub fn occupied(&self) -> impl Iterator<Item = bool> + '_ {
+        self.bricks.iter().map(|b| b.is_some())
+    }
+    /// 行の中の全てのブロックの移動
+    pub fn shift(&mut self, delta: nalgebra::Vector2<f32>) {
+        self.bricks.iter_mut()
+            .filter_map(|b| b.as_mut())
+            .for_each(|b| b.shift(delta))
+    }
+    /// 動くブロックの1ティック分の移動
+    pub fn advance(&mut self, disp_size: nalgebra::Vector2<f32>) {
+        self.bricks.iter_mut()
+            .filter_map(|b| b.as_mut())
+            .for_each(|b| b.advance(disp_size))
+    }
+    /// 残っているブロックの下端
+    pub fn bottom(&self) -> Option<f32> {
+        self.bricks.iter()
+            .filter_map(|b| b.as_ref())
+            .map(|b| b.model().position.y - b.model().size.y / 2.)
+            .reduce(f32::min)
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> anyhow::Result<Vec<Option<super::super::save::BrickSave>>> {
+        self.bricks.iter()
+            .map(|b| b.as_ref().map(|b| b.to_save()).transpose())
+            .collect()
+    }
+    /// セーブデータからの復元
+    pub fn from_save(bricks: &[Option<super::super::save::BrickSave>]) -> anyhow::Result<Self> {
+        let bricks = bricks.iter()
+            .map(|b| b.as_ref().map(Brick::<BF>::from_save).transpose())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self {
+            count: bricks.iter()
+                .filter(|b| b.as_ref().is_some_and(|b| b.feature().destructible()))
+                .count(), 
+            bricks, 
+        })
+    }
+    /// ボールが当たったブロック
+    ///
+    /// 接触と段の中の位置を返す。壊すのは`BrickColumn`が行う。
     pub fn collision(
-        &mut self, 
+        &self, 
         ball: &super::ball::Ball, 
         state: &mut super::super::state::BreakOutGameState, 
-    ) -> Option<BBCollisionPoint> {
-        for b in self.bricks.iter_mut()
-        {
-            if let Some(rb) = if let Some(rb) = b {
-                rb.collision(ball, state)
-            } else {
-                None
-            } {
-                self.count -= 1;
-                *b = None;
-                return Some(rb)
-            }
-        }
-        None
+    ) -> Option<(BBContact, usize)> {
+        self.bricks.iter()
+            .enumerate()
+            .find_map(|(x, b)| b.as_ref()?.collision(ball, state).map(|p| (p, x)))
     }
 }
 impl<BF: brick::BrickFeature> super::AsInstance for BrickRow<BF> {
@@ -194,4 +396,10 @@ pub struct BrickSpawnParam<
     pub brick_margin: BM, 
     pub brick_size: BS, 
     pub spawn_f: Arc<parking_lot::Mutex<SF>>, 
+    /// ブロックと共に配置する障害物
+    pub obstacles: Vec<super::obstacle::Obstacle>, 
+    /// 敵の出現予定
+    pub enemies: Vec<super::enemy::EnemySpawn>, 
+    /// ボス
+    pub boss: Option<Arc<super::boss::BossDef>>, 
 }
\ No newline at end of file
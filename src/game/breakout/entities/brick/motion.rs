@@ -0,0 +1,110 @@
+//! ブロックの動き
+
+use serde::{Serialize, Deserialize};
+
+use super::super::TICKS_PER_SEC;
+
+/// ブロックの動き方
+///
+/// 時間は秒、距離はピクセルで指定する。
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Motion {
+    /// 左右の往復
+    Sweep { amplitude: f32, period: f32 }, 
+    /// 上下の波
+    ///
+    /// 横の位置が`wavelength`だけずれると1周期ずれる。
+    Wave { amplitude: f32, period: f32, wavelength: f32 }, 
+    /// 元の位置を中心とした円軌道
+    Orbit { radius: f32, period: f32 }, 
+    /// 横に流れ、画面の端から出ると反対側の端から戻る
+    ///
+    /// `speed`は1秒あたりの移動量で、負の値で左に流れる。
+    Conveyor { speed: f32 }, 
+    /// 回転
+    ///
+    /// `speed`は1秒あたりの回転角(ラジアン)。段の動きでは段の中心を軸にする。
+    Rotate { speed: f32 }, 
+}
+
+/// 動いているブロックの状態
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrickMotion {
+    pub motion: Motion, 
+    /// 動きの基準の位置
+    anchor: [f32; 2], 
+    /// 回転の軸
+    pivot: [f32; 2], 
+    /// 元の回転角
+    #[serde(default)]
+    angle: f32, 
+    /// 動き始めてからのティック数
+    elapsed: u64, 
+}
+impl BrickMotion {
+    /// `anchor`を基準とし、`pivot`を軸に回転する動き
+    ///
+    /// 回転する動きの角度は`angle`に加える。
+    pub fn new(
+        motion: Motion, 
+        anchor: nalgebra::Point2<f32>, 
+        pivot: nalgebra::Point2<f32>, 
+        angle: f32, 
+    ) -> Self { Self {
+        motion, 
+        anchor: anchor.into(), 
+        pivot: pivot.into(), 
+        angle, 
+        elapsed: 0, 
+    }}
+    /// 基準の位置と軸の移動
+    pub fn shift(&mut self, delta: nalgebra::Vector2<f32>) {
+        self.anchor = (nalgebra::Point2::from(self.anchor) + delta).into();
+        self.pivot = (nalgebra::Point2::from(self.pivot) + delta).into();
+    }
+    /// 1ティック進めた位置と回転角
+    ///
+    /// `size`はブロックの大きさで、流れるブロックが完全に見えなくなってから戻るのに使う。
+    pub fn advance(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        size: nalgebra::Vector2<f32>, 
+    ) -> (nalgebra::Point2<f32>, f32) {
+        self.elapsed += 1;
+        let t = self.elapsed as f32 / TICKS_PER_SEC;
+        let (anchor, pivot) = (
+            nalgebra::Point2::from(self.anchor), 
+            nalgebra::Point2::from(self.pivot), 
+        );
+        let phase = |period: f32| if period.abs() <= f32::EPSILON { 0. }
+            else { std::f32::consts::TAU * t / period };
+        match self.motion {
+            Motion::Sweep { amplitude, period } => (
+                anchor + nalgebra::Vector2::new(amplitude * phase(period).sin(), 0.), 
+                self.angle, 
+            ), 
+            Motion::Wave { amplitude, period, wavelength } => {
+                let shift = if wavelength.abs() <= f32::EPSILON { 0. }
+                    else { std::f32::consts::TAU * anchor.x / wavelength };
+                (
+                    anchor + nalgebra::Vector2::new(0., amplitude * (phase(period) + shift).sin()), 
+                    self.angle, 
+                )
+            }, 
+            Motion::Orbit { radius, period } => {
+                let p = phase(period);
+                (anchor + nalgebra::Vector2::new(p.cos(), p.sin()) * radius, self.angle)
+            }, 
+            Motion::Conveyor { speed } => {
+                let span = disp_size.x + size.x;
+                let x = (anchor.x + speed * t + size.x / 2.).rem_euclid(span) - size.x / 2.;
+                ([x, anchor.y].into(), self.angle)
+            }, 
+            Motion::Rotate { speed } => {
+                let angle = (speed * t).rem_euclid(std::f32::consts::TAU);
+                let rotation = nalgebra::Rotation2::new(angle);
+                (pivot + rotation * (anchor - pivot), self.angle + angle)
+            }, 
+        }
+    }
+}
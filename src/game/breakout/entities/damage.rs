@@ -0,0 +1,76 @@
+//! 体力を持ち、ボールで削られるエンティティ
+
+/// 当たった時に白く光るティック数
+const FLASH_TICKS: u32 = 8;
+
+/// 光っている間の色
+const FLASH_COLOR: [f32; 4] = [1., 1., 1., 1.];
+
+/// 体力と被弾の表示
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Health {
+    hp: u32, 
+    max: u32, 
+    /// 光っている残りのティック数
+    flash: u32, 
+}
+impl Health {
+    pub fn new(max: u32) -> Self { Self {
+        hp: max.max(1), 
+        max: max.max(1), 
+        flash: 0, 
+    }}
+    /// 途中の体力からの復元
+    pub fn restore(max: u32, hp: u32, flash: u32) -> Self { Self {
+        hp: hp.min(max.max(1)), 
+        flash, 
+        ..Self::new(max)
+    }}
+    pub fn hp(&self) -> u32 { self.hp }
+    pub fn max(&self) -> u32 { self.max }
+    pub fn flash(&self) -> u32 { self.flash }
+    /// 最大値に対する残りの割合(0.0 - 1.0)
+    pub fn ratio(&self) -> f32 { self.hp as f32 / self.max as f32 }
+    pub fn is_dead(&self) -> bool { self.hp == 0 }
+    /// ダメージを受けて光る
+    ///
+    /// 倒れた場合は`true`を返す。
+    pub fn damage(&mut self, amount: u32) -> bool {
+        self.hp = self.hp.saturating_sub(amount);
+        self.flash = FLASH_TICKS;
+        self.is_dead()
+    }
+    /// 光っている時間を進める
+    pub fn tick(&mut self) {
+        self.flash = self.flash.saturating_sub(1);
+    }
+    /// 光っている間は白くした色
+    pub fn tint(&self, color: [f32; 4]) -> [f32; 4] {
+        if self.flash == 0 { color } else { FLASH_COLOR }
+    }
+}
+
+/// ボールの当たり方
+pub struct DamageContact {
+    /// 線分上で最初に接触する位置の割合(0.0 - 1.0)
+    pub at: f32, 
+    pub contact: super::brick::BBContact, 
+    /// 受けるダメージ(弱点以外は0)
+    pub damage: u32, 
+}
+
+/// 体力を持つエンティティ
+///
+/// 1度で壊れるブロックと違い、弱点に当たった分だけ体力が減る。
+pub trait Damageable {
+    fn health(&self) -> &Health;
+    fn health_mut(&mut self) -> &mut Health;
+    /// 線分`a`から`b`へのボールの移動との接触
+    fn contact(
+        &self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<DamageContact>;
+    /// 倒した時のスコア
+    fn score(&self) -> u64;
+}
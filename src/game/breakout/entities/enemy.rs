@@ -0,0 +1,306 @@
+//! 画面上端のゲートから現れて動き回る敵
+
+use serde::{Serialize, Deserialize};
+
+use super::damage::{Damageable, DamageContact, Health};
+
+/// 敵の大きさ
+const SIZE: [f32; 2] = [16., 16.];
+
+/// 1ティックあたりの最高速度
+const MAX_SPEED: f32 = 90. / 60.;
+
+/// 1ティックあたりに変えられる速度の上限
+const MAX_FORCE: f32 = 0.05;
+
+/// うろつく時に1ティックで向きを変える最大の角度(ラジアン)
+const WANDER_TURN: f32 = 0.3;
+
+/// ボールから逃げ始める距離
+const EVADE_RANGE: f32 = 96.;
+
+/// パドルの上に空ける高さ(急降下する敵は除く)
+const FLOOR_MARGIN: f32 = 64.;
+
+/// 同時に存在できる敵の数
+const MAX_ALIVE: usize = 8;
+
+/// 倒した時のスコアの既定値
+const DEFAULT_SCORE: u64 = 300;
+
+fn default_score() -> u64 { DEFAULT_SCORE }
+
+/// 敵の動き方
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Steering {
+    /// 向きを少しずつ変えながらうろつく
+    #[default]
+    Wander, 
+    /// 最も近いパドルに向かって急降下する
+    Dive, 
+    /// ボールが近づくと逃げ、それ以外はうろつく
+    Evade, 
+}
+impl Steering {
+    fn color(&self) -> [f32; 4] { match self {
+        Self::Wander => [0.6, 1., 0.4, 1.], 
+        Self::Dive => [1., 0.3, 0.3, 1.], 
+        Self::Evade => [0.8, 0.5, 1., 1.], 
+    }}
+}
+
+/// 敵の出現予定
+///
+/// 時間は秒、位置はピクセルで指定する。
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnemySpawn {
+    /// ステージ開始から出現までの時間
+    pub at: f32, 
+    /// 出現するゲートの横の位置
+    pub gate: f32, 
+    #[serde(default)]
+    pub steering: Steering, 
+    /// 倒した時のスコア
+    #[serde(default = "default_score")]
+    pub score: u64, 
+    /// 繰り返し出現する間隔(`None`の場合は1度のみ)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<f32>, 
+}
+impl EnemySpawn {
+    /// ティック`tick`に出現するか
+    fn fires_at(&self, tick: u64) -> bool {
+        let first = (self.at.max(0.) * super::TICKS_PER_SEC).round() as u64;
+        if tick < first { return false }
+        match self.every {
+            Some(every) => (tick - first).is_multiple_of(((every * super::TICKS_PER_SEC).round() as u64).max(1)), 
+            None => tick == first, 
+        }
+    }
+}
+
+/// 敵
+#[derive(Clone)]
+pub struct Enemy {
+    model: super::Instance, 
+    velocity: nalgebra::Vector2<f32>, 
+    steering: Steering, 
+    score: u64, 
+    /// 出現した順の番号(うろつく向きの揺らぎに使う)
+    id: u64, 
+    /// 1度当たると倒れる
+    health: Health, 
+}
+impl Enemy {
+    /// ゲートの上から下向きに出現する
+    pub fn spawn(
+        spawn: &EnemySpawn, 
+        id: u64, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) -> Self { Self {
+        model: super::Instance {
+            position: [
+                spawn.gate.min(disp_size.x - SIZE[0]).max(SIZE[0]), 
+                disp_size.y + SIZE[1], 
+            ].into(), 
+            size: SIZE.into(), 
+            angle: 0., 
+            color: spawn.steering.color(), 
+            shape: super::Shape::Circle, 
+        }, 
+        velocity: [0., -MAX_SPEED].into(), 
+        steering: spawn.steering, 
+        score: spawn.score, 
+        id, 
+        health: Health::new(1), 
+    }}
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    pub fn model(&self) -> &super::Instance { &self.model }
+    /// -1.0から1.0の揺らぎ
+    ///
+    /// 番号とティック数のみから決めるため、巻き戻しても同じ値になる。
+    fn jitter(&self, tick: u64) -> f32 {
+        let mut bytes = self.id.to_le_bytes().to_vec();
+        bytes.extend(tick.to_le_bytes());
+        let h = super::super::level::fnv1a(&bytes);
+        (h >> 40) as f32 / (1u64 << 24) as f32 * 2. - 1.
+    }
+    /// うろつく時の目標の速度
+    fn wander(&self, tick: u64) -> nalgebra::Vector2<f32> {
+        let heading = if self.velocity.norm() <= f32::EPSILON { -std::f32::consts::FRAC_PI_2 }
+            else { self.velocity.y.atan2(self.velocity.x) };
+        let heading = heading + self.jitter(tick) * WANDER_TURN;
+        nalgebra::Vector2::new(heading.cos(), heading.sin()) * MAX_SPEED
+    }
+    /// 1ティック分の移動
+    ///
+    /// 動ける範囲の外にいる間は内側へ戻ることを優先する。
+    fn steer(
+        &mut self, 
+        tick: u64, 
+        disp_size: nalgebra::Vector2<f32>, 
+        floor: f32, 
+        ball: Option<nalgebra::Point2<f32>>, 
+        paddles: &[super::paddle::Paddle], 
+    ) {
+        let p = self.model.position;
+        let floor = if self.steering == Steering::Dive { 0. } else { floor };
+        let inside = nalgebra::Point2::new(
+            p.x.clamp(SIZE[0], disp_size.x - SIZE[0]), 
+            p.y.clamp(floor.min(disp_size.y - SIZE[1]), disp_size.y - SIZE[1]), 
+        );
+        let desired = if inside != p { (inside - p).normalize() * MAX_SPEED }
+            else { match self.steering {
+                Steering::Wander => self.wander(tick), 
+                Steering::Dive => paddles.iter()
+                    .map(|paddle| paddle.model().position - p)
+                    .min_by(|a, b| a.norm().total_cmp(&b.norm()))
+                    .filter(|d| d.norm() > f32::EPSILON)
+                    .map(|d| d.normalize() * MAX_SPEED)
+                    .unwrap_or_else(|| self.wander(tick)), 
+                Steering::Evade => ball
+                    .map(|b| p - b)
+                    .filter(|d| d.norm() < EVADE_RANGE && d.norm() > f32::EPSILON)
+                    .map(|d| d.normalize() * MAX_SPEED)
+                    .unwrap_or_else(|| self.wander(tick)), 
+            }};
+        let force = desired - self.velocity;
+        let force = if force.norm() > MAX_FORCE { force.normalize() * MAX_FORCE } else { force };
+        self.velocity += force;
+        if self.velocity.norm() > MAX_SPEED { self.velocity = self.velocity.normalize() * MAX_SPEED }
+        self.model.position += self.velocity;
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::EnemySave {
+        super::super::save::EnemySave {
+            position: self.model.position.into(), 
+            velocity: self.velocity.into(), 
+            steering: self.steering, 
+            score: self.score, 
+            id: self.id, 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::super::save::EnemySave) -> Self {
+        let mut enemy = Self::spawn(&EnemySpawn {
+            at: 0., 
+            gate: 0., 
+            steering: save.steering, 
+            score: save.score, 
+            every: None, 
+        }, save.id, nalgebra::Vector2::zeros());
+        enemy.model.position = save.position.into();
+        enemy.velocity = save.velocity.into();
+        enemy
+    }
+}
+impl Damageable for Enemy {
+    fn health(&self) -> &Health { &self.health }
+    fn health_mut(&mut self) -> &mut Health { &mut self.health }
+    /// 動いている敵からの相対的な移動で判定する
+    fn contact(
+        &self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<DamageContact> {
+        self.model.sweep(a, b - self.velocity).map(|(at, normal)| DamageContact {
+            at, 
+            contact: super::brick::BBContact { normal, velocity: self.velocity }, 
+            damage: 1, 
+        })
+    }
+    fn score(&self) -> u64 { self.score }
+}
+impl super::AsInstance for Enemy {
+    fn as_instance(&self, instances: &mut super::RawInstArray) {
+        instances.push(&self.model)
+    }
+}
+
+/// 出現予定と存在する敵
+#[derive(Clone, Default)]
+pub struct Enemies {
+    schedule: Vec<EnemySpawn>, 
+    alive: Vec<Enemy>, 
+    /// ステージ開始からのティック数
+    tick: u64, 
+    /// これまでに出現した数
+    spawned: u64, 
+}
+impl Enemies {
+    pub fn new(schedule: Vec<EnemySpawn>) -> Self { Self {
+        schedule, 
+        ..Default::default()
+    }}
+    pub fn iter(&self) -> impl Iterator<Item = &Enemy> { self.alive.iter() }
+    /// 出現と移動
+    pub fn tick(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        floor: f32, 
+        ball: Option<nalgebra::Point2<f32>>, 
+        paddles: &[super::paddle::Paddle], 
+    ) {
+        for spawn in self.schedule.iter().filter(|s| s.fires_at(self.tick)) {
+            if self.alive.len() >= MAX_ALIVE { break }
+            self.alive.push(Enemy::spawn(spawn, self.spawned, disp_size));
+            self.spawned += 1;
+        }
+        let tick = self.tick;
+        self.alive.iter_mut().for_each(|e| e.steer(tick, disp_size, floor + FLOOR_MARGIN, ball, paddles));
+        self.tick += 1;
+    }
+    /// 線分`a`から`b`への移動で最初に接触した敵へのダメージ
+    ///
+    /// 接触した面の法線と敵の移動量、倒した場合はそのスコアを返す。
+    pub fn hit(
+        &mut self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<(super::brick::BBContact, Option<u64>)> {
+        let (i, hit) = self.alive.iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.contact(a, b).map(|c| (i, c)))
+            .min_by(|x, y| x.1.at.total_cmp(&y.1.at))?;
+        let score = self.alive[i].health_mut().damage(hit.damage)
+            .then(|| self.alive.remove(i).score());
+        Some((hit.contact, score))
+    }
+    /// パドルに触れた敵を倒し、そのパドルのプレイヤーにスコアを加える
+    pub fn crash(
+        &mut self, 
+        paddles: &[super::paddle::Paddle], 
+        state: &mut super::super::state::BreakOutGameState, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) {
+        self.alive.retain(|e| {
+            let Some(paddle) = paddles.iter().find(|p| p.model().collision_aabb(&e.model))
+            else { return true };
+            sfx_ctx.play_effect("break");
+            *state.score.lock() += e.score;
+            state.add_player_score(paddle.player, e.score);
+            false
+        })
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::EnemiesSave {
+        super::super::save::EnemiesSave {
+            alive: self.alive.iter().map(|e| e.to_save()).collect(), 
+            tick: self.tick, 
+            spawned: self.spawned, 
+        }
+    }
+    /// セーブデータからの復元
+    ///
+    /// 出現予定は現在のステージのものを引き継ぐ。
+    pub fn restore(&mut self, save: &super::super::save::EnemiesSave) {
+        self.alive = save.alive.iter().map(Enemy::from_save).collect();
+        self.tick = save.tick;
+        self.spawned = save.spawned;
+    }
+}
+impl super::AsInstance for Enemies {
+    fn as_instance(&self, instances: &mut super::RawInstArray) {
+        self.alive.iter().for_each(|e| e.as_instance(instances))
+    }
+}
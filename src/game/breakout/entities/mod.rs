@@ -2,18 +2,41 @@
 
 use winit::event::{VirtualKeyCode, ElementState, MouseButton};
 
-use super::obj_renderer::model::{Instance, AsInstance, RawInstArray};
+use super::obj_renderer::model::{Instance, AsInstance, RawInstArray, Shape};
 
 pub mod brick;
 pub mod ball;
 pub mod paddle;
 pub mod pointer;
+/// ブロックから落ちてくるパワーアップ
+pub mod powerup;
+/// ステージに固定された障害物
+pub mod obstacle;
+/// 画面上端のゲートから現れて動き回る敵
+pub mod enemy;
+/// 体力を持ち、ボールで削られるエンティティ
+pub mod damage;
+/// 体力が尽きるまで動きと攻撃を変えながら戦うボス
+pub mod boss;
 
+/// 1秒あたりのティック数
+pub const TICKS_PER_SEC: f32 = 60.;
+
+#[derive(Clone)]
 pub struct BreakOutEntities<BF: brick::brick::BrickFeature> {
     bricks: brick::BrickCollection<BF>, 
     ball: Option<ball::Ball>, 
-    paddle: paddle::Paddle, 
+    paddles: Vec<paddle::Paddle>, 
+    /// 次にボールを打ち出すプレイヤー(`None`の場合は誰でも打ち出せる)
+    server: Option<usize>, 
     pointer: pointer::Pointer, 
+    powerups: Vec<powerup::PowerUp>, 
+    obstacles: Vec<obstacle::Obstacle>, 
+    enemies: enemy::Enemies, 
+    /// 倒すまでクリアにならないボス
+    boss: Option<boss::Boss>, 
+    /// ブロックが無くなったらクリアとするか
+    clear_on_empty: bool, 
 }
 impl<BF: brick::brick::BrickFeature> BreakOutEntities<BF> {
     pub fn new<BM, BS, SF>(
@@ -28,67 +51,241 @@ impl<BF: brick::brick::BrickFeature> BreakOutEntities<BF> {
             nalgebra::Vector2<f32>
         ) -> Option<brick::Brick<BF>>, 
     { Self {
-        paddle: paddle::Paddle::spawn(
-            [disp_size.x / 2., 120.].into(), 
+        paddles: vec![paddle::Paddle::spawn(
+            [disp_size.x / 2., paddle::PADDLE_LINE].into(), 
             [1., 1., 1., 1.]
-        ),
+        )],
+        server: None, 
         ball: None,
         pointer: pointer::Pointer::spawn(), 
+        powerups: Vec::new(), 
+        obstacles: brick_param.obstacles.clone(), 
+        enemies: enemy::Enemies::new(brick_param.enemies.clone()), 
+        boss: brick_param.boss.clone().map(boss::Boss::spawn), 
         bricks: brick::BrickCollection::spawn(disp_size, brick_param), 
+        clear_on_empty: true, 
     }}
     pub fn update(
         &mut self, 
         disp_size: nalgebra::Vector2<f32>, 
         state: &mut super::state::BreakOutGameState, 
-        sfx_ctx: &crate::sfx::SfxModule, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
     ) {
-        if if let Some(b) = self.ball.as_mut().map(|b| {
-            b.refle_edge(disp_size, sfx_ctx);
-            b.refle_paddle(&self.paddle, &mut self.pointer, sfx_ctx);
+        let goals = self.paddles.iter().map(|p| p.goal).collect::<Vec<_>>();
+        if let Some(goal) = self.ball.as_mut().and_then(|b| {
+            b.refle_edge(disp_size, &goals, sfx_ctx);
+            self.pointer.visible = false;
+            for paddle in self.paddles.iter() {
+                b.refle_paddle(paddle, &mut self.pointer, sfx_ctx);
+            }
+            b.refle_obstacle(&self.obstacles, sfx_ctx);
+            // 得点はボールに最後に触れたプレイヤーのものとする
+            let score = *state.score.lock();
             b.refle_brick(
                 self.bricks.get_mut(), 
                 state, 
                 sfx_ctx, 
             );
+            b.refle_enemy(&mut self.enemies, state, sfx_ctx);
+            if self.boss.as_mut().is_some_and(|boss| b.refle_damageable(boss, state, sfx_ctx)) {
+                self.boss = None
+            }
+            let gained = *state.score.lock() - score;
+            if gained != 0 { state.add_player_score(b.owner, gained) }
             b.moving(state);
             b.update(state, &state.difficulity);
-            b.despawnable(sfx_ctx)
-        }) { b } else { false } { 
-            state.remain_ball -= 1;
+            b.out_of_goal(disp_size, &goals, sfx_ctx)
+        }) { 
+            // ゴールラインを守っていたプレイヤーが次に打ち出す
+            // 複数のプレイヤーで守っていた場合は誰でも打ち出せる
+            let mut defenders = self.paddles.iter()
+                .filter(|p| p.goal == goal)
+                .map(|p| p.player);
+            let player = defenders.next().unwrap_or(0);
+            state.lose_ball(player);
+            self.server = if defenders.next().is_some() { None } else { Some(player) };
             self.ball = None 
         }
-        if self.bricks.get().count() == 0 { state.state = super::state::GameState::GameClear }
-        self.paddle.update(disp_size, state, &mut self.ball);
-        self.paddle.change_color(state, &self.ball);
+        // ゲームが終わった後はブロックや敵、ボスの動き、スクリプトを止める
+        let playing = state.state == super::state::GameState::Yes;
+        if playing { self.bricks.get_mut().tick(disp_size, state) }
+        if playing {
+            let floor = self.paddle_line();
+            self.enemies.tick(disp_size, floor, self.ball.as_ref().map(|b| b.position()), &self.paddles);
+            self.enemies.crash(&self.paddles, state, sfx_ctx);
+        }
+        if let Some(boss) = self.boss.as_mut().filter(|_| playing) {
+            boss.tick(disp_size, &self.paddles);
+            boss.strike(&mut self.paddles, sfx_ctx);
+        }
+        self.powerups.extend(self.bricks.get_mut()
+            .take_drops()
+            .into_iter()
+            .map(|(kind, position)| powerup::PowerUp::spawn(kind, position))
+        );
+        let paddles = &mut self.paddles;
+        self.powerups.retain_mut(|p| {
+            if let Some(paddle) = paddles.iter_mut().find(|paddle| p.caught_by(paddle)) {
+                sfx_ctx.play_effect("reflection");
+                p.apply(paddle, state);
+                return false
+            }
+            p.fall()
+        });
+        if self.clear_on_empty && self.bricks.get().count() == 0 && self.boss.is_none() { 
+            state.state = super::state::GameState::GameClear 
+        }
+        if self.ball.is_none() && state.out_of_balls() {
+            state.state = super::state::GameState::GameOver
+        }
+        for paddle in self.paddles.iter_mut() {
+            let can_serve = self.server.is_none_or(|s| s == paddle.player)
+                && state.can_serve(paddle.player);
+            paddle.update(disp_size, state, &mut self.ball, can_serve);
+            paddle.change_color(state, &self.ball);
+        }
+        for i in 1..self.paddles.len() {
+            let (head, tail) = self.paddles.split_at_mut(i);
+            for other in head.iter_mut() {
+                other.separate(&mut tail[0], disp_size);
+            }
+        }
     }
     pub fn key_input(&mut self, keycode: VirtualKeyCode, state: ElementState) {
         let state = state == ElementState::Pressed;
-        match keycode {
-            VirtualKeyCode::A => self.paddle.move_flag.move_left = state, 
-            VirtualKeyCode::D => self.paddle.move_flag.move_right = state, 
-            VirtualKeyCode::Space => self.paddle.move_flag.ball_shot = state, 
-            _ => {}, 
+        for paddle in self.paddles.iter_mut() {
+            paddle.input.key_input(&mut paddle.move_flag, keycode, state);
         }
     }
     pub fn mouse_input(&mut self, button: MouseButton, state: ElementState) {
         let state = state == ElementState::Pressed;
-        match button {
-            MouseButton::Left => self.paddle.move_flag.ball_shot = state,
-            _ => {}, 
-        }
+        if button != MouseButton::Left { return }
+        self.paddles.iter_mut()
+            .filter(|p| p.input.uses_mouse())
+            .for_each(|p| p.move_flag.ball_shot = state);
     }
     pub fn mouse_motion_input(&mut self, input: crate::MouseMoveInput) {
-        self.paddle.move_flag.move_delta = input.0.x;
+        self.paddles.iter_mut()
+            .filter(|p| p.input.uses_mouse())
+            .for_each(|p| p.move_flag.move_delta = input.0.x);
+    }
+    /// プレイヤーのパドルの操作の直接の設定
+    pub fn set_input(&mut self, player: usize, flag: paddle::PaddleMoveFlag) {
+        self.paddles.iter_mut()
+            .filter(|p| p.player == player)
+            .for_each(|p| p.move_flag = flag);
+    }
+    /// 状態の一致の確認用のハッシュ値
+    pub fn checksum(&self) -> u64 {
+        let mut bytes = Vec::new();
+        for p in self.paddles.iter() {
+            bytes.extend(p.model.position.x.to_le_bytes());
+        }
+        if let Some(b) = self.ball.as_ref() {
+            bytes.extend(b.model.position.x.to_le_bytes());
+            bytes.extend(b.model.position.y.to_le_bytes());
+            bytes.extend(b.angle.x.to_le_bytes());
+            bytes.extend(b.angle.y.to_le_bytes());
+            bytes.extend(b.owner.to_le_bytes());
+        }
+        bytes.extend(self.bricks.get().count().to_le_bytes());
+        for p in self.powerups.iter() {
+            bytes.extend(p.position().y.to_le_bytes());
+        }
+        for e in self.enemies.iter() {
+            bytes.extend(e.position().x.to_le_bytes());
+            bytes.extend(e.position().y.to_le_bytes());
+        }
+        if let Some(boss) = self.boss.as_ref() {
+            use damage::Damageable;
+            bytes.extend(boss.health().hp().to_le_bytes());
+            bytes.extend(boss.position().x.to_le_bytes());
+            bytes.extend(boss.position().y.to_le_bytes());
+            for p in boss.projectiles() {
+                bytes.extend(p.position().x.to_le_bytes());
+                bytes.extend(p.position().y.to_le_bytes());
+            }
+        }
+        super::level::fnv1a(&bytes)
     }
+    /// パドルの差し替え
+    pub fn set_paddles(&mut self, paddles: Vec<paddle::Paddle>) {
+        // 全員が同じゴールラインを守る場合は誰でも打ち出せる
+        let shared = paddles.windows(2).all(|w| w[0].goal == w[1].goal);
+        self.server = if shared { None } else { paddles.first().map(|p| p.player) };
+        self.paddles = paddles;
+    }
+    pub fn ball(&self) -> Option<&ball::Ball> { self.ball.as_ref() }
+    pub fn paddles(&self) -> &[paddle::Paddle] { &self.paddles }
     pub fn remain_brick(&self) -> usize {
         self.bricks.get().count()
     }
+    pub fn obstacles(&self) -> &[obstacle::Obstacle] { &self.obstacles }
+    pub fn enemies(&self) -> &enemy::Enemies { &self.enemies }
+    pub fn boss(&self) -> Option<&boss::Boss> { self.boss.as_ref() }
+    pub fn bricks(&self) -> &brick::BrickColumn<BF> { self.bricks.get() }
+    pub fn bricks_mut(&mut self) -> &mut brick::BrickCollection<BF> { &mut self.bricks }
+    pub fn set_clear_on_empty(&mut self, clear_on_empty: bool) {
+        self.clear_on_empty = clear_on_empty
+    }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> anyhow::Result<super::save::EntitiesSave> {
+        Ok(super::save::EntitiesSave {
+            bricks: self.bricks.get().to_save()?, 
+            brick_tick: self.bricks.get().tick_count(), 
+            ball: self.ball.as_ref().map(|b| b.to_save()), 
+            paddles: self.paddles.iter().map(|p| p.to_save()).collect(), 
+            server: self.server, 
+            clear_on_empty: self.clear_on_empty, 
+            powerups: self.powerups.iter().map(|p| p.to_save()).collect(), 
+            enemies: self.enemies.to_save(), 
+            boss: self.boss.as_ref().map(|b| b.to_save()), 
+        })
+    }
+    /// セーブデータからの復元
+    ///
+    /// パドルは位置のみを復元し、操作方法などは現在の設定を引き継ぐ。
+    pub fn restore(&mut self, save: &super::save::EntitiesSave) -> anyhow::Result<()> {
+        if save.paddles.len() != self.paddles.len() {
+            anyhow::bail!(
+                "paddle count mismatch: saved {0}, expected {1}", 
+                save.paddles.len(), 
+                self.paddles.len()
+            );
+        }
+        *self.bricks.get_mut() = brick::BrickColumn::from_save(&save.bricks, save.brick_tick)?;
+        self.ball = save.ball.as_ref().map(ball::Ball::from_save);
+        self.paddles.iter_mut()
+            .zip(save.paddles.iter())
+            .for_each(|(p, s)| p.restore(s));
+        self.server = save.server;
+        self.clear_on_empty = save.clear_on_empty;
+        self.powerups = save.powerups.iter().map(powerup::PowerUp::from_save).collect();
+        self.enemies.restore(&save.enemies);
+        // 倒したボスは復元せず、ボスの定義はステージのものを使う
+        match (self.boss.as_mut(), save.boss.as_ref()) {
+            (Some(boss), Some(s)) => boss.restore(s), 
+            (Some(_), None) => self.boss = None, 
+            _ => {}, 
+        }
+        Ok(())
+    }
+    /// パドルの上端の高さ
+    pub fn paddle_line(&self) -> f32 {
+        self.paddles.iter()
+            .filter(|p| p.goal == paddle::Goal::Bottom)
+            .map(|p| p.model.position.y + p.model.size.y / 2.)
+            .fold(0., f32::max)
+    }
 }
 impl<BF: brick::brick::BrickFeature> AsInstance for BreakOutEntities<BF> {
     fn as_instance(&self, instances: &mut super::obj_renderer::model::RawInstArray) {
-        self.paddle.as_instance(instances);
+        self.paddles.iter().for_each(|p| p.as_instance(instances));
         self.bricks.as_instance(instances);
+        self.enemies.as_instance(instances);
+        if let Some(boss) = self.boss.as_ref() { boss.as_instance(instances) };
         if let Some(ball) = self.ball.as_ref() { ball.as_instance(instances) };
+        self.powerups.iter().for_each(|p| p.as_instance(instances));
         self.pointer.as_instance(instances);
     }
 }
\ No newline at end of file
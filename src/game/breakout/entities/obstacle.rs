@@ -0,0 +1,92 @@
+//! ステージに固定された障害物
+
+use serde::{Serialize, Deserialize};
+
+use super::super::obj_renderer::model::{sweep_edges, sweep_circle};
+
+/// 障害物の色
+#[cfg(feature = "client")]
+const WALL_COLOR: [f32; 4] = [0.7, 0.7, 0.8, 1.];
+#[cfg(feature = "client")]
+const DEFLECTOR_COLOR: [f32; 4] = [0.3, 0.6, 0.9, 1.];
+#[cfg(feature = "client")]
+const BUMPER_COLOR: [f32; 4] = [1., 0.5, 0.2, 1.];
+
+/// バンパーを描く三角形の数
+#[cfg(feature = "client")]
+const BUMPER_SEGMENTS: usize = 16;
+
+/// 障害物
+///
+/// 位置は画面の左下を原点としたピクセルで指定する。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Obstacle {
+    /// 両面で反射する線分の壁
+    Wall { from: [f32; 2], to: [f32; 2] }, 
+    /// 3つの頂点で囲まれた三角形の反射板
+    Deflector { points: [[f32; 2]; 3] }, 
+    /// 当たったボールを加速させる円形のバンパー
+    ///
+    /// `kick`は1ティックあたりの移動量に加える速さ。
+    Bumper { center: [f32; 2], radius: f32, kick: f32 }, 
+}
+impl Obstacle {
+    /// 線分`a`から`b`への移動との接触
+    ///
+    /// 最初に接触する位置の割合(0.0 - 1.0)と、ボールの側を向く法線を返す。
+    pub fn sweep(
+        &self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<(f32, nalgebra::Vector2<f32>)> { match self {
+        Self::Wall { from, to } => {
+            let (from, to) = (nalgebra::Point2::from(*from), nalgebra::Point2::from(*to));
+            let (r, normal) = sweep_edges([[from, to]], a, b)?;
+            // 壁は両面で反射するため、ボールの来た側を向ける
+            Some((r, if normal.dot(&(a - from)) < 0. { -normal } else { normal }))
+        }, 
+        Self::Deflector { points } => {
+            let mut p = points.map(nalgebra::Point2::from);
+            // 辺の右側が外側となるよう反時計回りに揃える
+            if (p[1] - p[0]).perp(&(p[2] - p[0])) < 0. { p.swap(1, 2) }
+            sweep_edges([[p[0], p[1]], [p[1], p[2]], [p[2], p[0]]], a, b)
+        }, 
+        Self::Bumper { center, radius, .. } => sweep_circle((*center).into(), *radius, a, b), 
+    }}
+    /// 当たったボールに加える速さ
+    pub fn kick(&self) -> f32 { match self {
+        Self::Bumper { kick, .. } => *kick, 
+        _ => 0., 
+    }}
+    /// 描画の命令
+    #[cfg(feature = "client")]
+    pub fn commands(
+        &self, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) -> Vec<crate::game::default_2d_renderer::D2DRendererComm> {
+        use crate::game::default_2d_renderer::{D2DRendererComm, shape::{line::Line, triangle::Triangle}};
+        let to_clip = |p: [f32; 2]| [p[0] / disp_size.x * 2. - 1., p[1] / disp_size.y * 2. - 1.];
+        match self {
+            Self::Wall { from, to } => vec![D2DRendererComm::Line(Line::new(
+                [to_clip(*from), to_clip(*to)], 
+                WALL_COLOR, 
+            ))], 
+            Self::Deflector { points } => vec![D2DRendererComm::Triangle(Triangle::new(
+                points.map(to_clip), 
+                DEFLECTOR_COLOR, 
+            ))], 
+            Self::Bumper { center, radius, .. } => (0..BUMPER_SEGMENTS)
+                .map(|i| {
+                    let rim = |i: usize| {
+                        let t = std::f32::consts::TAU * i as f32 / BUMPER_SEGMENTS as f32;
+                        to_clip([center[0] + t.cos() * radius, center[1] + t.sin() * radius])
+                    };
+                    D2DRendererComm::Triangle(Triangle::new(
+                        [to_clip(*center), rim(i), rim(i + 1)], 
+                        BUMPER_COLOR, 
+                    ))
+                })
+                .collect(), 
+        }
+    }
+}
@@ -1,4 +1,5 @@
 /// パドルの移動フラグ
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PaddleMoveFlag {
     pub move_left: bool, 
     pub move_right: bool, 
@@ -14,8 +15,61 @@ impl Default for PaddleMoveFlag {
     }}
 }
 
+/// キー操作でのパドルの1ティックあたりの移動量
+pub const PADDLE_SPEED: f32 = 256. / 60.;
+
+/// 画面の端からパドルまでの高さ
+pub const PADDLE_LINE: f32 = 120.;
+
+/// パドルが守るゴールライン
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Goal {
+    /// 画面の下端
+    Bottom, 
+    /// 画面の上端
+    Top, 
+}
+
+/// パドルの操作方法
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaddleInput {
+    /// キーボード(AD, Space)とマウスの両方
+    Any, 
+    /// キーボードのみ
+    Keys {
+        left: winit::event::VirtualKeyCode, 
+        right: winit::event::VirtualKeyCode, 
+        shot: winit::event::VirtualKeyCode, 
+    }, 
+    /// マウスのみ
+    Mouse, 
+}
+impl PaddleInput {
+    /// キー入力の反映
+    pub fn key_input(
+        &self, 
+        flag: &mut PaddleMoveFlag, 
+        keycode: winit::event::VirtualKeyCode, 
+        pressed: bool, 
+    ) {
+        use winit::event::VirtualKeyCode;
+        let (left, right, shot) = match *self {
+            Self::Any => (VirtualKeyCode::A, VirtualKeyCode::D, VirtualKeyCode::Space), 
+            Self::Keys { left, right, shot } => (left, right, shot), 
+            Self::Mouse => return, 
+        };
+        if keycode == left { flag.move_left = pressed }
+        if keycode == right { flag.move_right = pressed }
+        if keycode == shot { flag.ball_shot = pressed }
+    }
+    /// マウスで操作するか
+    pub fn uses_mouse(&self) -> bool {
+        matches!(self, Self::Any | Self::Mouse)
+    }
+}
+
 /// 難易度
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub enum PaddleDifficulity {
     Easy, 
     #[default]
@@ -31,10 +85,27 @@ impl PaddleDifficulity {
 }
 
 /// パドル
+#[derive(Clone)]
 pub struct Paddle {
     pub(super) model: super::Instance, 
     pub move_flag: PaddleMoveFlag, 
     difficulity: PaddleDifficulity, 
+    /// 操作するプレイヤー
+    pub player: usize, 
+    /// 守るゴールライン
+    pub goal: Goal, 
+    /// 操作方法
+    pub input: PaddleInput, 
+    /// 難易度による幅に対する倍率
+    pub width_scale: f32, 
+    /// パワーアップで広がっている残りのティック数
+    wide_ticks: u32, 
+    /// ボスの弾で縮んでいる残りのティック数
+    shrink_ticks: u32, 
+    /// ボスの弾で動けない残りのティック数
+    stun_ticks: u32, 
+    /// 通常時の色
+    color: [f32; 4], 
 }
 impl Paddle {
     pub fn spawn(
@@ -46,15 +117,69 @@ impl Paddle {
             size: PaddleDifficulity::default().size(),
             angle: 0.,
             color,
+            shape: super::Shape::Rect,
         },
         move_flag: Default::default(),
         difficulity: Default::default(),
+        player: 0, 
+        goal: Goal::Bottom, 
+        input: PaddleInput::Any, 
+        width_scale: 1., 
+        wide_ticks: 0, 
+        shrink_ticks: 0, 
+        stun_ticks: 0, 
+        color, 
+    } }
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    pub fn model(&self) -> &super::Instance { &self.model }
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::PaddleSave {
+        super::super::save::PaddleSave {
+            position: self.model.position.into(), 
+            wide_ticks: self.wide_ticks, 
+            shrink_ticks: self.shrink_ticks, 
+            stun_ticks: self.stun_ticks, 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn restore(&mut self, save: &super::super::save::PaddleSave) {
+        self.model.position = save.position.into();
+        self.wide_ticks = save.wide_ticks;
+        self.shrink_ticks = save.shrink_ticks;
+        self.stun_ticks = save.stun_ticks;
+    }
+    pub fn width(&self) -> f32 { self.model.size.x }
+    /// 指定したティック数の間だけ幅を広げる
+    pub fn widen(&mut self, ticks: u32) {
+        self.wide_ticks = self.wide_ticks.max(ticks);
+    }
+    /// 指定したティック数の間だけ幅を縮める
+    pub fn shrink(&mut self, ticks: u32) {
+        self.shrink_ticks = self.shrink_ticks.max(ticks);
+    }
+    /// 指定したティック数の間だけ動けなくする
+    pub fn stun(&mut self, ticks: u32) {
+        self.stun_ticks = self.stun_ticks.max(ticks);
+    }
+    pub fn is_stunned(&self) -> bool { self.stun_ticks != 0 }
+    /// プレイヤーの割り当て
+    pub fn for_player(
+        self, 
+        player: usize, 
+        goal: Goal, 
+        input: PaddleInput, 
+    ) -> Self { Self {
+        player, 
+        goal, 
+        input, 
+        ..self
     } }
     pub fn update(
         &mut self, 
         disp_size: nalgebra::Vector2<f32>, 
         state: &mut super::super::state::BreakOutGameState, 
         ball: &mut Option<super::ball::Ball>, 
+        can_serve: bool, 
     ) {
         self.difficulity = match state.difficulity {
             crate::game::breakout::state::BreakOutDifficulity::Easy => {
@@ -68,17 +193,36 @@ impl Paddle {
             },
         };
         self.model.size = self.difficulity.size();
-        if self.move_flag.ball_shot && ball.is_none() && state.remain_ball != 0 {
-            *ball = Some(super::ball::Ball::spawn(
-                [self.model.position.x, self.model.position.y + 8.].into(), 
+        self.model.size.x *= self.width_scale;
+        if self.wide_ticks != 0 {
+            self.wide_ticks -= 1;
+            self.model.size.x *= 1.5;
+        }
+        if self.shrink_ticks != 0 {
+            self.shrink_ticks -= 1;
+            self.model.size.x *= 0.5;
+        }
+        // 動けない間は入力を捨て、打ち出しもできない
+        if self.stun_ticks != 0 {
+            self.stun_ticks -= 1;
+            self.move_flag.move_delta = 0.;
+            return
+        }
+        if self.move_flag.ball_shot && ball.is_none() && can_serve {
+            let dir = match self.goal {
+                Goal::Bottom => 1., 
+                Goal::Top => -1., 
+            };
+            let mut spawned = super::ball::Ball::spawn(
+                [self.model.position.x, self.model.position.y + 8. * dir].into(), 
                 [1., 1., 1., 1.], 
-                [0., 1.].into(), 
+                [0., dir].into(), 
                 256. / 60.
-            ));
-        } else if ball.is_none() {
-            if state.remain_ball == 0 { state.state = super::super::state::GameState::GameOver };
+            );
+            spawned.owner = self.player;
+            *ball = Some(spawned);
         }
-        let speed = 256. / 60.;
+        let speed = PADDLE_SPEED;
         if self.move_flag.move_right && self.model.position.x + speed < disp_size.x {
             self.model.position.x += speed;
         }
@@ -93,6 +237,32 @@ impl Paddle {
             self.model.position.x = disp_size.x + self.model.size.x / 2.
         }
     }
+    /// 同じゴールラインのパドルと重ならないように押し離す
+    pub fn separate(
+        &mut self, 
+        other: &mut Self, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) {
+        if self.goal != other.goal { return }
+        let dx = other.model.position.x - self.model.position.x;
+        let overlap = (self.model.size.x + other.model.size.x) / 2. - dx.abs();
+        if overlap <= 0. { return }
+        let push = if dx < 0. { -overlap / 2. } else { overlap / 2. };
+        self.model.position.x -= push;
+        other.model.position.x += push;
+        // 画面端で押し返された分は相手側に寄せる
+        let (left, right) = if dx < 0. { (other, self) } else { (self, other) };
+        let min = -left.model.size.x / 2.;
+        if left.model.position.x < min {
+            right.model.position.x += min - left.model.position.x;
+            left.model.position.x = min;
+        }
+        let max = disp_size.x + right.model.size.x / 2.;
+        if right.model.position.x > max {
+            left.model.position.x -= right.model.position.x - max;
+            right.model.position.x = max;
+        }
+    }
     pub fn change_color(
         &mut self, 
         state: &super::super::state::BreakOutGameState, 
@@ -101,7 +271,8 @@ impl Paddle {
         self.model.color = if ball.is_none() && state.state != super::super::state::GameState::GameOver {
             [1., 0., 0., 1.]
         } else { match state.state {
-            super::super::state::GameState::Yes => [1., 1., 1., 1.],
+            super::super::state::GameState::Yes if self.is_stunned() => [0.5, 0.5, 0.5, 1.],
+            super::super::state::GameState::Yes => self.color,
             super::super::state::GameState::GameOver => [0., 0., 0., 0.],
             super::super::state::GameState::GameClear => [0., 0., 1., 0.],
         }}
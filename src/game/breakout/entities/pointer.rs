@@ -1,4 +1,5 @@
 /// ボールの着弾地点の表示
+#[derive(Clone)]
 pub struct Pointer {
     pub(super) model: super::Instance, 
     pub(super) visible: bool, 
@@ -10,6 +11,7 @@ impl Pointer {
             size: [8., 8.].into(),
             angle: 0.,
             color: [1., 0., 0., 1.],
+            shape: super::Shape::Rect,
         },
         visible: false, 
     }}
@@ -0,0 +1,106 @@
+//! ブロックから落ちてくるパワーアップ
+
+use serde::{Serialize, Deserialize};
+
+/// 1ティックあたりの落下量
+const FALL_SPEED: f32 = 120. / 60.;
+
+/// パワーアップの大きさ
+const SIZE: [f32; 2] = [12., 12.];
+
+/// パドルを広げるティック数
+pub const WIDE_TICKS: u32 = 10 * 60;
+
+/// 取った時に加えるスコア
+const BONUS_SCORE: u64 = 500;
+
+/// パワーアップの種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// 残弾を1つ増やす
+    ExtraBall, 
+    /// しばらくパドルを広げる
+    Wide, 
+    /// スコアを加える
+    Bonus, 
+}
+impl PowerUpKind {
+    pub fn name(&self) -> &'static str { match self {
+        Self::ExtraBall => "extra_ball", 
+        Self::Wide => "wide", 
+        Self::Bonus => "bonus", 
+    }}
+    pub fn from_name(name: &str) -> Option<Self> { match name {
+        "extra_ball" => Some(Self::ExtraBall), 
+        "wide" => Some(Self::Wide), 
+        "bonus" => Some(Self::Bonus), 
+        _ => None, 
+    }}
+    fn color(&self) -> [f32; 4] { match self {
+        Self::ExtraBall => [1., 0.4, 0.8, 1.], 
+        Self::Wide => [0.3, 0.8, 1., 1.], 
+        Self::Bonus => [1., 0.9, 0.2, 1.], 
+    }}
+}
+
+/// 落ちてくるパワーアップ
+#[derive(Clone)]
+pub struct PowerUp {
+    pub kind: PowerUpKind, 
+    model: super::Instance, 
+}
+impl PowerUp {
+    pub fn spawn(kind: PowerUpKind, position: nalgebra::Point2<f32>) -> Self { Self {
+        kind, 
+        model: super::Instance {
+            position, 
+            size: SIZE.into(), 
+            angle: std::f32::consts::FRAC_PI_4, 
+            color: kind.color(), 
+            shape: super::Shape::Rect, 
+        }, 
+    }}
+    pub fn position(&self) -> nalgebra::Point2<f32> { self.model.position }
+    /// 落下
+    ///
+    /// 画面の下に消えた場合は`false`を返す。
+    pub fn fall(&mut self) -> bool {
+        self.model.position.y -= FALL_SPEED;
+        self.model.position.y > -self.model.size.y
+    }
+    /// パドルが取ったか
+    pub fn caught_by(&self, paddle: &super::paddle::Paddle) -> bool {
+        let (p, s) = (paddle.model().position, paddle.model().size);
+        (self.model.position.x - p.x).abs() < (s.x + self.model.size.x) / 2.
+            && (self.model.position.y - p.y).abs() < (s.y + self.model.size.y) / 2.
+    }
+    /// 取った時の効果
+    pub fn apply(
+        &self, 
+        paddle: &mut super::paddle::Paddle, 
+        state: &mut super::super::state::BreakOutGameState, 
+    ) { match self.kind {
+        PowerUpKind::ExtraBall => state.gain_ball(paddle.player), 
+        PowerUpKind::Wide => paddle.widen(WIDE_TICKS), 
+        PowerUpKind::Bonus => {
+            *state.score.lock() += BONUS_SCORE;
+            state.add_player_score(paddle.player, BONUS_SCORE);
+        }, 
+    }}
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::super::save::PowerUpSave {
+        super::super::save::PowerUpSave {
+            kind: self.kind, 
+            position: self.model.position.into(), 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::super::save::PowerUpSave) -> Self {
+        Self::spawn(save.kind, save.position.into())
+    }
+}
+impl super::AsInstance for PowerUp {
+    fn as_instance(&self, instances: &mut super::RawInstArray) {
+        instances.push(&self.model)
+    }
+}
@@ -0,0 +1,340 @@
+//! 強化学習向けの環境
+//!
+//! ゲームと同じ規則のシミュレーションを、`reset`と`step`によって外部から操作する。
+//! 描画と音声を用いないため、`client`機能を無効にしても利用できる。
+
+use rand::{Rng, SeedableRng};
+
+use super::{
+    entities::{BreakOutEntities, paddle::PaddleMoveFlag}, 
+    level::{BrickFeature, LevelDef, generator::{self, GeneratorParam}}, 
+    obj_renderer::model::{AsInstance, RawInstArray}, 
+    state::{BreakOutGameState, GameState}, 
+};
+
+/// CPUによる描画
+pub mod raster;
+
+pub use raster::Frame;
+
+/// シミュレーションに用いる画面の大きさ
+pub const ENV_DISP_SIZE: [f32; 2] = [640., 640.];
+
+/// 状態ベクトルでの速度の正規化に用いるボールの最大速度
+const MAX_BALL_SPEED: f32 = 350. / 60.;
+
+/// 状態ベクトルのうちブロック以外の要素数
+const STATE_HEADER_LEN: usize = 8;
+
+/// 行動
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Noop, 
+    /// ボールの打ち出し
+    Fire, 
+    Right, 
+    Left, 
+}
+impl Action {
+    /// 行動の種類の数
+    pub const COUNT: usize = 4;
+
+    /// 番号からの変換
+    pub fn from_index(index: usize) -> Option<Self> { match index {
+        0 => Some(Self::Noop), 
+        1 => Some(Self::Fire), 
+        2 => Some(Self::Right), 
+        3 => Some(Self::Left), 
+        _ => None, 
+    }}
+
+    fn flag(&self) -> PaddleMoveFlag {
+        PaddleMoveFlag {
+            move_left: *self == Self::Left, 
+            move_right: *self == Self::Right, 
+            move_delta: 0., 
+            ball_shot: *self == Self::Fire, 
+        }
+    }
+}
+
+/// 画素による観測の設定
+#[derive(Clone, Copy, Debug)]
+pub struct PixelParam {
+    pub width: u32, 
+    pub height: u32, 
+    pub grayscale: bool, 
+}
+impl Default for PixelParam {
+    fn default() -> Self { Self {
+        width: 84, 
+        height: 84, 
+        grayscale: true, 
+    }}
+}
+
+/// 遊ぶステージ
+#[derive(Clone, Debug)]
+pub enum EnvLevel {
+    /// 常に同じステージ
+    Fixed(LevelDef), 
+    /// `reset`のシード値から生成したステージ
+    Generated(GeneratorParam), 
+}
+impl Default for EnvLevel {
+    fn default() -> Self { Self::Fixed(LevelDef::builtin()) }
+}
+
+/// 環境の設定
+#[derive(Clone, Debug)]
+pub struct EnvParam {
+    pub level: EnvLevel, 
+    /// 状態ベクトルを観測に含めるか
+    pub state_vector: bool, 
+    /// 画素を観測に含める場合の設定
+    pub pixels: Option<PixelParam>, 
+    /// 1回の`step`で同じ行動を繰り返すティック数
+    pub frame_skip: u32, 
+    /// 打ち切るまでの`step`の回数
+    pub max_steps: Option<u32>, 
+    /// ボールを落とした際の報酬の減少量
+    pub life_penalty: f32, 
+}
+impl Default for EnvParam {
+    fn default() -> Self { Self {
+        level: EnvLevel::default(), 
+        state_vector: true, 
+        pixels: None, 
+        frame_skip: 1, 
+        max_steps: None, 
+        life_penalty: 0., 
+    }}
+}
+
+/// 観測
+///
+/// 状態ベクトルは以下の順の値となる。
+///
+/// - パドルのx座標、y座標(画面の大きさで正規化)
+/// - ボールの有無(0または1)
+/// - ボールのx座標、y座標(画面の大きさで正規化)
+/// - ボールの速度のx成分、y成分(最大速度で正規化)
+/// - 残弾数
+/// - ブロックの有無(0または1)を`bricks[y][x]`の順に並べたもの(`y`は下の段から)
+#[derive(Clone, Debug, Default)]
+pub struct Observation {
+    pub state: Option<Vec<f32>>, 
+    pub pixels: Option<Frame>, 
+}
+
+/// 現在の状況
+#[derive(Clone, Copy, Debug)]
+pub struct EnvInfo {
+    pub score: u64, 
+    pub remain_ball: u32, 
+    pub remain_brick: usize, 
+    pub steps: u32, 
+    /// 全てのブロックを壊したか
+    pub cleared: bool, 
+    /// `max_steps`に達して打ち切ったか
+    pub truncated: bool, 
+}
+
+/// 強化学習向けの環境
+pub struct BreakOutEnv {
+    param: EnvParam, 
+    level: LevelDef, 
+    entities: BreakOutEntities<BrickFeature>, 
+    state: BreakOutGameState, 
+    steps: u32, 
+    truncated: bool, 
+}
+impl BreakOutEnv {
+    pub fn new(param: EnvParam) -> Self {
+        let level = match &param.level {
+            EnvLevel::Fixed(level) => level.clone(), 
+            EnvLevel::Generated(gen) => generator::generate(0, gen), 
+        };
+        let mut env = Self {
+            entities: BreakOutEntities::new(level.spawn_param(), ENV_DISP_SIZE.into()), 
+            state: BreakOutGameState::new(), 
+            param, 
+            level, 
+            steps: 0, 
+            truncated: false, 
+        };
+        env.reset(0);
+        env
+    }
+
+    pub fn param(&self) -> &EnvParam { &self.param }
+    pub fn level(&self) -> &LevelDef { &self.level }
+    pub fn entities(&self) -> &BreakOutEntities<BrickFeature> { &self.entities }
+    pub fn state(&self) -> &BreakOutGameState { &self.state }
+
+    /// 状態ベクトルの要素数
+    pub fn state_len(&self) -> usize {
+        STATE_HEADER_LEN + (self.level.column * self.level.row) as usize
+    }
+
+    /// 初期状態に戻す
+    ///
+    /// シード値によってパドルの初期位置が変わり、生成するステージの場合は配置も変わる。
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        if let EnvLevel::Generated(gen) = &self.param.level {
+            self.level = generator::generate(seed, gen);
+        }
+        let disp_size = ENV_DISP_SIZE.into();
+        self.entities = BreakOutEntities::new(self.level.spawn_param(), disp_size);
+        self.state = BreakOutGameState::new();
+        self.state.set_extra_life(self.level.extra_life);
+        self.steps = 0;
+        self.truncated = false;
+
+        let mut rng = rand_pcg::Pcg32::seed_from_u64(seed);
+        let offset = rng.gen_range(-0.3..=0.3) * disp_size.x;
+        self.entities.set_input(0, PaddleMoveFlag { move_delta: offset, ..Default::default() });
+        self.entities.update(disp_size, &mut self.state, &crate::sfx::MutedSfx);
+        self.observe()
+    }
+
+    /// 行動を与えて進める
+    ///
+    /// 観測、報酬、終了したかを返す。報酬は得点の増加量となる。
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        if self.done() { return (self.observe(), 0., true) }
+        let disp_size = ENV_DISP_SIZE.into();
+        let mut reward = 0.;
+        for _ in 0..self.param.frame_skip.max(1) {
+            let score = *self.state.score.lock();
+            let remain_ball = self.state.remain_ball();
+            self.entities.set_input(0, action.flag());
+            self.entities.update(disp_size, &mut self.state, &crate::sfx::MutedSfx);
+            self.state.award_extra_life();
+            reward += (*self.state.score.lock() - score) as f32;
+            if self.state.remain_ball() < remain_ball { reward -= self.param.life_penalty }
+            if self.state.game_state() != GameState::Yes { break }
+        }
+        self.steps += 1;
+        if self.param.max_steps.is_some_and(|m| self.steps >= m) && self.state.game_state() == GameState::Yes {
+            self.truncated = true;
+        }
+        (self.observe(), reward, self.done())
+    }
+
+    /// 終了したか
+    pub fn done(&self) -> bool {
+        self.truncated || self.state.game_state() != GameState::Yes
+    }
+
+    pub fn info(&self) -> EnvInfo {
+        EnvInfo {
+            score: *self.state.score.lock(), 
+            remain_ball: self.state.remain_ball(), 
+            remain_brick: self.entities.remain_brick(), 
+            steps: self.steps, 
+            cleared: self.state.game_state() == GameState::GameClear, 
+            truncated: self.truncated, 
+        }
+    }
+
+    /// 現在の観測
+    pub fn observe(&self) -> Observation {
+        Observation {
+            state: self.param.state_vector.then(|| self.state_vector()), 
+            pixels: self.param.pixels.map(|p| self.render(p.width, p.height, p.grayscale)), 
+        }
+    }
+
+    /// 状態ベクトルの作成
+    fn state_vector(&self) -> Vec<f32> {
+        let disp_size: nalgebra::Vector2<f32> = ENV_DISP_SIZE.into();
+        let mut v = Vec::with_capacity(self.state_len());
+        let paddle = self.entities.paddles()[0].position();
+        v.extend([paddle.x / disp_size.x, paddle.y / disp_size.y]);
+        match self.entities.ball() {
+            Some(ball) => {
+                let (pos, vel) = (ball.position(), ball.velocity());
+                v.extend([
+                    1., 
+                    pos.x / disp_size.x, 
+                    pos.y / disp_size.y, 
+                    vel.x / MAX_BALL_SPEED, 
+                    vel.y / MAX_BALL_SPEED, 
+                ]);
+            }, 
+            None => v.extend([0.; 5]), 
+        }
+        v.push(self.state.remain_ball() as f32);
+        let row = self.level.row as usize;
+        for line in self.entities.bricks().rows().iter().take(self.level.column as usize) {
+            let start = v.len();
+            v.extend(line.occupied().take(row).map(|b| if b { 1. } else { 0. }));
+            v.resize(start + row, 0.);
+        }
+        v.resize(self.state_len(), 0.);
+        v
+    }
+
+    /// CPUによる描画
+    pub fn render(&self, width: u32, height: u32, grayscale: bool) -> Frame {
+        let mut instances = RawInstArray::new();
+        self.entities.as_instance(&mut instances);
+        raster::rasterize(&instances, ENV_DISP_SIZE.into(), width, height, grayscale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 固定の行動列で最後まで進めた際の状態ベクトルと状況
+    fn rollout(env: &mut BreakOutEnv, seed: u64) -> (Vec<Vec<f32>>, u64, u32) {
+        let mut states = vec![env.reset(seed).state.unwrap()];
+        for i in 0..200 {
+            let action = Action::from_index(i % Action::COUNT).unwrap();
+            let (obs, _, done) = env.step(action);
+            states.push(obs.state.unwrap());
+            if done { break }
+        }
+        let info = env.info();
+        (states, info.score, info.steps)
+    }
+
+    #[test]
+    fn same_seed_gives_same_trajectory() {
+        let param = EnvParam { frame_skip: 4, ..Default::default() };
+        let mut env = BreakOutEnv::new(param.clone());
+        let a = rollout(&mut env, 7);
+        // 同じ環境の再利用でも、別の環境でも一致する
+        assert_eq!(a, rollout(&mut env, 7));
+        assert_eq!(a, rollout(&mut BreakOutEnv::new(param), 7));
+        assert_ne!(a.0[0], rollout(&mut env, 8).0[0]);
+    }
+
+    #[test]
+    fn reset_regenerates_generated_level() {
+        let param = EnvParam {
+            level: EnvLevel::Generated(GeneratorParam::default()), 
+            ..Default::default()
+        };
+        let mut env = BreakOutEnv::new(param);
+        let obs = env.reset(3);
+        assert_eq!(obs.state.unwrap().len(), env.state_len());
+        assert_eq!(env.level(), &generator::generate(3, &GeneratorParam::default()));
+        assert_eq!(env.info().steps, 0);
+    }
+
+    #[test]
+    fn max_steps_truncates() {
+        let param = EnvParam { max_steps: Some(5), ..Default::default() };
+        let mut env = BreakOutEnv::new(param);
+        env.reset(0);
+        for _ in 0..4 { assert!(!env.step(Action::Noop).2) }
+        assert!(env.step(Action::Noop).2);
+        assert!(env.info().truncated);
+        // 終了後の行動は無視する
+        assert_eq!(env.step(Action::Fire).1, 0.);
+        assert_eq!(env.info().steps, 5);
+    }
+}
@@ -0,0 +1,82 @@
+//! CPUによる描画
+//!
+//! GPUのレンダラと同じインスタンスを、画素ごとに形の内外を判定して塗りつぶす。
+
+use super::super::obj_renderer::model::{RawInstArray, Shape, Corner};
+
+/// 描画結果
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: u32, 
+    pub height: u32, 
+    /// `true`の場合は1画素1バイトの輝度、`false`の場合は1画素3バイトのRGB
+    pub grayscale: bool, 
+    /// 上の行から順の画素
+    pub data: Vec<u8>, 
+}
+impl Frame {
+    pub fn channels(&self) -> usize { if self.grayscale { 1 } else { 3 } }
+}
+
+/// インスタンスの描画
+///
+/// ゲーム内の座標は上向きが正のため、画面の上端を先頭の行とする。
+pub fn rasterize(
+    instances: &RawInstArray, 
+    disp_size: nalgebra::Vector2<f32>, 
+    width: u32, 
+    height: u32, 
+    grayscale: bool, 
+) -> Frame {
+    let mut rgb = vec![[0f32; 3]; (width * height) as usize];
+    let (sx, sy) = (disp_size.x / width as f32, disp_size.y / height as f32);
+    for inst in instances.get() {
+        let color = inst.color();
+        if color[3] <= 0. { continue }
+        let [px, py] = inst.position();
+        let [hx, hy] = inst.half_size();
+        let [cos, sin] = inst.rotation();
+        let inside = shape_test(inst.shape());
+        // 回転を考慮した外接円の範囲のみを調べる
+        let r = (hx * hx + hy * hy).sqrt();
+        let x0 = ((px - r) / sx).floor().max(0.) as u32;
+        let x1 = (((px + r) / sx).ceil().max(0.) as u32).min(width);
+        let y0 = ((disp_size.y - (py + r)) / sy).floor().max(0.) as u32;
+        let y1 = (((disp_size.y - (py - r)) / sy).ceil().max(0.) as u32).min(height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let wx = (x as f32 + 0.5) * sx - px;
+                let wy = disp_size.y - (y as f32 + 0.5) * sy - py;
+                let lx = wx * cos + wy * sin;
+                let ly = -wx * sin + wy * cos;
+                if lx.abs() > hx || ly.abs() > hy || !inside(lx / hx, ly / hy) { continue }
+                let dst = &mut rgb[(y * width + x) as usize];
+                for (d, c) in dst.iter_mut().zip(color) {
+                    *d = c * color[3] + *d * (1. - color[3]);
+                }
+            }
+        }
+    }
+    let to_u8 = |v: f32| (v.clamp(0., 1.) * 255.).round() as u8;
+    let data = if grayscale {
+        rgb.iter().map(|c| to_u8(0.299 * c[0] + 0.587 * c[1] + 0.114 * c[2])).collect()
+    } else {
+        rgb.iter().flat_map(|c| c.map(to_u8)).collect()
+    };
+    Frame { width, height, grayscale, data }
+}
+
+/// 形の番号に対する、-1.0 - 1.0に正規化した位置が内側にあるかの判定
+fn shape_test(mesh: u32) -> impl Fn(f32, f32) -> bool {
+    let corner = [Corner::TopRight, Corner::TopLeft, Corner::BottomLeft, Corner::BottomRight]
+        .into_iter()
+        .find(|c| Shape::Triangle(*c).mesh() == mesh);
+    move |x, y| match corner {
+        Some(c) => {
+            let [sx, sy] = c.sign();
+            sx * x + sy * y >= 0.
+        }, 
+        None if mesh == Shape::Circle.mesh() => x * x + y * y <= 1., 
+        None => true, 
+    }
+}
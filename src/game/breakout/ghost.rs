@@ -0,0 +1,242 @@
+//! 自己ベストのゴーストの記録と再生
+
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use super::entities::{BreakOutEntities, paddle::PaddleMoveFlag};
+use super::obj_renderer::model::{AsInstance, RawInstArray};
+use super::level::{BrickFeature, LevelDef};
+use super::save::StateSave;
+use super::state::{BreakOutGameState, GameState};
+use super::super::util::persist;
+
+/// ゴーストの不透明度の倍率
+pub const GHOST_ALPHA: f32 = 0.35;
+
+/// 記録したステージの1回分のプレイ
+///
+/// シミュレーションは決定的なため、開始時の状態と各ティックの入力のみを持つ。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GhostReplay {
+    pub level_hash: u64, 
+    /// 開始時の状態
+    pub start: StateSave, 
+    /// ステージ中に得たスコア
+    pub score: u64, 
+    /// ステージの終了までのティック数
+    pub ticks: u64, 
+    /// 連長圧縮した各ティックの入力
+    inputs: Vec<(u32, PaddleMoveFlag)>, 
+}
+impl GhostReplay {
+    /// `other`より良い記録か
+    ///
+    /// スコアの高い方、同じであれば早く終えた方を良い記録とする。
+    pub fn beats(&self, other: &Self) -> bool {
+        (self.score, std::cmp::Reverse(self.ticks)) > (other.score, std::cmp::Reverse(other.ticks))
+    }
+}
+
+/// プレイ中の入力の記録
+pub struct GhostRecorder {
+    level_hash: u64, 
+    start: StateSave, 
+    inputs: Vec<(u32, PaddleMoveFlag)>, 
+    ticks: u64, 
+}
+impl GhostRecorder {
+    pub fn new(level: &LevelDef, state: &BreakOutGameState) -> Self { Self {
+        level_hash: level.hash(), 
+        start: state.to_save(), 
+        inputs: Vec::new(), 
+        ticks: 0, 
+    }}
+    pub fn level_hash(&self) -> u64 { self.level_hash }
+    /// 開始時のスコア
+    pub fn start_score(&self) -> u64 { self.start.score }
+    pub fn ticks(&self) -> u64 { self.ticks }
+
+    /// 1ティック分の入力の記録
+    pub fn push(&mut self, flag: PaddleMoveFlag) {
+        self.ticks += 1;
+        match self.inputs.last_mut() {
+            Some((run, last)) if *last == flag && *run < u32::MAX => *run += 1, 
+            _ => self.inputs.push((1, flag)), 
+        }
+    }
+
+    /// 記録の完了
+    pub fn finish(self, state: &BreakOutGameState) -> GhostReplay { GhostReplay {
+        level_hash: self.level_hash, 
+        score: state.score.lock().saturating_sub(self.start.score), 
+        start: self.start, 
+        ticks: self.ticks, 
+        inputs: self.inputs, 
+    }}
+}
+
+/// 再生中のゴースト
+///
+/// 自身のブロックを持って並行してシミュレーションするため、
+/// プレイヤー側のブロックには一切影響しない。
+pub struct Ghost {
+    replay: GhostReplay, 
+    entities: BreakOutEntities<BrickFeature>, 
+    state: BreakOutGameState, 
+    /// 再生中の入力の位置
+    cursor: usize, 
+    /// 現在の入力を繰り返した回数
+    repeated: u32, 
+}
+impl Ghost {
+    pub fn new(
+        replay: GhostReplay, 
+        level: &LevelDef, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) -> Self {
+        Self {
+            entities: BreakOutEntities::new(level.spawn_param(), disp_size), 
+            state: BreakOutGameState::from_save(&replay.start), 
+            replay, 
+            cursor: 0, 
+            repeated: 0, 
+        }
+    }
+
+    /// 記録を再生し終えたか
+    pub fn finished(&self) -> bool {
+        self.cursor >= self.replay.inputs.len() || self.state.game_state() != GameState::Yes
+    }
+
+    /// 1ティック分の再生
+    pub fn step(&mut self, disp_size: nalgebra::Vector2<f32>) {
+        if self.finished() { return }
+        let (run, flag) = self.replay.inputs[self.cursor];
+        self.entities.set_input(0, flag);
+        self.entities.update(disp_size, &mut self.state, &crate::sfx::MutedSfx);
+        self.state.award_extra_life();
+        self.repeated += 1;
+        if self.repeated >= run {
+            self.cursor += 1;
+            self.repeated = 0;
+        }
+    }
+
+    /// 現時点でゴーストが得たスコア
+    pub fn score(&self) -> u64 {
+        self.state.score.lock().saturating_sub(self.replay.start.score)
+    }
+    pub fn replay(&self) -> &GhostReplay { &self.replay }
+}
+impl AsInstance for Ghost {
+    fn as_instance(&self, instances: &mut RawInstArray) {
+        if self.finished() { return }
+        let models = self.entities.paddles().iter()
+            .map(|p| p.model())
+            .chain(self.entities.ball().map(|b| b.model()));
+        for model in models {
+            let mut model = *model;
+            model.color[3] *= GHOST_ALPHA;
+            instances.push(&model);
+        }
+    }
+}
+
+/// ステージごとの自己ベストの記録
+#[derive(Default, Serialize, Deserialize)]
+pub struct GhostStore {
+    entries: Vec<GhostReplay>, 
+    #[serde(skip)]
+    path: PathBuf, 
+}
+impl GhostStore {
+    /// ユーザーデータのディレクトリから読み込む
+    pub fn load() -> Self {
+        let path = persist::data_dir().join("ghosts.ron");
+        let mut store: Self = persist::load_or_recover(&path);
+        store.path = path;
+        store
+    }
+
+    /// 記録の保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save_atomic(&self.path, self)
+    }
+
+    pub fn get(&self, level_hash: u64) -> Option<&GhostReplay> {
+        self.entries.iter().find(|e| e.level_hash == level_hash)
+    }
+
+    /// プレイの記録
+    ///
+    /// 自己ベストを更新した場合は`true`を返す。
+    pub fn record(&mut self, replay: GhostReplay) -> bool {
+        match self.entries.iter_mut().find(|e| e.level_hash == replay.level_hash) {
+            Some(best) if replay.beats(best) => *best = replay, 
+            Some(_) => return false, 
+            None => self.entries.push(replay), 
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(tick: u32) -> PaddleMoveFlag {
+        PaddleMoveFlag {
+            move_left: tick % 90 < 30, 
+            move_right: tick % 90 >= 60, 
+            ball_shot: tick == 0, 
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn playback_follows_recorded_play() {
+        let disp_size = [640., 640.].into();
+        let level = LevelDef::builtin();
+        let mut entities = BreakOutEntities::<BrickFeature>::new(level.spawn_param(), disp_size);
+        let mut state = BreakOutGameState::new();
+        let mut recorder = GhostRecorder::new(&level, &state);
+        for tick in 0..300 {
+            recorder.push(input(tick));
+            entities.set_input(0, input(tick));
+            entities.update(disp_size, &mut state, &crate::sfx::MutedSfx);
+            state.award_extra_life();
+        }
+        let replay = recorder.finish(&state);
+        assert_eq!(replay.ticks, 300);
+        // 同じ入力が続く区間はまとめて記録する
+        assert!(replay.inputs.len() < 20);
+
+        let mut ghost = Ghost::new(replay, &level, disp_size);
+        while !ghost.finished() { ghost.step(disp_size) }
+        assert_eq!(ghost.score(), *state.score.lock());
+        assert_eq!(ghost.entities.paddles()[0].position(), entities.paddles()[0].position());
+        assert_eq!(
+            ghost.entities.ball().map(|b| b.position()), 
+            entities.ball().map(|b| b.position()), 
+        );
+    }
+
+    #[test]
+    fn store_keeps_the_best_replay() {
+        let level = LevelDef::builtin();
+        let state = BreakOutGameState::new();
+        let replay = |score, ticks| GhostReplay {
+            score, 
+            ticks, 
+            ..GhostRecorder::new(&level, &state).finish(&state)
+        };
+        let mut store = GhostStore::default();
+        assert!(store.record(replay(100, 500)));
+        assert!(!store.record(replay(100, 600)));
+        assert!(!store.record(replay(50, 100)));
+        assert!(store.record(replay(100, 400)));
+        let best = store.get(level.hash()).unwrap();
+        assert_eq!((best.score, best.ticks), (100, 400));
+    }
+}
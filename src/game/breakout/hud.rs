@@ -0,0 +1,120 @@
+//! ゲーム画面上の情報表示
+
+use super::obj_renderer::model::{Instance, AsInstance, RawInstArray, Shape};
+use super::entities::damage::Damageable;
+
+/// 残弾が増えたときのジングル(周波数[Hz], 長さ[ms])
+pub const EXTRA_LIFE_JINGLE: [(f32, u64); 4] = [
+    (523.25, 80), 
+    (659.25, 80), 
+    (783.99, 80), 
+    (1046.5, 200), 
+];
+
+/// ボスの体力ゲージの高さ
+const BOSS_BAR_HEIGHT: f32 = 6.;
+
+/// ボスの体力ゲージの色
+const BOSS_BAR_COLOR: [f32; 4] = [1., 0.2, 0.3, 1.];
+
+/// プレイヤーごとの残弾のアイコンの段の間隔
+const LIFE_ROW_PITCH: f32 = 14.;
+
+/// 残弾のアイコン表示
+#[derive(Default)]
+pub struct LifeHud {
+    icons: Vec<Instance>, 
+}
+impl LifeHud {
+    pub fn new() -> Self { Self {
+        icons: Vec::new(), 
+    }}
+    /// 残弾数に合わせてアイコンを並べ直す
+    ///
+    /// 画面右上から左へ向けて、パドルの上にボールが乗った形で並べる。
+    /// プレイヤーごとに残弾を持つ場合は、1Pから順に下へ段を分けてパドルの色で並べる。
+    pub fn update(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        state: &super::state::BreakOutGameState, 
+    ) {
+        self.icons.clear();
+        let rows = match state.lives_rule() {
+            super::state::LivesRule::Shared => vec![(state.remain_ball(), [1., 1., 1., 1.])], 
+            super::state::LivesRule::PerPlayer => state.players().iter()
+                .enumerate()
+                .map(|(i, p)| (p.remain_ball, if i == 0 { [1., 1., 1., 1.] } else { super::coop::P2_COLOR }))
+                .collect(), 
+        };
+        for (row, (count, color)) in rows.into_iter().enumerate() {
+            let y = disp_size.y - 20. - row as f32 * LIFE_ROW_PITCH;
+            for i in 0..count {
+                let x = disp_size.x - 24. - i as f32 * 20.;
+                self.icons.push(Instance {
+                    position: [x, y - 3.].into(), 
+                    size: [14., 3.].into(), 
+                    angle: 0., 
+                    color, 
+                    shape: Shape::Rect, 
+                });
+                self.icons.push(Instance {
+                    position: [x, y + 3.].into(), 
+                    size: [4., 4.].into(), 
+                    angle: 0., 
+                    color, 
+                    shape: Shape::Rect, 
+                });
+            }
+        }
+    }
+}
+impl AsInstance for LifeHud {
+    fn as_instance(&self, instances: &mut RawInstArray) {
+        self.icons.iter().for_each(|i| instances.push(i))
+    }
+}
+
+/// ボスの体力ゲージ
+#[derive(Default)]
+pub struct BossHud {
+    bars: Vec<Instance>, 
+}
+impl BossHud {
+    pub fn new() -> Self { Self {
+        bars: Vec::new(), 
+    }}
+    /// ボスの体力に合わせてゲージを伸縮する
+    ///
+    /// 画面上部の中央に、左端を揃えて残りの体力の分だけ伸ばす。ボスがいなければ消す。
+    pub fn update(
+        &mut self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        boss: Option<&super::entities::boss::Boss>, 
+    ) {
+        self.bars.clear();
+        let Some(boss) = boss else { return };
+        let health = boss.health();
+        let width = disp_size.x / 2.;
+        let y = disp_size.y - 40.;
+        self.bars.push(Instance {
+            position: [disp_size.x / 2., y].into(), 
+            size: [width + 4., BOSS_BAR_HEIGHT + 4.].into(), 
+            angle: 0., 
+            color: [0.25, 0.25, 0.25, 1.], 
+            shape: Shape::Rect, 
+        });
+        let fill = width * health.ratio();
+        self.bars.push(Instance {
+            position: [(disp_size.x - width + fill) / 2., y].into(), 
+            size: [fill, BOSS_BAR_HEIGHT].into(), 
+            angle: 0., 
+            color: health.tint(BOSS_BAR_COLOR), 
+            shape: Shape::Rect, 
+        });
+    }
+}
+impl AsInstance for BossHud {
+    fn as_instance(&self, instances: &mut RawInstArray) {
+        self.bars.iter().for_each(|i| instances.push(i))
+    }
+}
@@ -0,0 +1,199 @@
+//! ステージの共有コード
+//!
+//! ステージの定義を圧縮してbase64で文字列にしたもの。
+//! `BRK1:`に続けて、本体の長さ(u16)、本体のCRC32(u32)、本体(deflateで圧縮したRON)を
+//! URLセーフなbase64で表す。
+
+use std::io::{Read, Write};
+
+use base64::{
+    Engine, 
+    alphabet, 
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig}, 
+};
+
+use super::LevelDef;
+
+/// 共有コードの接頭辞
+pub const CODE_PREFIX: &str = "BRK1:";
+
+/// 長さとチェックサムの大きさ
+const HEADER_LEN: usize = 6;
+
+/// 展開後の大きさの上限
+const MAX_INFLATED: u64 = 1 << 20;
+
+/// 段数と1段あたりの数の上限
+const MAX_GRID: u32 = 64;
+
+/// base64の変換
+///
+/// 途中で切れたコードを長さの検査で検出できるよう、末尾の余りのビットは許容する。
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE, 
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent), 
+);
+
+/// 共有コードへの変換
+pub fn encode(level: &LevelDef) -> anyhow::Result<String> {
+    let body = ron::to_string(level)?;
+    let mut encoder = flate2::write::DeflateEncoder::new(
+        Vec::new(), 
+        flate2::Compression::best(), 
+    );
+    encoder.write_all(body.as_bytes())?;
+    let body = encoder.finish()?;
+    let len = u16::try_from(body.len())
+        .map_err(|_| anyhow::anyhow!("level is too large to share ({0} bytes)", body.len()))?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend(len.to_le_bytes());
+    bytes.extend(crc32fast::hash(&body).to_le_bytes());
+    bytes.extend(body);
+    Ok(format!(
+        "{CODE_PREFIX}{0}", 
+        BASE64.encode(bytes), 
+    ))
+}
+
+/// 共有コードからの復元
+///
+/// 改行や空白は無視する。途中で切れたコードや書き換えられたコードはエラーとなる。
+pub fn decode(code: &str) -> anyhow::Result<LevelDef> {
+    let code = code.split_whitespace().collect::<String>();
+    let Some(data) = code.strip_prefix(CODE_PREFIX) else {
+        if code.starts_with("BRK") {
+            anyhow::bail!("unsupported level code version")
+        }
+        anyhow::bail!("not a level code (expected it to start with {CODE_PREFIX})")
+    };
+    let bytes = BASE64.decode(data)
+        .map_err(|e| match e {
+            base64::DecodeError::InvalidLength => anyhow::anyhow!("level code is truncated"), 
+            _ => anyhow::anyhow!("level code contains invalid characters"), 
+        })?;
+    if bytes.len() < HEADER_LEN {
+        anyhow::bail!("level code is truncated")
+    }
+    let (header, body) = bytes.split_at(HEADER_LEN);
+    let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+    let crc = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+    if body.len() < len {
+        anyhow::bail!("level code is truncated ({0} of {len} bytes)", body.len())
+    }
+    if body.len() > len {
+        anyhow::bail!("level code has {0} unexpected trailing bytes", body.len() - len)
+    }
+    if crc32fast::hash(body) != crc {
+        anyhow::bail!("level code checksum mismatch (the code was altered or mistyped)")
+    }
+
+    let mut inflated = String::new();
+    flate2::read::DeflateDecoder::new(body)
+        .take(MAX_INFLATED)
+        .read_to_string(&mut inflated)
+        .map_err(|e| anyhow::anyhow!("level code data is corrupt: {e}"))?;
+    let level: LevelDef = ron::from_str(&inflated)
+        .map_err(|e| anyhow::anyhow!("level code holds an invalid level: {e}"))?;
+    // ブロックの無い寸法は配置の計算が成り立たない
+    if level.column == 0 || level.row == 0 {
+        anyhow::bail!("level grid {0}x{1} has no bricks", level.row, level.column)
+    }
+    if level.column > MAX_GRID || level.row > MAX_GRID {
+        anyhow::bail!(
+            "level grid {0}x{1} exceeds the {MAX_GRID}x{MAX_GRID} limit", 
+            level.row, 
+            level.column, 
+        )
+    }
+    Ok(level)
+}
+
+/// 共有コードをやりとりするファイル
+///
+/// 書き出したコードをここからコピーし、受け取ったコードをここへ貼り付ける。
+pub fn share_path() -> std::path::PathBuf {
+    super::super::super::util::persist::data_dir().join("share_code.txt")
+}
+
+/// 共有コードのファイルへの書き出し
+pub fn export(level: &LevelDef) -> anyhow::Result<String> {
+    let code = encode(level)?;
+    let path = share_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, format!("{code}\n"))?;
+    log::info!("level code for {0:?} written to {1}: {code}", level.name, path.display());
+    Ok(code)
+}
+
+/// 共有コードのファイルからの読み込み
+pub fn import() -> anyhow::Result<LevelDef> {
+    let path = share_path();
+    let code = std::fs::read_to_string(&path)
+        .map_err(|_| anyhow::anyhow!("no level code found; paste one into {0}", path.display()))?;
+    if code.trim().is_empty() {
+        anyhow::bail!("no level code found; paste one into {0}", path.display())
+    }
+    decode(&code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// コードのバイト列を書き換えて作り直す
+    fn alter(code: &str, f: impl FnOnce(&mut Vec<u8>)) -> String {
+        let mut bytes = BASE64.decode(code.strip_prefix(CODE_PREFIX).unwrap()).unwrap();
+        f(&mut bytes);
+        format!("{CODE_PREFIX}{0}", BASE64.encode(bytes))
+    }
+
+    fn error(code: &str) -> String { decode(code).unwrap_err().to_string() }
+
+    #[test]
+    fn round_trip() {
+        let mut level = LevelDef::builtin();
+        level.name = "Shared".into();
+        level.bricks[1][2] = None;
+        let code = encode(&level).unwrap();
+        assert!(code.starts_with(CODE_PREFIX));
+        assert_eq!(decode(&code).unwrap(), level);
+        // 貼り付けの際に入った改行や空白は無視する
+        let (a, b) = code.split_at(code.len() / 2);
+        assert_eq!(decode(&format!("  {a}\n {b}\n")).unwrap(), level);
+    }
+
+    #[test]
+    fn altered_code_is_rejected() {
+        let code = encode(&LevelDef::builtin()).unwrap();
+        let tampered = alter(&code, |b| *b.last_mut().unwrap() ^= 0x01);
+        assert!(error(&tampered).contains("checksum mismatch"));
+        let crc = alter(&code, |b| b[2] ^= 0x80);
+        assert!(error(&crc).contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn truncated_and_foreign_codes_are_rejected() {
+        let code = encode(&LevelDef::builtin()).unwrap();
+        assert!(error(&code[..code.len() - 8]).contains("truncated"));
+        assert!(error(&code[..CODE_PREFIX.len() + 4]).contains("truncated"));
+        assert!(error(&alter(&code, |b| b.push(0))).contains("trailing"));
+        assert!(error(&code.replace(CODE_PREFIX, "BRK9:")).contains("unsupported"));
+        assert!(error("hello").contains("not a level code"));
+    }
+
+    #[test]
+    fn empty_or_oversized_grid_is_rejected() {
+        for (column, row) in [(0, 24), (5, 0)] {
+            let code = encode(&LevelDef { column, row, ..LevelDef::builtin() }).unwrap();
+            assert!(error(&code).contains("has no bricks"), "{column}x{row}");
+        }
+        let code = encode(&LevelDef { row: MAX_GRID + 1, ..LevelDef::builtin() }).unwrap();
+        assert!(error(&code).contains("exceeds"));
+    }
+}
@@ -0,0 +1,354 @@
+//! シード値によるステージの自動生成
+//!
+//! 同じシード値とパラメータからは常に同じ配置が生成される。
+
+use rand::{Rng, SeedableRng, distributions::{Distribution, WeightedIndex}};
+use serde::{Serialize, Deserialize};
+
+use super::{LevelDef, BrickDef, BrickType};
+use super::super::state::ExtraLifeRule;
+
+/// ブロックを並べる横幅
+const FIELD_WIDTH: f32 = 600.;
+
+/// ブロックの間隔
+const BRICK_MARGIN: [f32; 2] = [4., 4.];
+
+/// 配置の対称性
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Symmetry {
+    None, 
+    /// 左右対称
+    Mirror, 
+    /// 180度の回転対称
+    Rotational, 
+}
+impl std::str::FromStr for Symmetry {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { match s {
+        "none" => Ok(Self::None), 
+        "mirror" => Ok(Self::Mirror), 
+        "rotational" => Ok(Self::Rotational), 
+        _ => anyhow::bail!("unknown symmetry: {s}"), 
+    }}
+}
+
+/// 配置の形状
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pattern {
+    /// 全面
+    Random, 
+    /// 下を底辺とする三角形
+    Pyramid, 
+    /// `cell`個四方のマスによる市松模様
+    Checkerboard { cell: u32 }, 
+    /// 文字列(A-Z, 0-9)
+    Letters(String), 
+}
+impl std::str::FromStr for Pattern {
+    type Err = anyhow::Error;
+    /// `random`, `pyramid`, `checker[:N]`, `text:WORD`の形式
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = s.split_once(':').map_or((s, None), |(k, a)| (k, Some(a)));
+        match (kind, arg) {
+            ("random", None) => Ok(Self::Random), 
+            ("pyramid", None) => Ok(Self::Pyramid), 
+            ("checker", None) => Ok(Self::Checkerboard { cell: 1 }), 
+            ("checker", Some(cell)) => Ok(Self::Checkerboard { cell: cell.parse()? }), 
+            ("text", Some(text)) => Ok(Self::Letters(text.to_uppercase())), 
+            _ => anyhow::bail!("unknown pattern: {s}"), 
+        }
+    }
+}
+
+/// 生成のパラメータ
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeneratorParam {
+    /// ブロックの段数
+    pub column: u32, 
+    /// 1段あたりのブロック数
+    pub row: u32, 
+    pub symmetry: Symmetry, 
+    pub pattern: Pattern, 
+    /// 形状の中でブロックを置く確率
+    pub density: f32, 
+    /// ブロックの種類ごとの出現の重み(Normal, Upper, Top)
+    pub weights: [u32; 3], 
+    /// 目標とする難易度(0.0 - 1.0)
+    ///
+    /// 指定した場合は`density`を無視し、難易度が最も近くなる密度を探索する。
+    pub difficulty: Option<f32>, 
+}
+impl Default for GeneratorParam {
+    fn default() -> Self { Self {
+        column: 6, 
+        row: 20, 
+        symmetry: Symmetry::Mirror, 
+        pattern: Pattern::Random, 
+        density: 0.7, 
+        weights: [70, 20, 10], 
+        difficulty: None, 
+    }}
+}
+
+/// 3x5ドットの文字の字形(上の行から、各行の下位3ビット)
+fn glyph(c: char) -> [u8; 5] { match c {
+    'A' => [0b010, 0b101, 0b111, 0b101, 0b101], 
+    'B' => [0b110, 0b101, 0b110, 0b101, 0b110], 
+    'C' => [0b011, 0b100, 0b100, 0b100, 0b011], 
+    'D' => [0b110, 0b101, 0b101, 0b101, 0b110], 
+    'E' => [0b111, 0b100, 0b110, 0b100, 0b111], 
+    'F' => [0b111, 0b100, 0b110, 0b100, 0b100], 
+    'G' => [0b011, 0b100, 0b101, 0b101, 0b011], 
+    'H' => [0b101, 0b101, 0b111, 0b101, 0b101], 
+    'I' => [0b111, 0b010, 0b010, 0b010, 0b111], 
+    'J' => [0b001, 0b001, 0b001, 0b101, 0b010], 
+    'K' => [0b101, 0b101, 0b110, 0b101, 0b101], 
+    'L' => [0b100, 0b100, 0b100, 0b100, 0b111], 
+    'M' => [0b101, 0b111, 0b111, 0b101, 0b101], 
+    'N' => [0b110, 0b101, 0b101, 0b101, 0b101], 
+    'O' => [0b010, 0b101, 0b101, 0b101, 0b010], 
+    'P' => [0b110, 0b101, 0b110, 0b100, 0b100], 
+    'Q' => [0b010, 0b101, 0b101, 0b110, 0b011], 
+    'R' => [0b110, 0b101, 0b110, 0b101, 0b101], 
+    'S' => [0b011, 0b100, 0b010, 0b001, 0b110], 
+    'T' => [0b111, 0b010, 0b010, 0b010, 0b010], 
+    'U' => [0b101, 0b101, 0b101, 0b101, 0b111], 
+    'V' => [0b101, 0b101, 0b101, 0b101, 0b010], 
+    'W' => [0b101, 0b101, 0b111, 0b111, 0b101], 
+    'X' => [0b101, 0b101, 0b010, 0b101, 0b101], 
+    'Y' => [0b101, 0b101, 0b010, 0b010, 0b010], 
+    'Z' => [0b111, 0b001, 0b010, 0b100, 0b111], 
+    '0' => [0b111, 0b101, 0b101, 0b101, 0b111], 
+    '1' => [0b010, 0b110, 0b010, 0b010, 0b111], 
+    '2' => [0b110, 0b001, 0b010, 0b100, 0b111], 
+    '3' => [0b110, 0b001, 0b010, 0b001, 0b110], 
+    '4' => [0b101, 0b101, 0b111, 0b001, 0b001], 
+    '5' => [0b111, 0b100, 0b110, 0b001, 0b110], 
+    '6' => [0b011, 0b100, 0b111, 0b101, 0b111], 
+    '7' => [0b111, 0b001, 0b010, 0b010, 0b010], 
+    '8' => [0b111, 0b101, 0b111, 0b101, 0b111], 
+    '9' => [0b111, 0b101, 0b111, 0b001, 0b110], 
+    _ => [0; 5], 
+}}
+
+impl GeneratorParam {
+    /// パラメータの検査
+    ///
+    /// 段数や1段あたりの数が0の場合や、ブロックの幅が無くなるほど1段あたりの数が多い場合はエラーとする。
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.column == 0 || self.row == 0 {
+            anyhow::bail!("column and row must be greater than 0");
+        }
+        if self.brick_width() <= 0. {
+            anyhow::bail!("row {0} is too large: bricks would be {1} px wide", self.row, self.brick_width());
+        }
+        Ok(())
+    }
+
+    /// ブロックの幅
+    fn brick_width(&self) -> f32 {
+        let row = self.row.max(1) as f32;
+        ((FIELD_WIDTH - (row - 1.) * BRICK_MARGIN[0]) / row).floor()
+    }
+
+    /// 形状の中に`[x, y]`(yは下から)が含まれるか
+    fn shape(&self, x: u32, y: u32) -> bool { match &self.pattern {
+        Pattern::Random => true, 
+        Pattern::Pyramid => {
+            let half = self.row as f32 / 2. * (self.column - y) as f32 / self.column as f32;
+            (x as f32 + 0.5 - self.row as f32 / 2.).abs() <= half
+        }, 
+        Pattern::Checkerboard { cell } => {
+            let cell = (*cell).max(1);
+            (x / cell + y / cell).is_multiple_of(2)
+        }, 
+        Pattern::Letters(text) => {
+            let width = (text.chars().count() * 4).saturating_sub(1) as i64;
+            let left = (self.row as i64 - width) / 2;
+            let top = (self.column as i64 - 5) / 2;
+            // 字形は上から並ぶので上下を反転する
+            let (gx, gy) = (x as i64 - left, self.column as i64 - 1 - y as i64 - top);
+            if gx < 0 || gx >= width || !(0..5).contains(&gy) || gx % 4 == 3 { return false }
+            let c = text.chars().nth((gx / 4) as usize).unwrap_or(' ');
+            glyph(c)[gy as usize] & (0b100 >> (gx % 4)) != 0
+        }, 
+    }}
+
+    /// 対称性による代表の位置
+    fn canonical(&self, x: u32, y: u32) -> (u32, u32) {
+        let (mx, my) = (self.row - 1 - x, self.column - 1 - y);
+        match self.symmetry {
+            Symmetry::None => (x, y), 
+            Symmetry::Mirror => (x.min(mx), y), 
+            Symmetry::Rotational => if (y, x) <= (my, mx) { (x, y) } else { (mx, my) }, 
+        }
+    }
+}
+
+/// ステージの生成
+pub fn generate(seed: u64, param: &GeneratorParam) -> LevelDef {
+    let Some(target) = param.difficulty else {
+        return generate_with(seed, param, param.density)
+    };
+    // 密度に対して難易度は単調に増えるので二分探索する
+    let (mut lo, mut hi) = (0f32, 1f32);
+    let mut best = generate_with(seed, param, 1.);
+    for _ in 0..16 {
+        let density = (lo + hi) / 2.;
+        let level = generate_with(seed, param, density);
+        let d = difficulty(&level);
+        if (d - target).abs() < (difficulty(&best) - target).abs() {
+            best = level;
+        }
+        if d < target { lo = density } else { hi = density }
+    }
+    best
+}
+
+/// 密度を指定したステージの生成
+fn generate_with(seed: u64, param: &GeneratorParam, density: f32) -> LevelDef {
+    let mut rng = rand_pcg::Pcg32::seed_from_u64(seed);
+    let types = WeightedIndex::new(param.weights).ok();
+    // 代表の位置ごとに配置の有無と種類を決める
+    // 密度に関わらず同じ乱数列を消費するように、種類は常に抽選する
+    let picks = (0..param.column).map(|_| (0..param.row).map(|_| {
+        let roll = rng.gen::<f32>();
+        let blk_type = match types.as_ref().map(|t| t.sample(&mut rng)) {
+            Some(1) => BrickType::Upper, 
+            Some(2) => BrickType::Top, 
+            _ => BrickType::Normal, 
+        };
+        (roll < density).then_some(blk_type)
+    }).collect::<Vec<_>>()).collect::<Vec<_>>();
+    let hue = rng.gen::<f32>();
+
+    let bricks = (0..param.column).map(|y| (0..param.row).map(|x| {
+        if !param.shape(x, y) { return None }
+        let (cx, cy) = param.canonical(x, y);
+        picks[cy as usize][cx as usize].map(|blk_type| BrickDef {
+            score: match blk_type {
+                BrickType::Normal => 100, 
+                BrickType::Upper => 200, 
+                BrickType::Top => 300, 
+                BrickType::Solid => 0, 
+            } + y as u64 * 10, 
+            blk_type, 
+            color: color(hue + y as f32 / param.column.max(1) as f32 * 0.3, blk_type), 
+            script: None, 
+            shape: Default::default(), 
+            angle: 0., 
+            motion: None, 
+        })
+    }).collect()).collect();
+
+    let brick_margin = BRICK_MARGIN;
+    let brick_size = [param.brick_width(), 12.];
+    LevelDef {
+        name: format!("Generated {seed}"), 
+        column: param.column, 
+        row: param.row, 
+        margin_top: 48., 
+        brick_margin, 
+        brick_size, 
+        bricks, 
+        extra_life: ExtraLifeRule::default(), 
+        scripts: Default::default(), 
+        row_motions: Default::default(), 
+        obstacles: Vec::new(), 
+        enemies: Vec::new(), 
+        boss: None, 
+    }
+}
+
+/// 色相と種類からブロックの色を決める
+fn color(hue: f32, blk_type: BrickType) -> [f32; 4] {
+    let (s, v) = match blk_type {
+        BrickType::Normal => (0.5, 0.9), 
+        BrickType::Upper => (0.75, 0.9), 
+        BrickType::Top => (1., 0.8), 
+        BrickType::Solid => (0., 0.5), 
+    };
+    let h = hue.rem_euclid(1.) * 6.;
+    let f = h - h.floor();
+    let (p, q, t) = (v * (1. - s), v * (1. - s * f), v * (1. - s * (1. - f)));
+    let [r, g, b] = match h as u32 {
+        0 => [v, t, p], 
+        1 => [q, v, p], 
+        2 => [p, v, t], 
+        3 => [p, q, v], 
+        4 => [t, p, v], 
+        _ => [v, p, q], 
+    };
+    [r, g, b, 1.]
+}
+
+/// ステージの難易度の推定値(0.0 - 1.0)
+///
+/// 全てのマスに対するブロックの割合に、種類による重み
+/// (Normal 0.5, Upper 0.75, TopとSolid 1.0)を掛けたもの。
+pub fn difficulty(level: &LevelDef) -> f32 {
+    let cells = (level.column * level.row) as f32;
+    if cells == 0. { return 0. }
+    let sum: f32 = level.bricks.iter()
+        .flatten()
+        .flatten()
+        .map(|b| match b.blk_type {
+            BrickType::Normal => 0.5, 
+            BrickType::Upper => 0.75, 
+            BrickType::Top | BrickType::Solid => 1., 
+        })
+        .sum();
+    sum / cells
+}
+
+/// 確認用の文字による表示
+///
+/// 上の段から`#`がNormal、`+`がUpper、`*`がTop、`X`がSolid、`.`が空きを表す。
+pub fn preview(level: &LevelDef) -> String {
+    level.bricks.iter()
+        .rev()
+        .map(|r| r.iter().map(|b| match b.as_ref().map(|b| b.blk_type) {
+            None => '.', 
+            Some(BrickType::Normal) => '#', 
+            Some(BrickType::Upper) => '+', 
+            Some(BrickType::Top) => '*', 
+            Some(BrickType::Solid) => 'X', 
+        }).chain(std::iter::once('\n')).collect::<String>())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_level() {
+        let param = GeneratorParam { difficulty: Some(0.4), ..Default::default() };
+        for seed in [0, 1, 12345, u64::MAX] {
+            assert_eq!(generate(seed, &param), generate(seed, &param));
+        }
+        let a = generate(1, &GeneratorParam::default());
+        let b = generate(2, &GeneratorParam::default());
+        assert_ne!(preview(&a), preview(&b));
+    }
+
+    #[test]
+    fn mirror_symmetry_is_kept() {
+        let param = GeneratorParam { symmetry: Symmetry::Mirror, ..Default::default() };
+        let level = generate(7, &param);
+        for row in level.bricks.iter() {
+            let types = row.iter().map(|b| b.as_ref().map(|b| b.blk_type)).collect::<Vec<_>>();
+            assert!(types.iter().eq(types.iter().rev()));
+        }
+    }
+
+    #[test]
+    fn check_rejects_empty_and_too_wide_grids() {
+        assert!(GeneratorParam::default().check().is_ok());
+        assert!(GeneratorParam { column: 0, ..Default::default() }.check().is_err());
+        assert!(GeneratorParam { row: 0, ..Default::default() }.check().is_err());
+        assert!(GeneratorParam { row: 200, ..Default::default() }.check().is_err());
+        assert!(GeneratorParam { row: 100, ..Default::default() }.check().is_ok());
+    }
+}
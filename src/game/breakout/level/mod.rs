@@ -0,0 +1,372 @@
+//! ステージの定義
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::{Serialize, Deserialize};
+
+use super::entities::brick::{
+    Brick, 
+    BrickEffect, 
+    BrickSpawnParam, 
+    BrickSpawnFn, 
+    brick::{BrickCtx, BrickEvent}, 
+    motion::{Motion, BrickMotion}, 
+};
+use super::entities::obstacle::Obstacle;
+use super::entities::enemy::EnemySpawn;
+use super::entities::boss::BossDef;
+use super::obj_renderer::model::Shape;
+use super::state::{BreakOutGameState, BreakOutDifficulity, ExtraLifeRule};
+
+/// ステージの自動生成
+pub mod generator;
+
+/// ステージの検証と分析
+pub mod validate;
+
+/// ステージの共有コード
+pub mod code;
+
+/// ブロックのスクリプト
+pub mod script;
+
+/// FNV-1aによるハッシュ値
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// ステージの定義から生成したブロックのスポーン関数
+pub type LevelSpawnFn = BrickSpawnFn<BrickFeature>;
+
+/// ブロックの種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrickType {
+    Normal, 
+    Upper, 
+    Top, 
+    /// 壊れないブロック
+    Solid, 
+}
+
+/// ブロックの機能
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BrickFeature {
+    score: u64, 
+    blk_type: BrickType, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    script: Option<script::ScriptHandle>, 
+}
+impl BrickFeature {
+    pub fn new(
+        score: u64, 
+        blk_type: BrickType, 
+    ) -> Self { Self {
+        score, 
+        blk_type, 
+        script: None, 
+    } }
+    /// スクリプトの設定
+    pub fn with_script(mut self, script: Option<script::ScriptHandle>) -> Self {
+        self.script = script;
+        self
+    }
+}
+impl super::entities::brick::brick::BrickFeature for BrickFeature {
+    fn hitted_process(
+        &self, 
+        state: &mut BreakOutGameState, 
+    ) {
+        if self.blk_type == BrickType::Solid { return }
+        *state.score.lock() += self.score;
+        match self.blk_type {
+            BrickType::Normal => {}, 
+            BrickType::Upper => if state.difficulity == BreakOutDifficulity::Easy {
+                state.difficulity = BreakOutDifficulity::Normal
+            }, 
+            BrickType::Top => state.difficulity = BreakOutDifficulity::Hard, 
+            BrickType::Solid => {}, 
+        }
+    }
+    fn destructible(&self) -> bool { self.blk_type != BrickType::Solid }
+    fn behave(
+        &self, 
+        event: BrickEvent, 
+        ctx: &BrickCtx, 
+        state: &mut BreakOutGameState, 
+    ) -> Vec<BrickEffect> {
+        self.script.as_ref().map_or_else(Vec::new, |s| s.run(event, ctx, state))
+    }
+    fn save_data(&self) -> anyhow::Result<String> {
+        Ok(ron::to_string(self)?)
+    }
+    fn load_data(data: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(data)?)
+    }
+}
+
+fn is_zero(v: &f32) -> bool { *v == 0. }
+
+/// ステージ上の1つのブロックの定義
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BrickDef {
+    pub score: u64, 
+    pub blk_type: BrickType, 
+    pub color: [f32; 4], 
+    /// ステージの`scripts`にあるスクリプトの名前
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>, 
+    /// 当たり判定と描画の形
+    #[serde(default, skip_serializing_if = "Shape::is_rect")]
+    pub shape: Shape, 
+    /// 回転角(ラジアン)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub angle: f32, 
+    /// ブロックの動き(段の動きより優先する)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<Motion>, 
+}
+
+/// ステージの定義
+///
+/// `column`がブロックの段数、`row`が1段あたりのブロック数で、
+/// `bricks[y][x]`が各位置のブロックとなる。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelDef {
+    pub name: String, 
+    pub column: u32, 
+    pub row: u32, 
+    pub margin_top: f32, 
+    pub brick_margin: [f32; 2], 
+    pub brick_size: [f32; 2], 
+    pub bricks: Vec<Vec<Option<BrickDef>>>, 
+    /// スコアによる残弾の追加
+    #[serde(default)]
+    pub extra_life: ExtraLifeRule, 
+    /// 名前を付けたブロックのスクリプト
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>, 
+    /// 段ごとの動き(キーは下からの段の番号)
+    ///
+    /// 段の全てのブロックが同じように動き、回転は段の中心を軸にする。
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub row_motions: BTreeMap<u32, Motion>, 
+    /// 壁や反射板などの障害物
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub obstacles: Vec<Obstacle>, 
+    /// 敵の出現予定
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enemies: Vec<EnemySpawn>, 
+    /// 倒すまでクリアにならないボス
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss: Option<Box<BossDef>>, 
+}
+impl LevelDef {
+    /// 組み込みの標準ステージ
+    pub fn builtin() -> Self {
+        let (column, row) = (5, 24);
+        Self {
+            name: "Standard".into(), 
+            column, 
+            row, 
+            margin_top: 32., 
+            brick_margin: [2., 4.], 
+            brick_size: [24., 12.], 
+            bricks: (0..column).map(|y| (0..row).map(|x| Some(BrickDef {
+                score: 100 * (y as u64 + 1), 
+                blk_type: if y >= 4 { BrickType::Top }
+                    else if y >= 3 { BrickType::Upper }
+                    else { BrickType::Normal }, 
+                color: [
+                    1. - y as f32 * (1. / 5.), 
+                    x as f32 * (1. / 24.), 
+                    y as f32 * (1. / 5.), 
+                    1.
+                ], 
+                script: None, 
+                shape: Shape::Rect, 
+                angle: 0., 
+                motion: None, 
+            })).collect()).collect(), 
+            extra_life: ExtraLifeRule::default(), 
+            scripts: BTreeMap::new(), 
+            row_motions: BTreeMap::new(), 
+            obstacles: Vec::new(), 
+            enemies: Vec::new(), 
+            boss: None, 
+        }
+    }
+
+    /// ファイルからの読み込み
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let body = std::fs::read_to_string(path)?;
+        let level: Self = ron::from_str(&body)?;
+        // ブロックの無い寸法は配置の計算が成り立たない
+        if level.column == 0 || level.row == 0 {
+            anyhow::bail!("level grid {0}x{1} has no bricks", level.row, level.column)
+        }
+        Ok(level)
+    }
+
+    /// ファイルへの書き込み
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let body = ron::ser::to_string_pretty(
+            self, 
+            ron::ser::PrettyConfig::default()
+        )?;
+        std::fs::write(path, body)?;
+        Ok(())
+    }
+
+    /// 配置のハッシュ値
+    ///
+    /// 名前を除いた配置のみから算出するため、名前を変えても同じ値になる。
+    /// スクリプト、段の動き、障害物、敵、ボスはある場合のみ含め、それらの無いステージの値は変えない。
+    pub fn hash(&self) -> u64 {
+        let mut layout = ron::to_string(&(
+            self.column, 
+            self.row, 
+            self.margin_top, 
+            self.brick_margin, 
+            self.brick_size, 
+            &self.bricks, 
+        )).expect("level layout serialize");
+        if !self.scripts.is_empty() {
+            layout += &ron::to_string(&self.scripts).expect("level scripts serialize");
+        }
+        if !self.row_motions.is_empty() {
+            layout += &ron::to_string(&self.row_motions).expect("level motions serialize");
+        }
+        if !self.obstacles.is_empty() {
+            layout += &ron::to_string(&self.obstacles).expect("level obstacles serialize");
+        }
+        if !self.enemies.is_empty() {
+            layout += &ron::to_string(&self.enemies).expect("level enemies serialize");
+        }
+        if let Some(boss) = self.boss.as_ref() {
+            layout += &ron::to_string(boss).expect("level boss serialize");
+        }
+        fnv1a(layout.as_bytes())
+    }
+
+    /// 各位置のブロックの中心
+    ///
+    /// `BrickColumn::spawn`と同じ配置で、`pos`は`[x, y]`(yは下の段から)。
+    pub fn brick_center(
+        &self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        pos: [u32; 2], 
+    ) -> nalgebra::Point2<f32> {
+        let [size_x, size_y] = self.brick_size;
+        let [margin_x, margin_y] = self.brick_margin;
+        let width = self.row as f32 * size_x + self.row.saturating_sub(1) as f32 * margin_x;
+        let height = self.column as f32 * size_y + self.column.saturating_sub(1) as f32 * margin_y;
+        let left = (disp_size.x - width) / 2.;
+        let bottom = disp_size.y - (self.margin_top + height);
+        [
+            left + pos[0] as f32 * (size_x + margin_x) + size_x / 2., 
+            bottom + pos[1] as f32 * (size_y + margin_y) + size_y / 2., 
+        ].into()
+    }
+
+    /// 指定した点を含むブロックの位置
+    ///
+    /// ブロックの間の余白はその左下のブロックに含める。
+    pub fn cell_at(
+        &self, 
+        disp_size: nalgebra::Vector2<f32>, 
+        point: nalgebra::Point2<f32>, 
+    ) -> Option<[u32; 2]> {
+        let origin = self.brick_center(disp_size, [0, 0]);
+        let [size_x, size_y] = self.brick_size;
+        let [margin_x, margin_y] = self.brick_margin;
+        let x = ((point.x - origin.x + size_x / 2.) / (size_x + margin_x)).floor();
+        let y = ((point.y - origin.y + size_y / 2.) / (size_y + margin_y)).floor();
+        if x < 0. || y < 0. || x >= self.row as f32 || y >= self.column as f32 { return None }
+        Some([x as u32, y as u32])
+    }
+
+    /// スクリプトのコンパイル
+    ///
+    /// コンパイルできなかったスクリプトは記録して除く。
+    pub fn compile_scripts(&self) -> BTreeMap<String, script::ScriptHandle> {
+        self.scripts.iter()
+            .filter_map(|(name, source)| match script::compile(name, source) {
+                Ok(s) => Some((name.clone(), s)), 
+                Err(e) => {
+                    log::error!("{e}");
+                    None
+                }, 
+            })
+            .collect()
+    }
+
+    /// ブロックのスポーン用パラメータへの変換
+    pub fn spawn_param(&self) -> BrickSpawnParam<
+        [f32; 2], 
+        [f32; 2], 
+        LevelSpawnFn, 
+        BrickFeature, 
+    > {
+        let bricks = self.bricks.clone();
+        let scripts = self.compile_scripts();
+        let row_motions = self.row_motions.clone();
+        let (row, pitch) = (self.row, self.brick_size[0] + self.brick_margin[0]);
+        BrickSpawnParam {
+            column: self.column, 
+            row: self.row, 
+            margin_top: self.margin_top, 
+            brick_margin: self.brick_margin, 
+            brick_size: self.brick_size, 
+            spawn_f: Arc::new(Mutex::new(Box::new(move |
+                pos: [u32; 2], 
+                blk_pos: nalgebra::Point2<f32>, 
+                blk_size: nalgebra::Vector2<f32>, 
+            | {
+                bricks.get(pos[1] as usize)
+                    .and_then(|r| r.get(pos[0] as usize))
+                    .and_then(|b| b.as_ref())
+                    .map(|b| {
+                        // ブロックの動きは自身を、段の動きは段の中心を回転の軸にする
+                        let motion = b.motion.map(|m| BrickMotion::new(m, blk_pos, blk_pos, b.angle))
+                            .or_else(|| row_motions.get(&pos[1]).map(|m| {
+                                let offset = (pos[0] as f32 - (row as f32 - 1.) / 2.) * pitch;
+                                let pivot = [blk_pos.x - offset, blk_pos.y].into();
+                                BrickMotion::new(*m, blk_pos, pivot, b.angle)
+                            }));
+                        Brick::spawn(
+                            BrickFeature::new(b.score, b.blk_type)
+                                .with_script(b.script.as_ref().and_then(|s| scripts.get(s).cloned())), 
+                            blk_pos, 
+                            blk_size, 
+                            b.color, 
+                        ).with_shape(b.shape, b.angle).with_motion(motion)
+                    })
+            }))), 
+            obstacles: self.obstacles.clone(), 
+            enemies: self.enemies.clone(), 
+            boss: self.boss.as_deref().cloned().map(Arc::new), 
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_rejects_empty_grid() {
+        let path = std::env::temp_dir().join(format!("breakout-empty-grid-{0}.ron", std::process::id()));
+        let level = LevelDef { row: 0, bricks: Vec::new(), ..LevelDef::builtin() };
+        level.save(&path).unwrap();
+        let loaded = LevelDef::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+        LevelDef::builtin().save(&path).unwrap();
+        let loaded = LevelDef::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), LevelDef::builtin());
+    }
+}
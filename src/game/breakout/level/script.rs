@@ -0,0 +1,230 @@
+//! ブロックのスクリプト
+//!
+//! ステージの定義の`scripts`に名前を付けてRhaiのスクリプトを書き、
+//! ブロックの`script`でその名前を指定する。スクリプトには次の関数を定義でき、
+//! `this`からブロックの状況の参照と操作ができる。
+//!
+//! ```ron
+//! scripts: {
+//!     "bomb": "fn on_destroy() { this.destroy(-1, 0); this.destroy(1, 0); }",
+//!     "lucky": "fn on_hit() { if this.remaining % 5 == 0 { this.power_up(\"wide\"); } }",
+//! },
+//! ```
+//!
+//! - `on_hit()` : ボールが当たった時
+//! - `on_destroy()` : 壊れた時
+//! - `on_tick()` : 毎ティック
+//!
+//! スクリプトの実行は操作の回数などを制限し、エラーは記録してその関数を以降呼ばない。
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use serde::{Serialize, Deserialize};
+
+use super::super::entities::{
+    brick::{BrickEffect, brick::{BrickCtx, BrickEvent}},
+    powerup::PowerUpKind,
+};
+use super::super::state::{BreakOutGameState, BreakOutDifficulity};
+
+/// 1回の呼び出しでの操作の回数の上限
+const MAX_OPERATIONS: u64 = 50_000;
+
+/// 1回の呼び出しでの命令の数の上限
+const MAX_COMMANDS: usize = 32;
+
+/// スクリプトからの命令
+#[derive(Clone, Debug)]
+enum Command {
+    AddScore(u64),
+    Difficulty(BreakOutDifficulity),
+    Effect(BrickEffect),
+}
+
+/// スクリプトの`this`
+#[derive(Clone)]
+struct ScriptCtx {
+    x: i64,
+    y: i64,
+    tick: i64,
+    score: i64,
+    difficulty: BreakOutDifficulity,
+    remaining: i64,
+    commands: Vec<Command>,
+}
+impl ScriptCtx {
+    fn push(&mut self, command: Command) -> Result<(), Box<rhai::EvalAltResult>> {
+        if self.commands.len() >= MAX_COMMANDS {
+            return Err(format!("more than {MAX_COMMANDS} commands in one call").into())
+        }
+        self.commands.push(command);
+        Ok(())
+    }
+}
+
+/// サンドボックス化したエンジン
+///
+/// モジュールの読み込みと`eval`を禁止し、`print`と`debug`は記録に流す。
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(16)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_max_array_size(1024)
+        .set_max_map_size(256)
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .on_print(|s| log::info!("brick script: {s}"))
+        .on_debug(|s, _, pos| log::debug!("brick script {pos}: {s}"))
+        .disable_symbol("eval");
+    engine.register_type_with_name::<ScriptCtx>("Brick")
+        .register_get("x", |c: &mut ScriptCtx| c.x)
+        .register_get("y", |c: &mut ScriptCtx| c.y)
+        .register_get("tick", |c: &mut ScriptCtx| c.tick)
+        .register_get("score", |c: &mut ScriptCtx| c.score)
+        .register_get("difficulty", |c: &mut ScriptCtx| c.difficulty.name().to_lowercase())
+        .register_get("remaining", |c: &mut ScriptCtx| c.remaining)
+        .register_fn("add_score", |c: &mut ScriptCtx, n: i64| {
+            if n < 0 { return Err("score can not be reduced".into()) }
+            c.push(Command::AddScore(n as u64))
+        })
+        .register_fn("set_difficulty", |c: &mut ScriptCtx, name: &str| {
+            let difficulty = match name.to_lowercase().as_str() {
+                "easy" => BreakOutDifficulity::Easy,
+                "normal" => BreakOutDifficulity::Normal,
+                "hard" => BreakOutDifficulity::Hard,
+                _ => return Err(format!("unknown difficulty {name:?}").into()),
+            };
+            c.push(Command::Difficulty(difficulty))
+        })
+        .register_fn("power_up", |c: &mut ScriptCtx, name: &str| {
+            let kind = PowerUpKind::from_name(name)
+                .ok_or_else(|| format!("unknown power-up {name:?}"))?;
+            c.push(Command::Effect(BrickEffect::PowerUp(kind)))
+        })
+        .register_fn("destroy", |c: &mut ScriptCtx, dx: i64, dy: i64| {
+            c.push(Command::Effect(BrickEffect::Destroy(offset(dx, dy)?)))
+        })
+        .register_fn("recolor", |c: &mut ScriptCtx, dx: i64, dy: i64, r: f64, g: f64, b: f64| {
+            let color = [r as f32, g as f32, b as f32, 1.];
+            c.push(Command::Effect(BrickEffect::Recolor(offset(dx, dy)?, color)))
+        });
+    engine
+}
+
+/// 周囲のブロックへの相対位置
+fn offset(dx: i64, dy: i64) -> Result<[i32; 2], Box<rhai::EvalAltResult>> {
+    match (i32::try_from(dx), i32::try_from(dy)) {
+        (Ok(dx), Ok(dy)) => Ok([dx, dy]),
+        _ => Err(format!("offset ({dx}, {dy}) is out of range").into()),
+    }
+}
+
+/// フックの関数名
+fn hook_name(event: BrickEvent) -> &'static str { match event {
+    BrickEvent::Hit => "on_hit",
+    BrickEvent::Destroy => "on_destroy",
+    BrickEvent::Tick => "on_tick",
+}}
+
+/// コンパイル済みのスクリプト
+pub struct BrickScript {
+    name: String,
+    source: String,
+    engine: Engine,
+    ast: AST,
+    /// `on_hit`、`on_destroy`、`on_tick`の定義の有無
+    hooks: [bool; 3],
+    /// エラーにより呼ばなくなったフック
+    failed: [AtomicBool; 3],
+}
+impl BrickScript {
+    /// スクリプトの実行
+    ///
+    /// スコアと難易度は`state`に反映し、周囲への作用を返す。
+    pub fn run(
+        &self,
+        event: BrickEvent,
+        ctx: &BrickCtx,
+        state: &mut BreakOutGameState,
+    ) -> Vec<BrickEffect> {
+        let i = event as usize;
+        if !self.hooks[i] || self.failed[i].load(Ordering::Relaxed) { return Vec::new() }
+        let mut this = Dynamic::from(ScriptCtx {
+            x: ctx.cell[0] as i64,
+            y: ctx.cell[1] as i64,
+            tick: ctx.tick as i64,
+            score: *state.score.lock() as i64,
+            difficulty: state.difficulity,
+            remaining: ctx.remaining as i64,
+            commands: Vec::new(),
+        });
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut this),
+            &mut Scope::new(),
+            &self.ast,
+            hook_name(event),
+            (),
+        );
+        if let Err(e) = result {
+            // 同じエラーを繰り返し記録しないよう以降は呼ばない
+            self.failed[i].store(true, Ordering::Relaxed);
+            log::warn!("brick script {0:?} {1} disabled: {e}", self.name, hook_name(event));
+            return Vec::new()
+        }
+        let Some(ctx) = this.try_cast::<ScriptCtx>() else { return Vec::new() };
+        ctx.commands.into_iter()
+            .filter_map(|c| match c {
+                Command::AddScore(n) => {
+                    *state.score.lock() += n;
+                    None
+                },
+                Command::Difficulty(d) => {
+                    state.difficulity = d;
+                    None
+                },
+                Command::Effect(e) => Some(e),
+            })
+            .collect()
+    }
+}
+
+/// スクリプトの共有用のハンドル
+///
+/// セーブデータには名前とソースを書き出し、読み込み時にコンパイルし直す。
+#[derive(Clone)]
+pub struct ScriptHandle(Arc<BrickScript>);
+impl std::ops::Deref for ScriptHandle {
+    type Target = BrickScript;
+    fn deref(&self) -> &Self::Target { &self.0 }
+}
+impl Serialize for ScriptHandle {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.0.name, &self.0.source).serialize(serializer)
+    }
+}
+impl<'de> Deserialize<'de> for ScriptHandle {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (name, source) = <(String, String)>::deserialize(deserializer)?;
+        compile(name, source).map_err(serde::de::Error::custom)
+    }
+}
+
+/// スクリプトのコンパイル
+pub fn compile(name: impl Into<String>, source: impl Into<String>) -> anyhow::Result<ScriptHandle> {
+    let (name, source) = (name.into(), source.into());
+    let engine = engine();
+    let ast = engine.compile(&source)
+        .map_err(|e| anyhow::anyhow!("script {name:?}: {e}"))?;
+    let hooks = [BrickEvent::Hit, BrickEvent::Destroy, BrickEvent::Tick]
+        .map(|e| ast.iter_functions().any(|f| f.name == hook_name(e) && f.params.is_empty()));
+    Ok(ScriptHandle(Arc::new(BrickScript {
+        name,
+        source,
+        engine,
+        ast,
+        hooks,
+        failed: Default::default(),
+    })))
+}
@@ -0,0 +1,336 @@
+//! ステージの検証と分析
+
+use std::collections::VecDeque;
+
+use super::{LevelDef, BrickType};
+use super::super::entities::ball::BALL_SIZE;
+use super::super::entities::paddle::PADDLE_LINE;
+
+/// ステージを遊ぶ画面の大きさ
+pub const FIELD_SIZE: [f32; 2] = [640., 640.];
+
+/// 問題の重大さ
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning, 
+    Error, 
+}
+
+/// 検出した問題
+///
+/// 位置は`[x, y]`(yは下の段から)。
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// 段数か1段あたりの数が0で、ブロックを配置できない(`[row, column]`)
+    EmptyGrid([u32; 2]), 
+    /// 2つのブロックが重なっている
+    Overlap([u32; 2], [u32; 2]), 
+    /// ブロックが画面の外にはみ出している
+    OutOfField([u32; 2]), 
+    /// 壊れないブロックに囲まれてボールが届かない
+    Enclosed([u32; 2]), 
+    /// 壊せるブロックが1つもない
+    NoClearable, 
+    /// 段数や1段あたりの数を超えて定義され、無視されるブロック
+    Ignored(usize), 
+    /// ブロックが`scripts`に無いスクリプトを指定している
+    UnknownScript([u32; 2], String), 
+    /// スクリプトをコンパイルできない
+    Script(String), 
+}
+impl Issue {
+    pub fn severity(&self) -> Severity { match self {
+        Self::Ignored(_) => Severity::Warning, 
+        _ => Severity::Error, 
+    }}
+}
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { match self {
+        Self::EmptyGrid(g) => write!(f, "brick grid {0}x{1} has no cells", g[0], g[1]), 
+        Self::Overlap(a, b) => write!(f, "bricks at ({0}, {1}) and ({2}, {3}) overlap", a[0], a[1], b[0], b[1]), 
+        Self::OutOfField(p) => write!(
+            f, 
+            "brick at ({0}, {1}) is outside the {2}x{3} field", 
+            p[0], p[1], FIELD_SIZE[0], FIELD_SIZE[1]
+        ), 
+        Self::Enclosed(p) => write!(f, "brick at ({0}, {1}) is enclosed by solid bricks", p[0], p[1]), 
+        Self::NoClearable => write!(f, "no clearable bricks"), 
+        Self::Ignored(n) => write!(f, "{n} bricks beyond the grid are ignored"), 
+        Self::UnknownScript(p, name) => write!(f, "brick at ({0}, {1}) uses unknown script {name:?}", p[0], p[1]), 
+        Self::Script(e) => write!(f, "{e}"), 
+    }}
+}
+
+/// ステージの統計
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelStats {
+    /// 配置されたブロックの数
+    pub bricks: usize, 
+    /// 壊してクリアに必要なブロックの数
+    pub clearable: usize, 
+    pub solid: usize, 
+    /// 全て壊した場合のスコア
+    pub total_score: u64, 
+    /// 最も低いブロックの下端の高さ
+    pub lowest: Option<f32>, 
+    /// 推定のクリアの難しさ(0.0 - 1.0)
+    pub difficulty: f32, 
+}
+impl LevelStats {
+    /// 難しさの目安
+    pub fn rating(&self) -> &'static str {
+        if self.difficulty < 0.3 { "Easy" }
+        else if self.difficulty < 0.5 { "Normal" }
+        else if self.difficulty < 0.7 { "Hard" }
+        else { "Extreme" }
+    }
+}
+
+/// 検証の結果
+#[derive(Clone, Debug)]
+pub struct LevelReport {
+    pub issues: Vec<Issue>, 
+    pub stats: LevelStats, 
+}
+impl LevelReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.issues.iter().filter(|i| i.severity() == severity).count()
+    }
+    pub fn has_errors(&self) -> bool { self.count(Severity::Error) != 0 }
+}
+
+/// ステージの検証
+pub fn validate(level: &LevelDef) -> LevelReport {
+    let field = nalgebra::Vector2::from(FIELD_SIZE);
+    let size = nalgebra::Vector2::from(level.brick_size);
+    let placed = (0..level.column)
+        .flat_map(|y| (0..level.row).map(move |x| [x, y]))
+        .filter_map(|p| level.bricks.get(p[1] as usize)
+            .and_then(|r| r.get(p[0] as usize))
+            .and_then(|b| b.as_ref())
+            .map(|b| (p, b, level.brick_center(field, p)))
+        )
+        .collect::<Vec<_>>();
+    let mut issues = Vec::new();
+
+    if level.column == 0 || level.row == 0 {
+        issues.push(Issue::EmptyGrid([level.row, level.column]));
+    }
+
+    let defined = level.bricks.iter().flatten().filter(|b| b.is_some()).count();
+    if defined > placed.len() {
+        issues.push(Issue::Ignored(defined - placed.len()));
+    }
+
+    for (i, (a, _, pa)) in placed.iter().enumerate() {
+        for (b, _, pb) in placed[i + 1..].iter() {
+            if (pa.x - pb.x).abs() < size.x && (pa.y - pb.y).abs() < size.y {
+                issues.push(Issue::Overlap(*a, *b));
+            }
+        }
+    }
+
+    for (p, _, center) in placed.iter() {
+        let (min, max) = (center - size / 2., center + size / 2.);
+        if min.x < 0. || min.y < 0. || max.x > field.x || max.y > field.y {
+            issues.push(Issue::OutOfField(*p));
+        }
+    }
+
+    let reachable = reachable(level);
+    for (p, b, _) in placed.iter() {
+        if b.blk_type != BrickType::Solid && !reachable[p[1] as usize][p[0] as usize] {
+            issues.push(Issue::Enclosed(*p));
+        }
+    }
+
+    for (name, source) in level.scripts.iter() {
+        if let Err(e) = super::script::compile(name, source) {
+            issues.push(Issue::Script(e.to_string()));
+        }
+    }
+    for (p, b, _) in placed.iter() {
+        if let Some(name) = b.script.as_ref().filter(|s| !level.scripts.contains_key(*s)) {
+            issues.push(Issue::UnknownScript(*p, name.clone()));
+        }
+    }
+
+    let clearable = placed.iter().filter(|(_, b, _)| b.blk_type != BrickType::Solid).count();
+    // ボスのいるステージはブロックが無くてもボスを倒せばクリアとなる
+    if clearable == 0 && level.boss.is_none() {
+        issues.push(Issue::NoClearable);
+    }
+
+    let lowest = placed.iter()
+        .map(|(_, _, c)| c.y - size.y / 2.)
+        .reduce(f32::min);
+    let stats = LevelStats {
+        bricks: placed.len(), 
+        clearable, 
+        solid: placed.len() - clearable, 
+        total_score: placed.iter()
+            .filter(|(_, b, _)| b.blk_type != BrickType::Solid)
+            .map(|(_, b, _)| b.score)
+            .sum(), 
+        lowest, 
+        difficulty: difficulty(level, lowest), 
+    };
+    LevelReport { issues, stats }
+}
+
+/// 各位置にボールが届くか
+///
+/// グリッドの外側から、壊れないブロックを避けて上下左右に辿れる位置を届くものとする。
+/// ブロックの間隔がボールより広い場合は全ての位置に届く。
+fn reachable(level: &LevelDef) -> Vec<Vec<bool>> {
+    let (w, h) = (level.row as usize, level.column as usize);
+    let solid = |x: usize, y: usize| level.bricks.get(y)
+        .and_then(|r| r.get(x))
+        .and_then(|b| b.as_ref())
+        .is_some_and(|b| b.blk_type == BrickType::Solid);
+    if level.brick_margin[0] >= BALL_SIZE || level.brick_margin[1] >= BALL_SIZE {
+        return vec![vec![true; w]; h]
+    }
+    // 外周に1マスずつ空きを加えたグリッドで辿る
+    let mut visited = vec![vec![false; w + 2]; h + 2];
+    let mut queue = VecDeque::from([(0usize, 0usize)]);
+    visited[0][0] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbors = [
+            (x.wrapping_sub(1), y), 
+            (x + 1, y), 
+            (x, y.wrapping_sub(1)), 
+            (x, y + 1), 
+        ];
+        for (nx, ny) in neighbors {
+            if nx > w + 1 || ny > h + 1 || visited[ny][nx] { continue }
+            let inner = (1..=w).contains(&nx) && (1..=h).contains(&ny);
+            if inner && solid(nx - 1, ny - 1) { continue }
+            visited[ny][nx] = true;
+            queue.push_back((nx, ny));
+        }
+    }
+    visited[1..=h].iter()
+        .map(|r| r[1..=w].to_vec())
+        .collect()
+}
+
+/// クリアの難しさの推定
+///
+/// 種類で重み付けしたブロックの密度を半分、ブロックの小ささと
+/// パドルへの近さをそれぞれ4分の1として合わせたもの。
+fn difficulty(level: &LevelDef, lowest: Option<f32>) -> f32 {
+    let density = super::generator::difficulty(level);
+    let area = level.brick_size[0] * level.brick_size[1];
+    let small = (1. - area / 576.).clamp(0., 1.);
+    let near = lowest.map_or(0., |l| (1. - (l - PADDLE_LINE) / 400.).clamp(0., 1.));
+    density * 0.5 + small * 0.25 + near * 0.25
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::BrickDef;
+    use super::super::super::entities::boss::BossDef;
+
+    fn boss() -> Box<BossDef> {
+        Box::new(BossDef {
+            hp: 3, 
+            position: [320., 480.], 
+            size: [64., 32.], 
+            color: [1., 1., 1., 1.], 
+            score: 1000, 
+            weak_points: Vec::new(), 
+            phases: Vec::new(), 
+        })
+    }
+
+    /// 全ての位置を`blk_type`で埋めたステージ
+    fn filled(column: u32, row: u32, blk_type: BrickType) -> LevelDef {
+        let brick = BrickDef {
+            blk_type, 
+            ..LevelDef::builtin().bricks[0][0].clone().unwrap()
+        };
+        LevelDef {
+            column, 
+            row, 
+            bricks: vec![vec![Some(brick); row as usize]; column as usize], 
+            ..LevelDef::builtin()
+        }
+    }
+
+    #[test]
+    fn builtin_level_has_no_issues() {
+        assert_eq!(validate(&LevelDef::builtin()).issues, Vec::new());
+    }
+
+    #[test]
+    fn overlapping_bricks() {
+        let level = LevelDef { brick_margin: [-4., 4.], ..LevelDef::builtin() };
+        let issues = validate(&level).issues;
+        assert!(issues.contains(&Issue::Overlap([0, 0], [1, 0])));
+        assert!(!issues.contains(&Issue::Overlap([0, 0], [0, 1])));
+    }
+
+    #[test]
+    fn bricks_outside_the_field() {
+        let level = filled(5, 30, BrickType::Normal);
+        let issues = validate(&level).issues;
+        assert!(issues.contains(&Issue::OutOfField([0, 0])));
+        assert!(issues.contains(&Issue::OutOfField([29, 4])));
+        assert!(!issues.contains(&Issue::OutOfField([15, 0])));
+    }
+
+    #[test]
+    fn brick_enclosed_by_solid_bricks() {
+        let mut level = filled(3, 3, BrickType::Solid);
+        level.bricks[1][1].as_mut().unwrap().blk_type = BrickType::Normal;
+        assert_eq!(validate(&level).issues, vec![Issue::Enclosed([1, 1])]);
+        // 間隔がボールより広ければ通り抜けられる
+        level.brick_margin = [BALL_SIZE, BALL_SIZE];
+        assert_eq!(validate(&level).issues, Vec::new());
+    }
+
+    #[test]
+    fn no_clearable_bricks_without_a_boss() {
+        let mut level = filled(2, 4, BrickType::Solid);
+        assert_eq!(validate(&level).issues, vec![Issue::NoClearable]);
+        level.boss = Some(boss());
+        assert_eq!(validate(&level).issues, Vec::new());
+    }
+
+    #[test]
+    fn bricks_beyond_the_grid_are_ignored() {
+        let level = LevelDef { column: 3, ..LevelDef::builtin() };
+        let report = validate(&level);
+        assert_eq!(report.issues, vec![Issue::Ignored(48)]);
+        assert_eq!(report.issues[0].severity(), Severity::Warning);
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn unknown_and_broken_scripts() {
+        let mut level = LevelDef::builtin();
+        level.bricks[2][3].as_mut().unwrap().script = Some("missing".into());
+        level.scripts.insert("broken".into(), "fn hit( {".into());
+        let issues = validate(&level).issues;
+        assert!(issues.contains(&Issue::UnknownScript([3, 2], "missing".into())));
+        assert!(issues.iter().any(|i| matches!(i, Issue::Script(e) if e.contains("broken"))));
+    }
+
+    #[test]
+    fn empty_grid_is_an_error_even_with_a_boss() {
+        for (column, row) in [(0, 24), (5, 0), (0, 0)] {
+            let level = LevelDef {
+                column, 
+                row, 
+                bricks: Vec::new(), 
+                boss: Some(boss()), 
+                ..LevelDef::builtin()
+            };
+            let report = validate(&level);
+            assert!(report.issues.contains(&Issue::EmptyGrid([row, column])), "{column}x{row}");
+            assert!(report.has_errors());
+        }
+    }
+}
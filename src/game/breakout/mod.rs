@@ -4,6 +4,7 @@
 pub mod obj_renderer;
 
 /// テキストのレンダラ
+#[cfg(feature = "client")]
 pub mod text_renderer;
 
 /// 状態
@@ -12,13 +13,101 @@ pub mod state;
 /// エンティティ
 pub mod entities;
 
+/// ステージの定義
+pub mod level;
+
+/// ゲーム画面上の情報表示
+pub mod hud;
+
+/// キャンペーン
+pub mod campaign;
+
+/// ステージパック
+pub mod pack;
+
+/// エンドレスモード
+pub mod endless;
+
+/// 日替わりのチャレンジステージ
+pub mod daily;
+
+/// タイムアタック
+pub mod time_attack;
+
+/// 2人対戦
+pub mod versus;
+
+/// 2人協力
+pub mod coop;
+
+/// コンピュータによるパドルの操作
+pub mod autopilot;
+
+/// 強化学習向けの環境
+pub mod env;
+
+/// 中断したゲームの保存と再開
+pub mod save;
+
+/// 練習モードの巻き戻し
+pub mod rewind;
+
+/// 自己ベストのゴースト
+pub mod ghost;
+
+/// タイムアタックの表示色
+#[cfg(feature = "client")]
+const SPLIT_AHEAD_COLOR: [f32; 4] = [0.4, 1., 0.4, 1.];
+#[cfg(feature = "client")]
+const SPLIT_BEHIND_COLOR: [f32; 4] = [1., 0.4, 0.4, 1.];
+#[cfg(feature = "client")]
+const SPLIT_RECORD_COLOR: [f32; 4] = [1., 0.85, 0.2, 1.];
+
+/// デモプレイの終了後にタイトル画面へ戻るまでのティック数
+#[cfg(feature = "client")]
+const DEMO_END_TICKS: u32 = 180;
+
+#[cfg(feature = "client")]
 pub struct BreakOut<BF: entities::brick::brick::BrickFeature> {
     text: text_renderer::BreakOutGameTextRenderer, 
     renderer: obj_renderer::BreakOutRenderer, 
+    /// 障害物の描画
+    d2d: super::default_2d_renderer::D2DRenderer, 
     state: state::BreakOutGameState, 
     entities: entities::BreakOutEntities<BF>, 
+    life_hud: hud::LifeHud, 
+    boss_hud: hud::BossHud, 
+    /// ハイスコアの記録先
+    record: Option<super::highscore::ScoreTarget>, 
+    /// 遊んでいるゲームモード
+    mode: super::highscore::GameMode, 
+    /// 単独のステージとして遊ぶ場合のステージの定義
+    level: Option<level::LevelDef>, 
+    /// 進行中のキャンペーン
+    campaign: Option<campaign::CampaignRun>, 
+    /// 進行中のエンドレスモード
+    endless: Option<endless::Endless<BF>>, 
+    /// 進行中のタイムアタック
+    time_attack: Option<time_attack::TimeAttackRun>, 
+    /// コンピュータによる操作
+    autopilot: Option<autopilot::Autopilot>, 
+    /// デモプレイの場合は終了してからのティック数
+    demo: Option<u32>, 
+    /// 練習モードの巻き戻し
+    rewind: Option<rewind::Rewind<BF>>, 
+    /// 巻き戻しのキーを押しているか
+    rewinding: bool, 
+    /// エディタからの試遊か
+    playtest: bool, 
+    /// 自己ベストと比べるための入力の記録
+    recorder: Option<ghost::GhostRecorder>, 
+    /// 再生中の自己ベストのゴースト
+    ghost: Option<ghost::Ghost>, 
     to_pause: bool, 
+    to_next: bool, 
+    to_exit: bool, 
 }
+#[cfg(feature = "client")]
 impl<BF: entities::brick::brick::BrickFeature> BreakOut<BF> {
     pub fn new(
         gfx_ctx: &crate::gfx::WGContext, 
@@ -33,9 +122,80 @@ impl<BF: entities::brick::brick::BrickFeature> BreakOut<BF> {
             ) -> Option<entities::brick::Brick<BF>>, 
             BF
         >
+    ) -> anyhow::Result<Self> {
+        Self::with_state(
+            gfx_ctx, 
+            text_glyph, 
+            brick_param, 
+            state::BreakOutGameState::new()
+        )
+    }
+    /// コンピュータに操作させる
+    ///
+    /// `demo`の場合は何か入力があるか、終了してしばらく経つと戻る。
+    /// いずれの場合もハイスコアは記録しない。
+    pub fn with_autopilot(mut self, autopilot: autopilot::Autopilot, demo: bool) -> Self {
+        self.record = None;
+        self.recorder = None;
+        if let Some(entry) = self.text.entry_mut("bottom") {
+            entry.text_mut()[1].text = format!(" AI : {0} ", autopilot.skill().name()).into();
+            entry.text_mut()[2].text = if demo {
+                " DEMO - Press any key ".into()
+            } else {
+                " Tab : Change AI ".into()
+            };
+        }
+        self.autopilot = Some(autopilot);
+        self.demo = demo.then_some(0);
+        self
+    }
+    /// 練習モードにする
+    ///
+    /// ハイスコアは記録せず、Rキーを押している間は巻き戻せる。
+    pub fn with_practice(mut self) -> Self {
+        self.record = None;
+        self.recorder = None;
+        if let Some(entry) = self.text.entry_mut("top") {
+            entry.text_mut()[0].text = " Practice ".into();
+        }
+        if let Some(entry) = self.text.entry_mut("timer") {
+            entry.text_mut()[1].text = " R : Rewind ".into();
+        }
+        self.rewind = Some(rewind::Rewind::new(rewind::REWIND_CAPACITY));
+        self
+    }
+    /// エディタからの試遊にする
+    ///
+    /// ハイスコアは記録せず、Backspaceキーでいつでもエディタへ戻る。
+    pub fn with_playtest(mut self) -> Self {
+        self.record = None;
+        self.recorder = None;
+        self.playtest = true;
+        if let Some(entry) = self.text.entry_mut("top") {
+            entry.text_mut()[0].text = " Playtest ".into();
+        }
+        if let Some(entry) = self.text.entry_mut("bottom") {
+            entry.text_mut()[0].text = " Backspace : Editor ".into();
+        }
+        self
+    }
+    /// 既存の状態を引き継いで生成する
+    pub fn with_state(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        brick_param: entities::brick::BrickSpawnParam<
+            impl Into<nalgebra::Vector2<f32>>, 
+            impl Into<nalgebra::Vector2<f32>>, 
+            impl FnMut(
+                [u32; 2], 
+                nalgebra::Point2<f32>, 
+                nalgebra::Vector2<f32>, 
+            ) -> Option<entities::brick::Brick<BF>>, 
+            BF
+        >, 
+        state: state::BreakOutGameState, 
     ) -> anyhow::Result<Self> {
         let renderer = obj_renderer::BreakOutRenderer::new(gfx_ctx)?;
-        let state = state::BreakOutGameState::new();
         let entities = entities::BreakOutEntities::new(
             brick_param, 
             [
@@ -48,14 +208,551 @@ impl<BF: entities::brick::brick::BrickFeature> BreakOut<BF> {
         )?;
         Ok(Self {
             renderer, 
+            d2d: super::default_2d_renderer::D2DRenderer::new(gfx_ctx), 
             state, 
             entities, 
             text, 
+            life_hud: hud::LifeHud::new(), 
+            boss_hud: hud::BossHud::new(), 
+            record: None, 
+            mode: super::highscore::GameMode::Normal, 
+            level: None, 
+            campaign: None, 
+            endless: None, 
+            time_attack: None, 
+            autopilot: None, 
+            demo: None, 
+            rewind: None, 
+            rewinding: false, 
+            playtest: false, 
+            recorder: None, 
+            ghost: None, 
             to_pause: false, 
+            to_next: false, 
+            to_exit: false, 
+        })
+    }
+}
+#[cfg(feature = "client")]
+impl BreakOut<level::BrickFeature> {
+    /// ステージの定義から生成する
+    pub fn from_level(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        level: &level::LevelDef, 
+        mode: super::highscore::GameMode, 
+    ) -> anyhow::Result<Self> {
+        Self::from_level_with_state(
+            gfx_ctx, 
+            text_glyph, 
+            level, 
+            mode, 
+            state::BreakOutGameState::new()
+        )
+    }
+    /// ステージの定義と既存の状態から生成する
+    pub fn from_level_with_state(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        level: &level::LevelDef, 
+        mode: super::highscore::GameMode, 
+        state: state::BreakOutGameState, 
+    ) -> anyhow::Result<Self> {
+        let mut breakout = Self::with_state(gfx_ctx, text_glyph, level.spawn_param(), state)?;
+        breakout.state.set_extra_life(level.extra_life);
+        breakout.record = Some(super::highscore::ScoreTarget::new(mode, level));
+        breakout.mode = mode;
+        breakout.level = Some(level.clone());
+        breakout.recorder = Some(ghost::GhostRecorder::new(level, &breakout.state));
+        Ok(breakout)
+    }
+    /// キャンペーンの1ステージとして生成する
+    pub fn from_campaign(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        run: campaign::CampaignRun, 
+        state: state::BreakOutGameState, 
+    ) -> anyhow::Result<Self> {
+        let mut breakout = Self::from_level_with_state(
+            gfx_ctx, 
+            text_glyph, 
+            run.level(), 
+            super::highscore::GameMode::Campaign, 
+            state
+        )?;
+        breakout.record = Some(run.def.score_target(super::highscore::GameMode::Campaign));
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = format!(
+                " Stage {0}/{1} ", 
+                run.stage + 1, 
+                run.def.stages.len()
+            ).into();
+        }
+        breakout.campaign = Some(run);
+        Ok(breakout)
+    }
+    /// エンドレスモードとして生成する
+    pub fn from_endless(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        seed: u64, 
+        param: endless::EndlessParam, 
+    ) -> anyhow::Result<Self> {
+        let endless = endless::Endless::new(
+            seed, 
+            param, 
+            [
+                gfx_ctx.size.width as f32, 
+                gfx_ctx.size.height as f32, 
+            ].into()
+        );
+        let mut breakout = Self::with_state(
+            gfx_ctx, 
+            text_glyph, 
+            endless.spawn_param(), 
+            state::BreakOutGameState::new()
+        )?;
+        breakout.entities.set_clear_on_empty(false);
+        breakout.record = Some(endless.score_target());
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = " Endless ".into();
+        }
+        log::info!("endless seed: {0}", endless.seed());
+        breakout.endless = Some(endless);
+        Ok(breakout)
+    }
+    /// 日替わりステージとして生成する
+    ///
+    /// `scored`が`false`の場合は練習としてハイスコアに記録しない。
+    pub fn from_daily(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        daily: &daily::DailyChallenge, 
+        scored: bool, 
+    ) -> anyhow::Result<Self> {
+        let mut breakout = Self::from_level(
+            gfx_ctx, 
+            text_glyph, 
+            &daily.level, 
+            super::highscore::GameMode::Daily, 
+        )?;
+        breakout.record = scored.then(|| daily.score_target());
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = if scored {
+                format!(" Daily {0} ", daily.date.format("%m/%d")).into()
+            } else {
+                " Daily (Practice) ".into()
+            };
+        }
+        Ok(breakout)
+    }
+    /// 2人対戦として生成する
+    pub fn from_versus(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        input: versus::VersusInput, 
+    ) -> anyhow::Result<Self> {
+        let disp_size = [
+            gfx_ctx.size.width as f32, 
+            gfx_ctx.size.height as f32, 
+        ].into();
+        let level = versus::level(disp_size);
+        let mut breakout = Self::with_state(
+            gfx_ctx, 
+            text_glyph, 
+            level.spawn_param(), 
+            state::BreakOutGameState::new()
+        )?;
+        breakout.state.set_extra_life(level.extra_life);
+        breakout.state.set_players(2, state::LivesRule::PerPlayer, versus::VERSUS_BALLS);
+        breakout.entities.set_paddles(versus::paddles(disp_size, input));
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = " Versus ".into();
+        }
+        if let Some(entry) = breakout.text.entry_mut("bottom") {
+            let (p1_move, p1_shot) = match input {
+                versus::VersusInput::Keys => ("AD", "W"), 
+                versus::VersusInput::KeyboardMouse => ("Cursor", "Left-click"), 
+            };
+            entry.text_mut()[1].text = format!(" 1P {p1_move} / 2P Arrows : Move ").into();
+            entry.text_mut()[2].text = format!(" 1P {p1_shot} / 2P Down : Shoot ").into();
+        }
+        Ok(breakout)
+    }
+    /// 2人協力として生成する
+    pub fn from_coop(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        level: &level::LevelDef, 
+    ) -> anyhow::Result<Self> {
+        let mut breakout = Self::with_state(
+            gfx_ctx, 
+            text_glyph, 
+            level.spawn_param(), 
+            state::BreakOutGameState::new()
+        )?;
+        breakout.state.set_extra_life(level.extra_life);
+        breakout.state.set_players(2, state::LivesRule::Shared, coop::COOP_BALLS);
+        breakout.entities.set_paddles(coop::paddles([
+            gfx_ctx.size.width as f32, 
+            gfx_ctx.size.height as f32, 
+        ].into()));
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = " Co-op ".into();
+        }
+        if let Some(entry) = breakout.text.entry_mut("bottom") {
+            entry.text_mut()[1].text = " 1P AD / 2P Cursor : Move ".into();
+            entry.text_mut()[2].text = " 1P Space / 2P Left-click : Shoot ".into();
+        }
+        Ok(breakout)
+    }
+    /// タイムアタックの1ステージとして生成する
+    pub fn from_time_attack(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        run: time_attack::TimeAttackRun, 
+        state: state::BreakOutGameState, 
+    ) -> anyhow::Result<Self> {
+        let level = &run.def.stages[run.stage];
+        let mut breakout = Self::with_state(gfx_ctx, text_glyph, level.spawn_param(), state)?;
+        breakout.state.set_extra_life(level.extra_life);
+        breakout.level = Some(level.clone());
+        breakout.recorder = Some(ghost::GhostRecorder::new(level, &breakout.state));
+        if let Some(entry) = breakout.text.entry_mut("top") {
+            entry.text_mut()[0].text = format!(
+                " Time Attack {0}/{1} ", 
+                run.stage + 1, 
+                run.def.stages.len()
+            ).into();
+        }
+        breakout.time_attack = Some(run);
+        Ok(breakout)
+    }
+    /// セーブデータから再開する
+    ///
+    /// キャンペーンとタイムアタックは`campaign`が保存時と同じ場合のみ再開できる。
+    pub fn from_save(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::util::text_renderer::TextRendererGMArc, 
+        data: &save::SaveData, 
+        campaign: Option<&std::sync::Arc<campaign::CampaignDef>>, 
+    ) -> anyhow::Result<Self> {
+        let find_campaign = |hash: u64, stage: usize| campaign
+            .filter(|c| c.hash() == hash && stage < c.stages.len())
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("campaign {hash:016x} is not available"));
+        let state = state::BreakOutGameState::new();
+        let mut breakout = match &data.mode {
+            save::SavedMode::Level { level, mode, record, title } => {
+                let mut breakout = Self::from_level_with_state(
+                    gfx_ctx, 
+                    text_glyph, 
+                    level, 
+                    *mode, 
+                    state
+                )?;
+                breakout.record = record.clone();
+                if let Some(entry) = breakout.text.entry_mut("top") {
+                    entry.text_mut()[0].text = title.clone().into();
+                }
+                breakout
+            }, 
+            save::SavedMode::Campaign { campaign_hash, stage } => Self::from_campaign(
+                gfx_ctx, 
+                text_glyph, 
+                campaign::CampaignRun::new(find_campaign(*campaign_hash, *stage)?, *stage), 
+                state, 
+            )?, 
+            save::SavedMode::TimeAttack { campaign_hash, stage, splits, ticks, penalties } => Self::from_time_attack(
+                gfx_ctx, 
+                text_glyph, 
+                time_attack::TimeAttackRun::resume(
+                    find_campaign(*campaign_hash, *stage)?, 
+                    *stage, 
+                    splits.clone(), 
+                    *ticks, 
+                    *penalties, 
+                ), 
+                state, 
+            )?, 
+        };
+        breakout.state = state::BreakOutGameState::from_save(&data.state);
+        breakout.entities.restore(&data.entities)?;
+        // 途中からのプレイは自己ベストと比べられない
+        breakout.recorder = None;
+        Ok(breakout)
+    }
+}
+#[cfg(feature = "client")]
+impl<BF: entities::brick::brick::BrickFeature + Clone> BreakOut<BF> {
+    /// 巻き戻しの記録と適用
+    ///
+    /// 巻き戻している間は`true`を返し、シミュレーションを止める。
+    fn update_rewind(&mut self) -> bool {
+        let Some(rewind) = self.rewind.as_mut() else { return false };
+        let rewinding = self.rewinding;
+        if !rewinding {
+            if self.state.state == state::GameState::Yes {
+                rewind.record(&self.entities, &self.state);
+            }
+        } else if let Some(snapshot) = rewind.step_back(rewind::REWIND_SPEED) {
+            // 押しているキーの状態は巻き戻さない
+            let inputs = self.entities.paddles().iter()
+                .map(|p| (p.player, p.move_flag))
+                .collect::<Vec<_>>();
+            self.entities = snapshot.entities;
+            self.state = snapshot.state;
+            for (player, flag) in inputs {
+                self.entities.set_input(player, flag);
+            }
+        }
+        let seconds = rewind.seconds();
+        if let Some(entry) = self.text.entry_mut("timer") {
+            entry.text_mut()[0].text = format!(" Rewind {seconds:.1}s ").into();
+            entry.text_mut()[2].text = if rewinding { " << Rewinding ".into() } else { "".into() };
+        }
+        rewinding
+    }
+
+    /// ゴーストの記録と再生
+    ///
+    /// `input`は今回のティックで用いたプレイヤーの入力。
+    fn update_ghost(
+        &mut self, 
+        state: &mut super::state::GameState, 
+        input: Option<entities::paddle::PaddleMoveFlag>, 
+        disp_size: nalgebra::Vector2<f32>, 
+    ) {
+        let Some(recorder) = self.recorder.as_mut() else { return };
+        if recorder.ticks() == 0 {
+            // 最初のティックで自己ベストを読み込む
+            self.ghost = self.level.as_ref().and_then(|level| state.ghosts
+                .get(recorder.level_hash())
+                .map(|r| ghost::Ghost::new(r.clone(), level, disp_size))
+            );
+        }
+        if let Some(input) = input { recorder.push(input) }
+        let Some(ghost) = self.ghost.as_mut() else { return self.finish_recording(state) };
+        ghost.step(disp_size);
+
+        let score = self.state.score.lock().saturating_sub(recorder.start_score());
+        let delta = score as i64 - ghost.score() as i64;
+        if let Some(entry) = self.text.entry_mut("top") {
+            let text = &mut entry.text_mut()[4];
+            text.text = format!(" Ghost {delta:+} ").into();
+            text.color = if delta >= 0 { SPLIT_AHEAD_COLOR } else { SPLIT_BEHIND_COLOR };
+        }
+        self.finish_recording(state)
+    }
+
+    /// ステージを終えていれば入力の記録を自己ベストとして残す
+    fn finish_recording(&mut self, state: &mut super::state::GameState) {
+        if self.state.state == state::GameState::Yes { return }
+        let Some(recorder) = self.recorder.take() else { return };
+        if state.ghosts.record(recorder.finish(&self.state)) {
+            if let Err(e) = state.ghosts.save() {
+                log::error!("failed to save ghost replay: {e}");
+            }
+        }
+    }
+
+    /// 中断するためのセーブデータの作成
+    ///
+    /// 1人で遊んでいる途中のステージのみ保存の対象とする。
+    /// 終わったゲームとコンピュータの操作は`None`を、再開できないモードはエラーを返す。
+    fn save_data(&self) -> anyhow::Result<Option<save::SaveData>> {
+        if self.state.state != state::GameState::Yes || self.autopilot.is_some() {
+            return Ok(None)
+        }
+        if self.endless.is_some() { anyhow::bail!("endless mode cannot be suspended") }
+        if self.rewind.is_some() { anyhow::bail!("practice mode cannot be suspended") }
+        if self.playtest { anyhow::bail!("editor playtests cannot be suspended") }
+        if self.state.players().len() > 1 {
+            anyhow::bail!("multiplayer games cannot be suspended")
+        }
+        let mode = if let Some(run) = self.time_attack.as_ref() {
+            save::SavedMode::TimeAttack {
+                campaign_hash: run.hash(), 
+                stage: run.stage, 
+                splits: run.splits().to_vec(), 
+                ticks: run.stage_ticks(), 
+                penalties: run.penalties(), 
+            }
+        } else if let Some(run) = self.campaign.as_ref() {
+            save::SavedMode::Campaign {
+                campaign_hash: run.def.hash(), 
+                stage: run.stage, 
+            }
+        } else if let Some(level) = self.level.as_ref() {
+            save::SavedMode::Level {
+                level: Box::new(level.clone()), 
+                mode: self.mode, 
+                record: self.record.clone(), 
+                title: self.text.entry("top")
+                    .map(|e| e.text()[0].text.get().to_string())
+                    .unwrap_or_default(), 
+            }
+        } else {
+            anyhow::bail!("this game mode cannot be suspended")
+        };
+        Ok(Some(save::SaveData::new(
+            mode, 
+            self.state.to_save(), 
+            self.entities.to_save()?, 
+        )))
+    }
+
+    /// タイムアタックの計測と表示
+    fn update_time_attack(
+        &mut self, 
+        state: &mut super::state::GameState, 
+        lost_ball: bool, 
+    ) {
+        let Some(run) = self.time_attack.as_mut() else { return };
+        let hash = run.hash();
+        let best = state.time_attack.best_split(hash, run.stage);
+        match self.state.state {
+            state::GameState::Yes => {
+                run.tick();
+                if lost_ball { run.penalty() }
+            }, 
+            state::GameState::GameClear => if run.complete_stage() {
+                // ステージのタイムをベストと比較してから記録する
+                let split = run.stage_ticks();
+                let best_total = state.time_attack.get(hash).and_then(|r| r.best_total);
+                let new_record = state.time_attack.record(run);
+                if let Err(e) = state.time_attack.save() {
+                    log::error!("failed to save time attack record: {e}");
+                }
+                if let Some(entry) = self.text.entry_mut("timer") {
+                    let text = &mut entry.text_mut()[1];
+                    (text.text, text.color) = match (run.is_complete(), best, best_total) {
+                        (true, _, _) if new_record => (
+                            format!(" Total {0}  New Record! ", time_attack::format_ticks(run.total())).into(), 
+                            SPLIT_RECORD_COLOR, 
+                        ), 
+                        (true, _, Some(b)) => (
+                            format!(
+                                " Total {0} ({1}) ", 
+                                time_attack::format_ticks(run.total()), 
+                                time_attack::format_delta(run.total(), b)
+                            ).into(), 
+                            SPLIT_BEHIND_COLOR, 
+                        ), 
+                        (false, Some(b), _) => (
+                            format!(
+                                " Split {0} ({1}) ", 
+                                time_attack::format_ticks(split), 
+                                time_attack::format_delta(split, b)
+                            ).into(), 
+                            if split <= b { SPLIT_AHEAD_COLOR } else { SPLIT_BEHIND_COLOR }, 
+                        ), 
+                        _ => (
+                            format!(" Split {0} ", time_attack::format_ticks(split)).into(), 
+                            SPLIT_RECORD_COLOR, 
+                        ), 
+                    };
+                }
+            }, 
+            state::GameState::GameOver => {}, 
+        }
+
+        if let Some(entry) = self.text.entry_mut("timer") {
+            let ticks = run.stage_ticks();
+            let text = &mut entry.text_mut()[0];
+            text.text = format!(" Time {0} ", time_attack::format_ticks(run.total())).into();
+            text.color = match best {
+                Some(b) if ticks > b => SPLIT_BEHIND_COLOR, 
+                Some(_) => SPLIT_AHEAD_COLOR, 
+                None => [1., 1., 1., 1.], 
+            };
+            entry.text_mut()[2].text = match run.penalties() {
+                0 => "".into(), 
+                n => format!(" Penalty x{n} ").into(), 
+            };
+        }
+    }
+
+    /// ゲーム終了後の遷移
+    fn finish(
+        &mut self, 
+        state: &mut super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+    ) -> anyhow::Result<super::scene::SceneController> {
+        let cleared = self.state.state == state::GameState::GameClear;
+        if let Some(next) = self.time_attack.as_ref().and_then(|r| r.next()).filter(|_| cleared) {
+            let carried = std::mem::replace(
+                &mut self.state, 
+                state::BreakOutGameState::new()
+            ).carry_over();
+            return Ok(super::scene::SceneController::ReplaceScene(Box::new(
+                BreakOut::from_time_attack(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    next, 
+                    carried, 
+                )?
+            )))
+        }
+        if let Some(run) = self.campaign.as_ref() {
+            let hash = run.def.hash();
+            match run.next().filter(|_| cleared) {
+                Some(next) => {
+                    // 次のステージへ
+                    let carried = std::mem::replace(
+                        &mut self.state, 
+                        state::BreakOutGameState::new()
+                    ).carry_over();
+                    state.campaign_progress.set(campaign::CampaignProgress {
+                        campaign_hash: hash, 
+                        stage: next.stage, 
+                        score: *carried.score.lock(), 
+                        remain_ball: carried.remain_ball, 
+                    });
+                    if let Err(e) = state.campaign_progress.save() {
+                        log::error!("failed to save campaign progress: {e}");
+                    }
+                    return Ok(super::scene::SceneController::ReplaceScene(Box::new(
+                        BreakOut::from_campaign(
+                            gfx_ctx, 
+                            state.font.clone(), 
+                            next, 
+                            carried, 
+                        )?
+                    )))
+                }, 
+                None if cleared => {
+                    // 最終ステージを終えたので進行状況を消去
+                    state.campaign_progress.clear(hash);
+                    if let Err(e) = state.campaign_progress.save() {
+                        log::error!("failed to save campaign progress: {e}");
+                    }
+                }, 
+                None => {}, 
+            }
+        }
+
+        let score = *self.state.score.lock();
+        Ok(match self.record.as_ref() {
+            Some(target) if state.high_scores.qualifies(target, score) => {
+                super::scene::SceneController::ReplaceScene(Box::new(
+                    super::highscore::name_entry::NameEntry::new(
+                        state.font.clone(), 
+                        super::highscore::PendingScore {
+                            target: target.clone(), 
+                            score, 
+                            difficulity: self.state.difficulity, 
+                            detail: self.endless.as_ref().map(|e| e.detail()), 
+                        }, 
+                    )?
+                ))
+            }, 
+            _ => super::scene::SceneController::PopScene, 
         })
     }
 }
-impl<BF: entities::brick::brick::BrickFeature> super::scene::GameScene for BreakOut<BF> {
+#[cfg(feature = "client")]
+impl<BF: entities::brick::brick::BrickFeature + Clone> super::scene::GameScene for BreakOut<BF> {
     fn name(&self) -> std::borrow::Cow<'static, str> {
         "ブロック崩し".into()
     }
@@ -66,26 +763,138 @@ impl<BF: entities::brick::brick::BrickFeature> super::scene::GameScene for Break
         gfx_ctx: &crate::gfx::WGContext, 
         sfx_ctx: &crate::sfx::SfxModule, 
     ) -> anyhow::Result<super::scene::SceneController> {
-        self.entities.update(
+        if let Some(ai) = self.autopilot.as_mut() {
+            let flag = ai.control(
+                &self.entities, 
+                [
+                    gfx_ctx.size.width as f32, 
+                    gfx_ctx.size.height as f32, 
+                ].into(), 
+            );
+            self.entities.set_input(ai.player(), flag);
+            if let Some(entry) = self.text.entry_mut("bottom") {
+                entry.text_mut()[1].text = format!(" AI : {0} ", ai.skill().name()).into();
+            }
+        }
+        let remain_ball = self.state.remain_ball;
+        let rewinding = self.update_rewind();
+        let input = self.entities.paddles().first().map(|p| p.move_flag);
+        if !rewinding {
+            let theme = self.campaign.as_ref().map(|r| &r.def.sfx)
+                .or(self.time_attack.as_ref().map(|r| &r.def.sfx));
+            self.entities.update(
+                [
+                    gfx_ctx.size.width as f32, 
+                    gfx_ctx.size.height as f32, 
+                ].into(), 
+                &mut self.state, 
+                &crate::sfx::ThemedSfx::new(sfx_ctx, theme), 
+            );
+        }
+        if let Some(endless) = self.endless.as_mut() {
+            let paddle_line = self.entities.paddle_line();
+            endless.update(
+                self.entities.bricks_mut(), 
+                &mut self.state, 
+                [
+                    gfx_ctx.size.width as f32, 
+                    gfx_ctx.size.height as f32, 
+                ].into(), 
+                paddle_line
+            );
+        }
+        let lost_ball = self.state.remain_ball < remain_ball;
+        self.update_time_attack(state, lost_ball);
+        if !rewinding && self.state.award_extra_life() {
+            sfx_ctx.play_tones(&hud::EXTRA_LIFE_JINGLE);
+        }
+        self.update_ghost(
+            state, 
+            input, 
+            [
+                gfx_ctx.size.width as f32, 
+                gfx_ctx.size.height as f32, 
+            ].into(), 
+        );
+        self.life_hud.update(
             [
                 gfx_ctx.size.width as f32, 
                 gfx_ctx.size.height as f32, 
             ].into(), 
-            &mut self.state, 
-            sfx_ctx
+            &self.state
+        );
+        self.boss_hud.update(
+            [
+                gfx_ctx.size.width as f32, 
+                gfx_ctx.size.height as f32, 
+            ].into(), 
+            self.entities.boss()
         );
         self.renderer.update(&self.entities);
+        self.renderer.append(&self.life_hud);
+        self.renderer.append(&self.boss_hud);
+        if let Some(ghost) = self.ghost.as_ref() {
+            self.renderer.append(ghost);
+        }
         self.text.entry_mut("top").map(|entry| {
-            entry.text_mut()[1].text = match self.entities.remain_brick() {
-                0 => " Game Clear!! ".into(), 
-                remain @ _ => format!(" Remain blocks : {remain} ").into(), 
+            entry.text_mut()[1].text = match (self.endless.as_ref(), self.entities.remain_brick()) {
+                (Some(endless), _) => format!(
+                    " Rows : {0}  Depth : {1:.0} ", 
+                    endless.rows(), 
+                    endless.distance()
+                ).into(), 
+                (None, 0) => " Game Clear!! ".into(), 
+                (None, remain) => format!(" Remain blocks : {remain} ").into(), 
             };
             entry.text_mut()[2].text = match self.state.remain_ball {
-                0 => " Game Over! ".into(), 
-                remain @ _ => format!(" Remain balls : {remain} ").into(), 
+                0 if self.state.state == state::GameState::GameOver => " Game Over! ".into(), 
+                _ => "".into(), 
             };
-            entry.text_mut()[3].text = format!(" Score : {0} ", *self.state.score.lock()).into()
+            entry.text_mut()[3].text = format!(" Score : {0} ", *self.state.score.lock()).into();
+            let players = self.state.players();
+            if players.len() > 1 {
+                let per_player = self.state.lives_rule() == state::LivesRule::PerPlayer;
+                let scores = players.iter()
+                    .enumerate()
+                    .map(|(i, p)| if per_player {
+                        format!(" {0}P : {1} ({2}) ", i + 1, p.score, p.remain_ball)
+                    } else {
+                        format!(" {0}P : {1} ", i + 1, p.score)
+                    })
+                    .collect::<String>();
+                entry.text_mut()[3].text = if per_player {
+                    scores.into()
+                } else {
+                    format!(" Score : {0} /{scores}", *self.state.score.lock()).into()
+                };
+                if per_player && self.state.state != state::GameState::Yes {
+                    entry.text_mut()[2].text = match versus::winner(&self.state) {
+                        Some(p) => format!(" {0}P Wins! ", p + 1).into(), 
+                        None => " Draw ".into(), 
+                    };
+                }
+            }
         });
+        let finished = self.state.state != state::GameState::Yes;
+        if self.playtest && self.to_exit {
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::scene::SceneController::PopScene)
+        }
+        if let Some(ticks) = self.demo.as_mut() {
+            if finished { *ticks += 1 }
+            if self.to_exit || *ticks >= DEMO_END_TICKS {
+                return Ok(super::scene::SceneController::PopScene)
+            }
+            return Ok(super::scene::SceneController::NOp)
+        }
+        if let Some(entry) = self.text.entry_mut("bottom") {
+            entry.text_mut()[3].text = if finished { " Enter : Continue ".into() } else { "".into() };
+        }
+        if finished && self.to_next {
+            self.to_next = false;
+            sfx_ctx.play_resource("pause", |r| r);
+            return self.finish(state, gfx_ctx)
+        }
         if !self.to_pause {
             Ok(super::scene::SceneController::NOp)
         } else {
@@ -104,13 +913,37 @@ impl<BF: entities::brick::brick::BrickFeature> super::scene::GameScene for Break
         keycode: winit::event::VirtualKeyCode, 
         elem_state: winit::event::ElementState
     ) { 
-        self.entities.key_input(keycode, elem_state);
+        let pressed = elem_state == winit::event::ElementState::Pressed;
+        if self.demo.is_some() {
+            self.to_exit |= pressed;
+            return
+        }
+        match self.autopilot.as_mut() {
+            Some(ai) => if pressed && keycode == winit::event::VirtualKeyCode::Tab {
+                ai.set_skill(ai.skill().next());
+            }, 
+            None => self.entities.key_input(keycode, elem_state), 
+        }
         if keycode == winit::event::VirtualKeyCode::P {
             self.to_pause = elem_state == winit::event::ElementState::Pressed;
         }
+        if keycode == winit::event::VirtualKeyCode::R && self.rewind.is_some() {
+            self.rewinding = pressed;
+        }
+        if keycode == winit::event::VirtualKeyCode::Back && self.playtest {
+            self.to_exit |= pressed;
+        }
+        if keycode == winit::event::VirtualKeyCode::Return {
+            self.to_next = elem_state == winit::event::ElementState::Pressed;
+        }
     }
 
     fn mouse_button_input(&mut self, button: winit::event::MouseButton, elem_state: winit::event::ElementState) {
+        if self.demo.is_some() {
+            self.to_exit |= elem_state == winit::event::ElementState::Pressed;
+            return
+        }
+        if self.autopilot.is_some() { return }
         self.entities.mouse_input(button, elem_state);
     }
 
@@ -118,6 +951,7 @@ impl<BF: entities::brick::brick::BrickFeature> super::scene::GameScene for Break
     }
 
     fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
+        if self.autopilot.is_some() { return }
         self.entities.mouse_motion_input(delta);
     }
 
@@ -127,12 +961,22 @@ impl<BF: entities::brick::brick::BrickFeature> super::scene::GameScene for Break
         gfx_ctx: &crate::gfx::WGContext, 
         rendering_chain: crate::gfx::RenderingChain
     ) -> crate::gfx::RenderingChain {
-        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+        let disp_size = [gfx_ctx.size.width as f32, gfx_ctx.size.height as f32].into();
+        self.entities.obstacles().iter()
+            .flat_map(|o| o.commands(disp_size))
+            .for_each(|c| self.d2d.push(c));
+        let r: [&mut dyn crate::gfx::WGRenderer; 3] = [
             &mut self.renderer, 
+            &mut self.d2d, 
             &mut self.text, 
         ];
         rendering_chain.rendering(gfx_ctx, r)
     }
 
-    
+    fn save_game(&self) -> Option<save::SaveData> {
+        self.save_data().unwrap_or_else(|e| {
+            log::warn!("the game was not saved: {e}");
+            None
+        })
+    }
 }
\ No newline at end of file
@@ -1,18 +1,20 @@
 //! メインレンダラー
 
+#[cfg(feature = "client")]
 use wgpu::{RenderPipeline, Buffer, BindGroup, util::DeviceExt};
 
 /// モデルの実装
 pub mod model;
 
 /// カメラの実装
+#[cfg(feature = "client")]
 pub mod camera;
 
+#[cfg(feature = "client")]
 pub struct BreakOutRenderer {
     render_pipeline: RenderPipeline, 
     vertex_buffer: Buffer, 
     index_buffer: Buffer, 
-    num_indices: u32, 
     raw_instances: model::RawInstArray, 
     instances_buffer: Buffer, 
     camera: camera::Camera, 
@@ -20,6 +22,7 @@ pub struct BreakOutRenderer {
     camera_buffer: Buffer, 
     camera_bg: BindGroup, 
 }
+#[cfg(feature = "client")]
 impl crate::gfx::WGRenderer for BreakOutRenderer {
     fn rendering(
         &mut self, 
@@ -40,11 +43,13 @@ impl crate::gfx::WGRenderer for BreakOutRenderer {
             bytemuck::cast_slice(&[self.camera_mat])
         );
 
+        // 形ごとに描画するため同じ形のインスタンスをまとめる
+        let (instances, ranges) = self.raw_instances.by_mesh();
         self.instances_buffer.destroy();
         self.instances_buffer = ctx.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Instance buffer"),
-                contents: bytemuck::cast_slice(self.raw_instances.get()),
+                contents: bytemuck::cast_slice(&instances),
                 usage: wgpu::BufferUsages::VERTEX,
             }
         );
@@ -98,17 +103,21 @@ impl crate::gfx::WGRenderer for BreakOutRenderer {
                 wgpu::IndexFormat::Uint16
             );
 
-            // インデックスを利用した描画
-            render_pass.draw_indexed(
-                0..self.num_indices, 
-                0, 
-                0..self.raw_instances.len() as _
-            );
+            // インデックスを利用した形ごとの描画
+            for (mesh, range) in ranges.into_iter().enumerate() {
+                if range.is_empty() { continue }
+                render_pass.draw_indexed(
+                    model::mesh_range(mesh as u32), 
+                    0, 
+                    range
+                );
+            }
         }
 
         ctx.queue.submit(std::iter::once(enc.finish()));
     }
 }
+#[cfg(feature = "client")]
 impl BreakOutRenderer {
     pub fn new(
         ctx: &crate::gfx::WGContext, 
@@ -116,18 +125,17 @@ impl BreakOutRenderer {
         let vertex_buffer = ctx.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex buffer"),
-                contents: bytemuck::cast_slice(model::VERTICES),
+                contents: bytemuck::cast_slice(&model::vertices()),
                 usage: wgpu::BufferUsages::VERTEX,
             }
         );
         let index_buffer = ctx.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: Some("Index buffer"),
-                contents: bytemuck::cast_slice(model::INDICES),
+                contents: bytemuck::cast_slice(&model::indices()),
                 usage: wgpu::BufferUsages::INDEX,
             }
         );
-        let num_indices = model::INDICES.len() as u32;
         let raw_instances = model::RawInstArray::new();
         let instances_buffer = ctx.device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
@@ -237,7 +245,6 @@ impl BreakOutRenderer {
             render_pipeline,
             vertex_buffer,
             index_buffer,
-            num_indices,
             raw_instances,
             instances_buffer,
             camera,
@@ -250,4 +257,8 @@ impl BreakOutRenderer {
         self.raw_instances.init();
         instances.as_instance(&mut self.raw_instances)
     }
+    /// 既存のインスタンスを残したまま追加する
+    pub fn append(&mut self, instances: &impl model::AsInstance) {
+        instances.as_instance(&mut self.raw_instances)
+    }
 }
\ No newline at end of file
@@ -1,12 +1,19 @@
 //! モデルの実装
 
+use serde::{Serialize, Deserialize};
+
+/// 円の頂点の数
+const CIRCLE_SEGMENTS: u16 = 16;
+
 /// モデルの頂点データ
+#[cfg(feature = "client")]
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub pos: [f32; 4], 
     pub color: [f32; 4], 
 }
+#[cfg(feature = "client")]
 impl Vertex {
     const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
         0 => Float32x4, 1 => Float32x4
@@ -21,29 +28,121 @@ impl Vertex {
 }
 
 /// 頂点配列
-pub const VERTICES: &[Vertex] = &[
-    Vertex{ pos: [1.0, 1.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] }, 
-    Vertex{ pos: [-1.0, 1.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] }, 
-    Vertex{ pos: [-1.0, -1.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] }, 
-    Vertex{ pos: [1.0, -1.0, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] }, 
-];
+///
+/// 矩形の4頂点に続けて、円の中心と周上の頂点を並べる。
+#[cfg(feature = "client")]
+pub fn vertices() -> Vec<Vertex> {
+    let corner = |x: f32, y: f32| Vertex { pos: [x, y, 0.0, 1.0], color: [1.0, 1.0, 1.0, 1.0] };
+    [
+        corner(1.0, 1.0), 
+        corner(-1.0, 1.0), 
+        corner(-1.0, -1.0), 
+        corner(1.0, -1.0), 
+        corner(0.0, 0.0), 
+    ].into_iter()
+        .chain((0..CIRCLE_SEGMENTS).map(|i| {
+            let t = std::f32::consts::TAU * i as f32 / CIRCLE_SEGMENTS as f32;
+            corner(t.cos(), t.sin())
+        }))
+        .collect()
+}
 
 /// インデックス配列
-pub const INDICES: &[u16] = &[
-    0, 1, 2, 
-    2, 3, 0, 
-];
+///
+/// 形の番号(`Shape::mesh`)ごとの範囲は`mesh_range`で得る。
+pub fn indices() -> Vec<u16> {
+    let mut indices = vec![
+        // 矩形
+        0, 1, 2, 
+        2, 3, 0, 
+        // 直角が右上、左上、左下、右下の三角形
+        3, 0, 1, 
+        0, 1, 2, 
+        1, 2, 3, 
+        2, 3, 0, 
+    ];
+    indices.extend((0..CIRCLE_SEGMENTS).flat_map(|i| [4, 5 + i, 5 + (i + 1) % CIRCLE_SEGMENTS]));
+    indices
+}
+
+/// 形の番号に対するインデックスの範囲
+pub fn mesh_range(mesh: u32) -> std::ops::Range<u32> { match mesh {
+    0 => 0..6, 
+    1..=4 => 6 + (mesh - 1) * 3..9 + (mesh - 1) * 3, 
+    _ => 18..18 + CIRCLE_SEGMENTS as u32 * 3, 
+}}
+
+/// 形の数
+pub const MESH_COUNT: u32 = 6;
+
+/// 三角形の直角の位置
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Corner {
+    TopRight, 
+    TopLeft, 
+    BottomLeft, 
+    BottomRight, 
+}
+impl Corner {
+    /// 直角の頂点の向き(`[x, y]`のそれぞれ1または-1)
+    pub fn sign(&self) -> [f32; 2] { match self {
+        Self::TopRight => [1., 1.], 
+        Self::TopLeft => [-1., 1.], 
+        Self::BottomLeft => [-1., -1.], 
+        Self::BottomRight => [1., -1.], 
+    }}
+}
+
+/// 当たり判定と描画の形
+///
+/// いずれも`Instance`の大きさの矩形に収まり、回転は`Instance::angle`に従う。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shape {
+    /// 矩形
+    #[default]
+    Rect, 
+    /// 矩形を対角線で半分にした直角三角形
+    Triangle(Corner), 
+    /// 幅と高さの短い方を直径とする円
+    Circle, 
+}
+impl Shape {
+    pub fn is_rect(&self) -> bool { *self == Self::Rect }
+    /// 描画に使う形の番号
+    pub fn mesh(&self) -> u32 { match self {
+        Self::Rect => 0, 
+        Self::Triangle(c) => 1 + *c as u32, 
+        Self::Circle => 5, 
+    }}
+}
 
 /// 生インスタンスの配列型
+#[derive(Default)]
 pub struct RawInstArray(Vec<InstanceRaw>);
 impl RawInstArray {
-    pub(super) fn new() -> Self { Self(Vec::new()) }
+    pub fn new() -> Self { Self(Vec::new()) }
+    #[cfg(feature = "client")]
     pub(super) fn init(&mut self) { self.0.clear() }
-    pub(super) fn get(&self) -> &[InstanceRaw] { self.0.as_slice() }
-    pub(super) fn len(&self) -> usize { self.0.len() }
+    pub fn get(&self) -> &[InstanceRaw] { self.0.as_slice() }
     pub fn push(&mut self, instance: &Instance) {
         self.0.push(instance.to_raw())
     }
+    /// 形ごとにまとめたインスタンスと、各形のインスタンスの範囲
+    ///
+    /// 同じ形の中では追加した順を保つ。
+    #[cfg(feature = "client")]
+    pub(super) fn by_mesh(&self) -> (Vec<InstanceRaw>, Vec<std::ops::Range<u32>>) {
+        let mut sorted = self.0.clone();
+        sorted.sort_by_key(|i| i.shape);
+        let ranges = (0..MESH_COUNT)
+            .map(|m| {
+                let start = sorted.partition_point(|i| i.shape < m) as u32;
+                let end = sorted.partition_point(|i| i.shape <= m) as u32;
+                start..end
+            })
+            .collect();
+        (sorted, ranges)
+    }
 }
 
 /// インスタンスの参照を得られる型
@@ -52,60 +151,166 @@ pub trait AsInstance {
 }
 
 /// インスタンス
+#[derive(Clone, Copy, Debug)]
 pub struct Instance {
     pub position: nalgebra::Point2<f32>, 
     pub size: nalgebra::Vector2<f32>, 
     pub angle: f32, 
     pub color: [f32; 4], 
+    pub shape: Shape, 
 }
 impl Instance {
     pub fn to_raw(&self) -> InstanceRaw {
+        let size = match self.shape {
+            Shape::Circle => nalgebra::Vector2::repeat(self.radius()), 
+            _ => self.size / 2., 
+        };
         InstanceRaw {
             position: [
                 self.position.x, 
                 self.position.y
             ],
-            size: [self.size.x / 2., self.size.y / 2.], 
+            size: size.into(), 
             rotation: [self.angle.cos(), self.angle.sin()], 
             color: self.color.clone(),
+            shape: self.shape.mesh(), 
         }
     }
+    /// 円の半径
+    fn radius(&self) -> f32 { self.size.x.min(self.size.y) / 2. }
+    /// 外接する軸に平行な矩形の幅と高さの半分
+    pub fn half_extent(&self) -> nalgebra::Vector2<f32> {
+        if self.shape == Shape::Circle { return nalgebra::Vector2::repeat(self.radius()) }
+        let (cos, sin) = (self.angle.cos().abs(), self.angle.sin().abs());
+        let half = self.size / 2.;
+        [half.x * cos + half.y * sin, half.x * sin + half.y * cos].into()
+    }
+    /// 外接する軸に平行な矩形どうしの判定
     pub fn collision_aabb(&self, other: &Self) -> bool {
-        self.position.x - self.size.x / 2. <= other.position.x + other.size.x / 2.
-        && self.position.y - self.size.y / 2. <= other.position.y + other.size.y / 2.
-        && other.position.x - other.size.x / 2. <= self.position.x + self.size.x / 2.
-        && other.position.y - other.size.y / 2. <= self.position.y + self.size.y / 2.
+        let (a, b) = (self.half_extent(), other.half_extent());
+        (self.position.x - other.position.x).abs() <= a.x + b.x
+        && (self.position.y - other.position.y).abs() <= a.y + b.y
+    }
+    /// 回転を考慮した頂点
+    ///
+    /// 矩形は右上から反時計回り、三角形は直角の頂点から反時計回りに並べる。円は空となる。
+    pub fn vertices(&self) -> Vec<nalgebra::Point2<f32>> {
+        let rotation = nalgebra::Rotation2::new(self.angle);
+        let half = self.size / 2.;
+        let local: Vec<[f32; 2]> = match self.shape {
+            Shape::Rect => vec![[1., 1.], [-1., 1.], [-1., -1.], [1., -1.]], 
+            Shape::Triangle(c) => {
+                let [sx, sy] = c.sign();
+                // 直角の頂点から反時計回りになるよう、向きによって残りの順を入れ替える
+                if sx * sy > 0. { vec![[sx, sy], [-sx, sy], [sx, -sy]] }
+                else { vec![[sx, sy], [sx, -sy], [-sx, sy]] }
+            }, 
+            Shape::Circle => Vec::new(), 
+        };
+        local.into_iter()
+            .map(|[x, y]| self.position + rotation * nalgebra::Vector2::new(x * half.x, y * half.y))
+            .collect()
     }
-    pub fn edges(&self) -> [[nalgebra::Point2<f32>; 2]; 4] { 
-        let self_size = self.size * 0.5;
-        [[
-            [self.position.x - self_size.x, self.position.y - self_size.y].into(), 
-            [self.position.x + self_size.x, self.position.y - self_size.y].into(), 
-        ], [
-            [self.position.x - self_size.x, self.position.y + self_size.y].into(), 
-            [self.position.x + self_size.x, self.position.y + self_size.y].into(), 
-        ], [
-            [self.position.x - self_size.x, self.position.y - self_size.y].into(), 
-            [self.position.x - self_size.x, self.position.y + self_size.y].into(), 
-        ], [
-            [self.position.x + self_size.x, self.position.y - self_size.y].into(), 
-            [self.position.x + self_size.x, self.position.y + self_size.y].into(),
-        ]]
+    /// 回転を考慮した辺
+    pub fn edges(&self) -> Vec<[nalgebra::Point2<f32>; 2]> {
+        let vertices = self.vertices();
+        (0..vertices.len())
+            .map(|i| [vertices[i], vertices[(i + 1) % vertices.len()]])
+            .collect()
+    }
+    /// 線分`a`から`b`への移動との接触
+    ///
+    /// 最初に接触する位置の割合(0.0 - 1.0)と、接触した面の外向きの法線を返す。
+    pub fn sweep(
+        &self, 
+        a: nalgebra::Point2<f32>, 
+        b: nalgebra::Point2<f32>, 
+    ) -> Option<(f32, nalgebra::Vector2<f32>)> {
+        if self.shape == Shape::Circle { return sweep_circle(self.position, self.radius(), a, b) }
+        sweep_edges(self.edges(), a, b)
     }
 }
+
+/// 線分`a`から`b`への移動と、反時計回りに並んだ辺との接触
+///
+/// 最初に接触する位置の割合(0.0 - 1.0)と、接触した辺の右側を向く法線を返す。
+pub fn sweep_edges(
+    edges: impl IntoIterator<Item = [nalgebra::Point2<f32>; 2]>, 
+    a: nalgebra::Point2<f32>, 
+    b: nalgebra::Point2<f32>, 
+) -> Option<(f32, nalgebra::Vector2<f32>)> {
+    let v_ab = b - a;
+    let mut hit = None;
+    let mut length = 1.;
+    for [c, d] in edges {
+        let v_cd = d - c;
+        let v_ac = c - a;
+
+        let bunbo = (v_ab.x * v_cd.y) - (v_ab.y * v_cd.x);
+        if bunbo.abs() <= f32::EPSILON { continue; }
+        let r = (v_cd.y * v_ac.x - v_cd.x * v_ac.y) / bunbo;
+        let s = (v_ab.y * v_ac.x - v_ab.x * v_ac.y) / bunbo;
+
+        if (0. ..=1.).contains(&r) && (0. ..=1.).contains(&s) && r <= length {
+            length = r;
+            // 頂点は反時計回りのため、辺の右側が外側となる
+            hit = Some((r, nalgebra::Vector2::new(v_cd.y, -v_cd.x).normalize()));
+        }
+    }
+    hit
+}
+
+/// 線分`a`から`b`への移動と円との接触
+///
+/// 最初に接触する位置の割合(0.0 - 1.0)と、接触した位置の外向きの法線を返す。
+pub fn sweep_circle(
+    center: nalgebra::Point2<f32>, 
+    radius: f32, 
+    a: nalgebra::Point2<f32>, 
+    b: nalgebra::Point2<f32>, 
+) -> Option<(f32, nalgebra::Vector2<f32>)> {
+    // |a + t * ab - p| = r の小さい方の解
+    let v_ab = b - a;
+    let v_pa = a - center;
+    let (qa, qb, qc) = (v_ab.dot(&v_ab), 2. * v_pa.dot(&v_ab), v_pa.dot(&v_pa) - radius * radius);
+    if qa <= f32::EPSILON { return None }
+    let disc = qb * qb - 4. * qa * qc;
+    if disc < 0. { return None }
+    let r = [(-qb - disc.sqrt()) / (2. * qa), (-qb + disc.sqrt()) / (2. * qa)]
+        .into_iter()
+        .find(|r| (0. ..=1.).contains(r))?;
+    let normal = (a + v_ab * r - center).try_normalize(f32::EPSILON)?;
+    Some((r, normal))
+}
 impl AsRef<Instance> for Instance {
     fn as_ref(&self) -> &Instance { self }
 }
 
 /// 生インスタンス
+///
+/// ヘッドレスの環境でも描画の代わりに読み取るため、GPUに渡す時のみ`Pod`とする。
 #[repr(C)]
-#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "client", derive(bytemuck::Pod, bytemuck::Zeroable))]
 pub struct InstanceRaw {
     position: [f32; 2], 
     size: [f32; 2], 
     rotation: [f32; 2], 
     color: [f32; 4], 
+    /// 形の番号(頂点属性には含めない)
+    shape: u32, 
+}
+impl InstanceRaw {
+    pub fn position(&self) -> [f32; 2] { self.position }
+    /// 幅と高さの半分
+    pub fn half_size(&self) -> [f32; 2] { self.size }
+    /// 回転角の(cos, sin)
+    pub fn rotation(&self) -> [f32; 2] { self.rotation }
+    pub fn color(&self) -> [f32; 4] { self.color }
+    /// 形の番号(`Shape::mesh`)
+    pub fn shape(&self) -> u32 { self.shape }
 }
+#[cfg(feature = "client")]
 impl InstanceRaw {
     const ATTRIBS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
         5 => Float32x2, 
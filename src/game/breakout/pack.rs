@@ -0,0 +1,189 @@
+//! ステージパック
+//!
+//! `levels/`とユーザーデータの`packs/`の下の、`pack.ron`を持つディレクトリを
+//! 1つのパックとして読み込む。パックのステージはキャンペーンとして順に遊ぶ。
+//!
+//! ```ron
+//! (
+//!     name: "Neon",
+//!     author: "someone",
+//!     levels: ["neon1.ron", "neon2.ron"],
+//!     sounds: {"break": "break.ogg"},
+//!     palette: (normal: Some((0.2, 1.0, 0.8, 1.0))),
+//! )
+//! ```
+
+use std::{collections::BTreeMap, path::{Path, PathBuf}, sync::Arc};
+
+use serde::Deserialize;
+
+use super::campaign::CampaignDef;
+use super::level::{BrickType, LevelDef};
+use super::super::util::persist;
+
+/// パックの定義ファイルの名前
+pub const PACK_MANIFEST: &str = "pack.ron";
+
+/// 効果音の名前空間の接頭辞
+///
+/// 組み込みの効果音の名前には`/`を含まないため、パックの効果音が上書きすることはない。
+const SOUND_NAMESPACE: &str = "pack/";
+
+/// パックの定義ファイルの形式
+#[derive(Deserialize)]
+struct PackManifest {
+    name: String, 
+    author: String, 
+    #[serde(default)]
+    description: String, 
+    /// パックのディレクトリからの相対パスで記述したステージの並び
+    levels: Vec<PathBuf>, 
+    /// 差し替える効果音の名前とファイル
+    #[serde(default)]
+    sounds: BTreeMap<String, PathBuf>, 
+    #[serde(default)]
+    palette: PackPalette, 
+}
+
+/// ブロックの種類ごとの色
+///
+/// 指定した種類のブロックはパックの全ステージでその色になる。
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PackPalette {
+    #[serde(default)]
+    pub normal: Option<[f32; 4]>, 
+    #[serde(default)]
+    pub upper: Option<[f32; 4]>, 
+    #[serde(default)]
+    pub top: Option<[f32; 4]>, 
+    #[serde(default)]
+    pub solid: Option<[f32; 4]>, 
+}
+impl PackPalette {
+    pub fn color(&self, blk_type: BrickType) -> Option<[f32; 4]> { match blk_type {
+        BrickType::Normal => self.normal, 
+        BrickType::Upper => self.upper, 
+        BrickType::Top => self.top, 
+        BrickType::Solid => self.solid, 
+    }}
+    /// ステージのブロックの色の置き換え
+    pub fn apply(&self, level: &mut LevelDef) {
+        for brick in level.bricks.iter_mut().flatten().flatten() {
+            if let Some(color) = self.color(brick.blk_type) { brick.color = color }
+        }
+    }
+}
+
+/// ステージパック
+pub struct LevelPack {
+    /// ディレクトリ名による識別子
+    pub id: String, 
+    pub dir: PathBuf, 
+    pub name: String, 
+    pub author: String, 
+    pub description: String, 
+    /// 効果音の名前とファイル
+    pub sounds: Vec<(String, PathBuf)>, 
+    /// 順に遊ぶステージ
+    pub campaign: Arc<CampaignDef>, 
+}
+impl LevelPack {
+    /// パックのディレクトリからの読み込み
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let id = dir.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow::anyhow!("invalid pack directory {0}", dir.display()))?;
+        let manifest: PackManifest = ron::from_str(
+            &std::fs::read_to_string(dir.join(PACK_MANIFEST))?
+        )?;
+        let stages = manifest.levels.iter()
+            .map(|l| {
+                let mut level = LevelDef::load(dir.join(l))
+                    .map_err(|e| anyhow::anyhow!("{0}: {e}", l.display()))?;
+                manifest.palette.apply(&mut level);
+                Ok(level)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if stages.is_empty() {
+            anyhow::bail!("pack {0} has no levels", manifest.name);
+        }
+        let sounds = manifest.sounds.into_iter()
+            .map(|(name, file)| (name, dir.join(file)))
+            .collect::<Vec<_>>();
+        let mut sfx = crate::sfx::SfxTheme::default();
+        for (name, _) in sounds.iter() {
+            sfx.insert(name.clone(), sound_key(&id, name));
+        }
+        Ok(Self {
+            campaign: Arc::new(CampaignDef {
+                name: manifest.name.clone(), 
+                stages, 
+                sfx, 
+            }), 
+            id, 
+            dir: dir.to_path_buf(), 
+            name: manifest.name, 
+            author: manifest.author, 
+            description: manifest.description, 
+            sounds, 
+        })
+    }
+
+    /// 効果音の登録
+    ///
+    /// 読み込めない効果音は記録して飛ばし、その効果音は組み込みのものが鳴る。
+    /// 登録済みの場合は何もしない。
+    #[cfg(feature = "client")]
+    pub fn register_sounds(&self, sfx_ctx: &crate::sfx::SfxModule) {
+        for (name, path) in self.sounds.iter() {
+            let key = sound_key(&self.id, name);
+            if sfx_ctx.has_resource(&key) { continue }
+            let decoder = std::fs::File::open(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|f| Ok(rodio::Decoder::new(f)?));
+            match decoder {
+                Ok(decoder) => { sfx_ctx.add_resource(key, decoder); }, 
+                Err(e) => log::warn!("pack {0}: failed to load sound {1}: {e}", self.id, path.display()), 
+            }
+        }
+    }
+}
+
+/// パックの効果音の登録名
+pub fn sound_key(pack_id: &str, name: &str) -> String {
+    format!("{SOUND_NAMESPACE}{pack_id}/{name}")
+}
+
+/// パックを探すディレクトリ
+pub fn pack_roots() -> [PathBuf; 2] {
+    [
+        PathBuf::from("./levels"), 
+        persist::data_dir().join("packs"), 
+    ]
+}
+
+/// 全てのパックの読み込み
+///
+/// 読み込めないパックは記録して飛ばす。識別子が重複するパックは先に見つけたものを使う。
+pub fn scan() -> Vec<LevelPack> {
+    let mut packs: Vec<LevelPack> = Vec::new();
+    for root in pack_roots() {
+        let Ok(dir) = std::fs::read_dir(&root) else { continue };
+        let mut dirs = dir
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.join(PACK_MANIFEST).is_file())
+            .collect::<Vec<_>>();
+        dirs.sort();
+        for dir in dirs {
+            match LevelPack::load(&dir) {
+                Ok(pack) if packs.iter().any(|p| p.id == pack.id) => {
+                    log::warn!("skipped pack {0}: id {1} is already used", dir.display(), pack.id)
+                }, 
+                Ok(pack) => packs.push(pack), 
+                Err(e) => log::warn!("failed to load pack {0}: {e}", dir.display()), 
+            }
+        }
+    }
+    packs
+}
@@ -0,0 +1,117 @@
+//! 練習モードの巻き戻し
+
+use std::collections::VecDeque;
+
+use super::entities::{BreakOutEntities, brick::brick::BrickFeature};
+use super::state::BreakOutGameState;
+
+/// 保存するスナップショットの数(60Hzで10秒分)
+pub const REWIND_CAPACITY: usize = 10 * 60;
+
+/// 1フレームあたりに巻き戻すティック数
+pub const REWIND_SPEED: usize = 2;
+
+/// 巻き戻しの対象となる状態
+#[derive(Clone)]
+pub struct Snapshot<BF: BrickFeature> {
+    pub entities: BreakOutEntities<BF>, 
+    pub state: BreakOutGameState, 
+}
+
+/// スナップショットのリングバッファ
+pub struct Rewind<BF: BrickFeature> {
+    snapshots: VecDeque<Snapshot<BF>>, 
+    capacity: usize, 
+}
+impl<BF: BrickFeature> Rewind<BF> {
+    pub fn new(capacity: usize) -> Self { Self {
+        snapshots: VecDeque::with_capacity(capacity), 
+        capacity: capacity.max(1), 
+    }}
+
+    /// 記録しているティック数
+    pub fn len(&self) -> usize { self.snapshots.len() }
+    pub fn is_empty(&self) -> bool { self.snapshots.is_empty() }
+    /// 記録している秒数
+    pub fn seconds(&self) -> f32 { self.snapshots.len() as f32 / 60. }
+}
+impl<BF: BrickFeature + Clone> Rewind<BF> {
+    /// 1ティック分の状態の記録
+    ///
+    /// 容量を超えた場合は最も古いものを捨てる。
+    pub fn record(&mut self, entities: &BreakOutEntities<BF>, state: &BreakOutGameState) {
+        if self.snapshots.len() >= self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot {
+            entities: entities.clone(), 
+            state: state.clone(), 
+        });
+    }
+
+    /// `ticks`ティック分の巻き戻し
+    ///
+    /// 最も古いスナップショットは残し、それ以上は戻らない。
+    pub fn step_back(&mut self, ticks: usize) -> Option<Snapshot<BF>> {
+        let keep = self.snapshots.len().saturating_sub(ticks).max(1);
+        self.snapshots.truncate(keep);
+        self.snapshots.back().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::entities::paddle::PaddleMoveFlag;
+    use super::super::level::{BrickFeature as Feature, LevelDef};
+
+    /// 打ち出してから`ticks`ティック進め、各ティックのボールの位置を返す
+    fn play(
+        rewind: &mut Rewind<Feature>, 
+        entities: &mut BreakOutEntities<Feature>, 
+        state: &mut BreakOutGameState, 
+        ticks: usize, 
+    ) -> Vec<[f32; 2]> {
+        let disp_size = [640., 640.].into();
+        (0..ticks).map(|_| {
+            entities.set_input(0, PaddleMoveFlag { ball_shot: true, ..Default::default() });
+            entities.update(disp_size, state, &crate::sfx::MutedSfx);
+            rewind.record(entities, state);
+            entities.ball().map_or([0.; 2], |b| b.position().into())
+        }).collect()
+    }
+
+    #[test]
+    fn step_back_restores_recorded_tick() {
+        let level = LevelDef::builtin();
+        let mut entities = BreakOutEntities::new(level.spawn_param(), [640., 640.].into());
+        let mut state = BreakOutGameState::new();
+        let mut rewind = Rewind::new(REWIND_CAPACITY);
+        let trace = play(&mut rewind, &mut entities, &mut state, 30);
+
+        let snapshot = rewind.step_back(10).unwrap();
+        assert_eq!(rewind.len(), 20);
+        let ball = snapshot.entities.ball().unwrap().position();
+        assert_eq!([ball.x, ball.y], trace[19]);
+
+        // 戻した状態から同じ入力で進めると、同じ軌跡をたどる
+        let Snapshot { mut entities, mut state } = snapshot;
+        assert_eq!(play(&mut rewind, &mut entities, &mut state, 10), trace[20..]);
+    }
+
+    #[test]
+    fn oldest_snapshot_is_kept() {
+        let level = LevelDef::builtin();
+        let mut entities = BreakOutEntities::new(level.spawn_param(), [640., 640.].into());
+        let mut state = BreakOutGameState::new();
+        let mut rewind = Rewind::new(5);
+        let trace = play(&mut rewind, &mut entities, &mut state, 8);
+        assert_eq!(rewind.len(), 5);
+
+        // 容量を超えた古いものは捨て、最も古い1つより前には戻らない
+        let snapshot = rewind.step_back(100).unwrap();
+        assert_eq!(rewind.len(), 1);
+        let ball = snapshot.entities.ball().unwrap().position();
+        assert_eq!([ball.x, ball.y], trace[3]);
+    }
+}
@@ -0,0 +1,272 @@
+//! 中断したゲームの保存と再開
+
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use super::level::LevelDef;
+use super::state::{BreakOutDifficulity, ExtraLifeRule, LivesRule, PlayerState};
+use super::super::highscore::{GameMode, ScoreTarget};
+use super::super::util::persist;
+
+/// セーブデータの形式のバージョン
+///
+/// 形式を変更した場合は値を増やす。異なるバージョンのセーブデータは破棄する。
+pub const SAVE_VERSION: u32 = 1;
+
+/// セーブデータのファイル名
+const SAVE_FILE: &str = "suspend.ron";
+
+fn normal_mode() -> GameMode { GameMode::Normal }
+
+/// 中断したゲームの種類
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SavedMode {
+    /// 単独のステージ
+    Level {
+        level: Box<LevelDef>, 
+        /// 遊んでいたゲームモード
+        #[serde(default = "normal_mode")]
+        mode: GameMode, 
+        /// ハイスコアの記録先
+        record: Option<ScoreTarget>, 
+        /// 画面上部の表示
+        title: String, 
+    }, 
+    /// キャンペーンの1ステージ
+    Campaign {
+        campaign_hash: u64, 
+        stage: usize, 
+    }, 
+    /// タイムアタックの1ステージ
+    TimeAttack {
+        campaign_hash: u64, 
+        stage: usize, 
+        splits: Vec<u64>, 
+        ticks: u64, 
+        penalties: u32, 
+    }, 
+}
+
+/// ゲームの状態の保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSave {
+    pub score: u64, 
+    pub remain_ball: u32, 
+    pub difficulity: BreakOutDifficulity, 
+    pub extra_life: ExtraLifeRule, 
+    pub next_extra_life: Option<u64>, 
+    /// プレイヤーごとの状態(空の場合は1人)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub players: Vec<PlayerState>, 
+    #[serde(default)]
+    pub lives_rule: LivesRule, 
+}
+
+/// ブロックの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BrickSave {
+    pub position: [f32; 2], 
+    pub size: [f32; 2], 
+    pub color: [f32; 4], 
+    /// `BrickFeature::save_data`で書き出したブロックの機能
+    pub feature: String, 
+    #[serde(default)]
+    pub angle: f32, 
+    #[serde(default, skip_serializing_if = "super::obj_renderer::model::Shape::is_rect")]
+    pub shape: super::obj_renderer::model::Shape, 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion: Option<super::entities::brick::motion::BrickMotion>, 
+}
+
+/// ボールの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BallSave {
+    pub position: [f32; 2], 
+    pub color: [f32; 4], 
+    pub angle: [f32; 2], 
+    pub speed: f32, 
+    pub owner: usize, 
+    #[serde(default)]
+    pub boost: f32, 
+}
+
+/// パドルの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaddleSave {
+    pub position: [f32; 2], 
+    /// パワーアップで広がっている残りのティック数
+    #[serde(default)]
+    pub wide_ticks: u32, 
+    /// ボスの弾で縮んでいる残りのティック数
+    #[serde(default)]
+    pub shrink_ticks: u32, 
+    /// ボスの弾で動けない残りのティック数
+    #[serde(default)]
+    pub stun_ticks: u32, 
+}
+
+/// 落下中のパワーアップの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PowerUpSave {
+    pub kind: super::entities::powerup::PowerUpKind, 
+    pub position: [f32; 2], 
+}
+
+/// 敵の保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnemySave {
+    pub position: [f32; 2], 
+    pub velocity: [f32; 2], 
+    pub steering: super::entities::enemy::Steering, 
+    pub score: u64, 
+    pub id: u64, 
+}
+
+/// 敵の出現状況の保存内容
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EnemiesSave {
+    pub alive: Vec<EnemySave>, 
+    /// ステージ開始からのティック数
+    pub tick: u64, 
+    /// これまでに出現した数
+    pub spawned: u64, 
+}
+
+/// ボスの弾の保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProjectileSave {
+    pub position: [f32; 2], 
+    pub velocity: [f32; 2], 
+    pub effect: super::entities::boss::ProjectileEffect, 
+    pub ticks: u32, 
+}
+
+/// ボスの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BossSave {
+    pub hp: u32, 
+    pub flash: u32, 
+    pub phase: usize, 
+    pub position: [f32; 2], 
+    pub angle: f32, 
+    pub motion: Option<super::entities::brick::motion::BrickMotion>, 
+    pub cooldowns: Vec<u32>, 
+    pub projectiles: Vec<ProjectileSave>, 
+}
+
+/// エンティティの保存内容
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntitiesSave {
+    /// 下の段から順の各段のブロック
+    pub bricks: Vec<Vec<Option<BrickSave>>>, 
+    /// ブロックのステージ開始からのティック数
+    #[serde(default)]
+    pub brick_tick: u64, 
+    pub ball: Option<BallSave>, 
+    pub paddles: Vec<PaddleSave>, 
+    pub server: Option<usize>, 
+    pub clear_on_empty: bool, 
+    #[serde(default)]
+    pub powerups: Vec<PowerUpSave>, 
+    #[serde(default)]
+    pub enemies: EnemiesSave, 
+    #[serde(default)]
+    pub boss: Option<BossSave>, 
+}
+
+/// セーブデータ
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveData {
+    /// 形式のバージョン
+    pub version: u32, 
+    /// 保存した日時
+    pub saved_at: chrono::DateTime<chrono::Local>, 
+    pub mode: SavedMode, 
+    pub state: StateSave, 
+    pub entities: EntitiesSave, 
+}
+impl SavedMode {
+    /// キャンペーンとタイムアタックの場合はそのハッシュ値
+    pub fn campaign_hash(&self) -> Option<u64> { match self {
+        Self::Level { .. } => None, 
+        Self::Campaign { campaign_hash, .. } 
+        | Self::TimeAttack { campaign_hash, .. } => Some(*campaign_hash), 
+    }}
+}
+impl SaveData {
+    pub fn new(mode: SavedMode, state: StateSave, entities: EntitiesSave) -> Self { Self {
+        version: SAVE_VERSION, 
+        saved_at: chrono::Local::now(), 
+        mode, 
+        state, 
+        entities, 
+    }}
+
+    /// メニューに表示する概要
+    pub fn summary(&self) -> String { match &self.mode {
+        SavedMode::Level { level, .. } => level.name.clone(), 
+        SavedMode::Campaign { stage, .. } => format!("Campaign Stage {0}", stage + 1), 
+        SavedMode::TimeAttack { stage, .. } => format!("Time Attack Stage {0}", stage + 1), 
+    }}
+
+    /// ユーザーデータのディレクトリへの保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save_atomic(path(), self)
+    }
+}
+
+/// バージョンの確認用にセーブデータの先頭だけを読む
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32, 
+}
+
+/// セーブデータのパス
+fn path() -> PathBuf {
+    persist::data_dir().join(SAVE_FILE)
+}
+
+/// セーブデータの読み込み
+///
+/// 存在しない場合は`None`を返す。
+/// バージョンが異なるか読み込めないセーブデータは破棄する。
+pub fn load() -> Option<SaveData> {
+    let body = match std::fs::read_to_string(path()) {
+        Ok(body) => body, 
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None, 
+        Err(e) => {
+            log::warn!("failed to read save data: {e}");
+            return None
+        }, 
+    };
+    let data = ron::from_str::<SaveHeader>(&body)
+        .map_err(anyhow::Error::from)
+        .and_then(|h| if h.version == SAVE_VERSION {
+            Ok(ron::from_str::<SaveData>(&body)?)
+        } else {
+            Err(anyhow::anyhow!("unsupported version {0}", h.version))
+        });
+    match data {
+        Ok(data) => Some(data), 
+        Err(e) => {
+            log::warn!("discarded save data: {e}");
+            remove();
+            None
+        }, 
+    }
+}
+
+/// セーブデータの消去
+pub fn remove() {
+    let path = path();
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    for p in [path, backup.into()] {
+        match std::fs::remove_file(&p) {
+            Ok(_) => {}, 
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}, 
+            Err(e) => log::warn!("failed to remove {0}: {e}", p.display()), 
+        }
+    }
+}
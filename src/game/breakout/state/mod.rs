@@ -4,7 +4,7 @@ use std::sync::Arc;
 
 use parking_lot::Mutex;
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameState {
     Yes, 
     GameOver, 
@@ -12,11 +12,79 @@ pub enum GameState {
 }
 
 /// 難易度
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum BreakOutDifficulity {
     Easy, 
     Normal, 
     Hard, 
 }
+impl BreakOutDifficulity {
+    pub fn name(&self) -> &'static str { match self {
+        Self::Easy => "Easy", 
+        Self::Normal => "Normal", 
+        Self::Hard => "Hard", 
+    }}
+}
+
+/// スコアによる残弾の追加の設定
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExtraLifeRule {
+    /// 最初に残弾が増えるスコア(0で無効)
+    pub first: u64, 
+    /// 以降に残弾が増えるスコアの間隔(0で繰り返さない)
+    pub every: u64, 
+    /// 残弾の上限
+    pub max_ball: u32, 
+}
+impl Default for ExtraLifeRule {
+    fn default() -> Self { Self {
+        first: 10_000, 
+        every: 10_000, 
+        max_ball: 9, 
+    }}
+}
+impl ExtraLifeRule {
+    /// 最初のしきい値
+    fn first_threshold(&self) -> Option<u64> {
+        if self.first == 0 { None } else { Some(self.first) }
+    }
+    /// 次のしきい値
+    fn next_threshold(&self, current: u64) -> Option<u64> {
+        if self.every == 0 { None } else { Some(current + self.every) }
+    }
+    /// `score`を超えない間しきい値を進める
+    ///
+    /// 越えたしきい値の数を返す。
+    fn pass(&self, next: &mut Option<u64>, score: u64) -> u32 {
+        let mut passed = 0;
+        while let Some(n) = next.filter(|n| *n <= score) {
+            *next = self.next_threshold(n);
+            passed += 1;
+        }
+        passed
+    }
+}
+
+/// プレイヤーごとの状態
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlayerState {
+    /// そのプレイヤーが最後に触れたボールで得たスコア
+    pub score: u64, 
+    /// 残弾数(`LivesRule::PerPlayer`の場合のみ使用)
+    pub remain_ball: u32, 
+    /// 次に残弾が増えるスコア(`LivesRule::PerPlayer`の場合のみ使用)
+    pub next_extra_life: Option<u64>, 
+}
+
+/// 残弾の扱い
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LivesRule {
+    /// 全員で共有する
+    #[default]
+    Shared, 
+    /// プレイヤーごとに持つ
+    PerPlayer, 
+}
 
 pub struct BreakOutGameState {
     /// 残弾数
@@ -27,6 +95,27 @@ pub struct BreakOutGameState {
     pub score: std::sync::Arc<parking_lot::Mutex<u64>>, 
     /// 難易度
     pub difficulity: BreakOutDifficulity, 
+    /// 残弾の追加の設定
+    extra_life: ExtraLifeRule, 
+    /// 次に残弾が増えるスコア
+    next_extra_life: Option<u64>, 
+    /// プレイヤーごとの状態
+    players: Vec<PlayerState>, 
+    /// 残弾の扱い
+    lives_rule: LivesRule, 
+}
+/// スコアは共有せずに値を複製する
+impl Clone for BreakOutGameState {
+    fn clone(&self) -> Self { Self {
+        remain_ball: self.remain_ball, 
+        state: self.state, 
+        score: Arc::new(Mutex::new(*self.score.lock())), 
+        difficulity: self.difficulity, 
+        extra_life: self.extra_life, 
+        next_extra_life: self.next_extra_life, 
+        players: self.players.clone(), 
+        lives_rule: self.lives_rule, 
+    }}
 }
 impl BreakOutGameState {
     pub fn new() -> Self { Self {
@@ -34,5 +123,153 @@ impl BreakOutGameState {
         state: GameState::Yes,
         score: Arc::new(Mutex::new(0)), 
         difficulity: BreakOutDifficulity::Easy, 
+        extra_life: ExtraLifeRule::default(), 
+        next_extra_life: ExtraLifeRule::default().first_threshold(), 
+        players: vec![PlayerState::default()], 
+        lives_rule: LivesRule::Shared, 
     }}
+
+    /// スコアと残弾数を引き継いで開始する
+    pub fn resume(score: u64, remain_ball: u32) -> Self {
+        let mut state = Self::new();
+        *state.score.lock() = score;
+        state.remain_ball = remain_ball;
+        state.set_extra_life(state.extra_life);
+        state
+    }
+
+    /// 次のステージへ引き継ぐ
+    ///
+    /// スコアと残弾数、残弾の追加の進み具合を残し、難易度と状態を初期化する。
+    pub fn carry_over(self) -> Self { Self {
+        state: GameState::Yes, 
+        difficulity: BreakOutDifficulity::Easy, 
+        ..self
+    }}
+
+    /// 残弾の追加の設定の変更
+    pub fn set_extra_life(&mut self, rule: ExtraLifeRule) {
+        let score = *self.score.lock();
+        let mut next = rule.first_threshold();
+        rule.pass(&mut next, score);
+        self.extra_life = rule;
+        self.next_extra_life = next;
+        for p in self.players.iter_mut() {
+            p.next_extra_life = rule.first_threshold();
+            rule.pass(&mut p.next_extra_life, p.score);
+        }
+    }
+
+    /// セーブデータへの書き出し
+    pub fn to_save(&self) -> super::save::StateSave {
+        super::save::StateSave {
+            score: *self.score.lock(), 
+            remain_ball: self.remain_ball, 
+            difficulity: self.difficulity, 
+            extra_life: self.extra_life, 
+            next_extra_life: self.next_extra_life, 
+            players: self.players.clone(), 
+            lives_rule: self.lives_rule, 
+        }
+    }
+    /// セーブデータからの復元
+    pub fn from_save(save: &super::save::StateSave) -> Self {
+        let mut state = Self::new();
+        *state.score.lock() = save.score;
+        state.remain_ball = save.remain_ball;
+        state.difficulity = save.difficulity;
+        state.extra_life = save.extra_life;
+        state.next_extra_life = save.next_extra_life;
+        if !save.players.is_empty() { state.players = save.players.clone() }
+        state.lives_rule = save.lives_rule;
+        state
+    }
+
+    pub fn remain_ball(&self) -> u32 { self.remain_ball }
+    pub fn game_state(&self) -> GameState { self.state }
+
+    /// プレイヤーの人数と残弾の扱いの設定
+    ///
+    /// `LivesRule::PerPlayer`の場合は各プレイヤーに`remain_ball`個の残弾を与える。
+    pub fn set_players(&mut self, count: usize, lives_rule: LivesRule, remain_ball: u32) {
+        self.players = vec![PlayerState {
+            next_extra_life: self.extra_life.first_threshold(), 
+            ..Default::default()
+        }; count.max(1)];
+        self.lives_rule = lives_rule;
+        match lives_rule {
+            LivesRule::Shared => self.remain_ball = remain_ball, 
+            LivesRule::PerPlayer => {
+                self.remain_ball = 0;
+                self.players.iter_mut().for_each(|p| p.remain_ball = remain_ball);
+            }, 
+        }
+    }
+    pub fn players(&self) -> &[PlayerState] { &self.players }
+    pub fn lives_rule(&self) -> LivesRule { self.lives_rule }
+
+    /// プレイヤーのスコアの加算
+    pub fn add_player_score(&mut self, player: usize, score: u64) {
+        if let Some(p) = self.players.get_mut(player) { p.score += score }
+    }
+
+    /// プレイヤーがボールを打ち出せるか
+    pub fn can_serve(&self, player: usize) -> bool { match self.lives_rule {
+        LivesRule::Shared => self.remain_ball != 0, 
+        LivesRule::PerPlayer => self.players.get(player).is_some_and(|p| p.remain_ball != 0), 
+    }}
+
+    /// 残弾を1つ増やす
+    ///
+    /// 残弾の追加の設定の上限を超えては増やさない。
+    pub fn gain_ball(&mut self, player: usize) { match self.lives_rule {
+        LivesRule::Shared => if self.remain_ball < self.extra_life.max_ball {
+            self.remain_ball += 1
+        }, 
+        LivesRule::PerPlayer => if let Some(p) = self.players.get_mut(player) {
+            if p.remain_ball < self.extra_life.max_ball { p.remain_ball += 1 }
+        }, 
+    }}
+
+    /// プレイヤーがボールを落とした
+    pub fn lose_ball(&mut self, player: usize) { match self.lives_rule {
+        LivesRule::Shared => self.remain_ball = self.remain_ball.saturating_sub(1), 
+        LivesRule::PerPlayer => if let Some(p) = self.players.get_mut(player) {
+            p.remain_ball = p.remain_ball.saturating_sub(1)
+        }, 
+    }}
+
+    /// 残弾が尽きたか
+    pub fn out_of_balls(&self) -> bool { match self.lives_rule {
+        LivesRule::Shared => self.remain_ball == 0, 
+        LivesRule::PerPlayer => self.players.iter().any(|p| p.remain_ball == 0), 
+    }}
+
+    /// スコアがしきい値に達していれば残弾を増やす
+    ///
+    /// `LivesRule::PerPlayer`の場合は各プレイヤーのスコアで判定し、そのプレイヤーの残弾を増やす。
+    /// 残弾が増えた場合は`true`を返す。
+    pub fn award_extra_life(&mut self) -> bool {
+        if self.state != GameState::Yes { return false }
+        let max_ball = self.extra_life.max_ball;
+        match self.lives_rule {
+            LivesRule::Shared => {
+                let score = *self.score.lock();
+                let passed = self.extra_life.pass(&mut self.next_extra_life, score);
+                let gained = passed.min(max_ball.saturating_sub(self.remain_ball));
+                self.remain_ball += gained;
+                gained != 0
+            }, 
+            LivesRule::PerPlayer => {
+                let mut awarded = false;
+                for p in self.players.iter_mut() {
+                    let passed = self.extra_life.pass(&mut p.next_extra_life, p.score);
+                    let gained = passed.min(max_ball.saturating_sub(p.remain_ball));
+                    p.remain_ball += gained;
+                    awarded |= gained != 0;
+                }
+                awarded
+            }, 
+        }
+    }
 }
\ No newline at end of file
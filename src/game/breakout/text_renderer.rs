@@ -42,6 +42,11 @@ impl BreakOutGameTextRenderer {
 									20., 
 									[1., 1., 1., 1.], 
 								), 
+								TextObj::new(
+									"", 
+									20., 
+									[1., 1., 1., 1.], 
+								), 
 								TextObj::new(
 									"", 
 									20., 
@@ -51,6 +56,31 @@ impl BreakOutGameTextRenderer {
 							wgpu_glyph::Layout::default()
 						)
 					);
+					entries.insert(
+						"timer".into(), 
+						TextEntry::new(
+							TextBound::DispSize, 
+							[16., 36.], 
+							vec![
+								TextObj::new(
+									"", 
+									16., 
+									[1., 1., 1., 1.], 
+								), 
+								TextObj::new(
+									"", 
+									16., 
+									[1., 1., 1., 1.], 
+								), 
+								TextObj::new(
+									"", 
+									16., 
+									[1., 0.4, 0.4, 1.], 
+								), 
+							], 
+							wgpu_glyph::Layout::default(), 
+						)
+					);
 					entries.insert(
 						"bottom".into(), 
 						TextEntry::new(
@@ -72,6 +102,11 @@ impl BreakOutGameTextRenderer {
 									20., 
 									[1., 1., 1., 1.], 
 								), 
+								TextObj::new(
+									"", 
+									20., 
+									[1., 1., 0., 1.], 
+								), 
 							], 
 							wgpu_glyph::Layout::default(), 
 						)
@@ -0,0 +1,190 @@
+//! タイムアタック
+//!
+//! キャンペーンのステージを順にクリアするまでの時間を競う。
+//! 時間はシミュレーションのティック数で計るため、ポーズ中や処理落ちの影響を受けない。
+
+use std::{path::PathBuf, sync::Arc};
+
+use serde::{Serialize, Deserialize};
+
+use super::campaign::CampaignDef;
+use super::super::util::persist;
+
+/// 1秒あたりのティック数
+pub const TICKS_PER_SEC: u64 = 60;
+
+/// ボールを落とした際に加算するティック数
+pub const PENALTY_TICKS: u64 = 5 * TICKS_PER_SEC;
+
+/// ティック数を`分:秒.1/100秒`の形式にする
+pub fn format_ticks(ticks: u64) -> String {
+    let centis = ticks * 100 / TICKS_PER_SEC;
+    format!("{0}:{1:02}.{2:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+/// ベストとの差を符号付きで表す
+pub fn format_delta(ticks: u64, best: u64) -> String {
+    if ticks <= best {
+        format!("-{0}", format_ticks(best - ticks))
+    } else {
+        format!("+{0}", format_ticks(ticks - best))
+    }
+}
+
+/// 進行中のタイムアタック
+#[derive(Clone)]
+pub struct TimeAttackRun {
+    pub def: Arc<CampaignDef>, 
+    pub stage: usize, 
+    /// ステージの組のハッシュ値
+    hash: u64, 
+    /// クリアしたステージごとのタイム
+    splits: Vec<u64>, 
+    /// 現在のステージの経過ティック数(ペナルティを含む)
+    ticks: u64, 
+    /// ボールを落とした回数
+    penalties: u32, 
+}
+impl TimeAttackRun {
+    pub fn new(def: Arc<CampaignDef>) -> Self { Self {
+        hash: def.hash(), 
+        def, 
+        stage: 0, 
+        splits: Vec::new(), 
+        ticks: 0, 
+        penalties: 0, 
+    }}
+    /// 中断したステージの途中から再開する
+    pub fn resume(
+        def: Arc<CampaignDef>, 
+        stage: usize, 
+        splits: Vec<u64>, 
+        ticks: u64, 
+        penalties: u32, 
+    ) -> Self { Self {
+        hash: def.hash(), 
+        def, 
+        stage, 
+        splits, 
+        ticks, 
+        penalties, 
+    }}
+    pub fn hash(&self) -> u64 { self.hash }
+    pub fn is_last(&self) -> bool { self.stage + 1 >= self.def.stages.len() }
+    /// 次のステージ
+    pub fn next(&self) -> Option<Self> {
+        if self.is_last() { None } else { Some(Self {
+            def: Arc::clone(&self.def), 
+            stage: self.stage + 1, 
+            hash: self.hash, 
+            splits: self.splits.clone(), 
+            ticks: 0, 
+            penalties: self.penalties, 
+        })}
+    }
+
+    /// 1ティックの経過
+    pub fn tick(&mut self) { self.ticks += 1 }
+
+    /// ボールを落とした際のペナルティ
+    pub fn penalty(&mut self) {
+        self.ticks += PENALTY_TICKS;
+        self.penalties += 1;
+    }
+
+    /// 現在のステージの完了
+    ///
+    /// 既に完了している場合は何もしない。
+    pub fn complete_stage(&mut self) -> bool {
+        if self.splits.len() > self.stage { return false }
+        self.splits.push(self.ticks);
+        true
+    }
+
+    /// 全ステージを完了したか
+    pub fn is_complete(&self) -> bool { self.splits.len() >= self.def.stages.len() }
+
+    pub fn splits(&self) -> &[u64] { &self.splits }
+    pub fn penalties(&self) -> u32 { self.penalties }
+    /// 現在のステージの経過ティック数
+    pub fn stage_ticks(&self) -> u64 { self.ticks }
+
+    /// 開始からの合計ティック数
+    pub fn total(&self) -> u64 {
+        let current = if self.splits.len() > self.stage { 0 } else { self.ticks };
+        self.splits.iter().sum::<u64>() + current
+    }
+}
+
+/// ステージの組ごとのベスト記録
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimeAttackRecord {
+    pub campaign_hash: u64, 
+    /// ステージごとの最速タイム
+    pub best_splits: Vec<Option<u64>>, 
+    /// 全ステージの合計の最速タイム
+    pub best_total: Option<u64>, 
+}
+
+/// タイムアタックの記録
+#[derive(Default, Serialize, Deserialize)]
+pub struct TimeAttackStore {
+    entries: Vec<TimeAttackRecord>, 
+    #[serde(skip)]
+    path: PathBuf, 
+}
+impl TimeAttackStore {
+    /// ユーザーデータのディレクトリから読み込む
+    pub fn load() -> Self {
+        let path = persist::data_dir().join("timeattack.ron");
+        let mut store: Self = persist::load_or_recover(&path);
+        store.path = path;
+        store
+    }
+
+    /// 記録の保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        persist::save_atomic(&self.path, self)
+    }
+
+    pub fn get(&self, campaign_hash: u64) -> Option<&TimeAttackRecord> {
+        self.entries.iter().find(|e| e.campaign_hash == campaign_hash)
+    }
+
+    /// 指定したステージの最速タイム
+    pub fn best_split(&self, campaign_hash: u64, stage: usize) -> Option<u64> {
+        self.get(campaign_hash)
+            .and_then(|e| e.best_splits.get(stage).copied().flatten())
+    }
+
+    /// 完了したステージのタイムの記録
+    ///
+    /// 全ステージを完了していれば合計タイムも記録し、更新した場合は`true`を返す。
+    pub fn record(&mut self, run: &TimeAttackRun) -> bool {
+        let hash = run.hash();
+        let index = match self.entries.iter().position(|e| e.campaign_hash == hash) {
+            Some(i) => i, 
+            None => {
+                self.entries.push(TimeAttackRecord {
+                    campaign_hash: hash, 
+                    best_splits: Vec::new(), 
+                    best_total: None, 
+                });
+                self.entries.len() - 1
+            }, 
+        };
+        let entry = &mut self.entries[index];
+        entry.best_splits.resize(run.def.stages.len().max(entry.best_splits.len()), None);
+        for (best, split) in entry.best_splits.iter_mut().zip(run.splits()) {
+            if best.is_none_or(|b| *split < b) { *best = Some(*split) }
+        }
+        if !run.is_complete() { return false }
+        let total = run.total();
+        if entry.best_total.is_none_or(|b| total < b) {
+            entry.best_total = Some(total);
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! 2人対戦
+//!
+//! 画面の上下にパドルを置き、中央のブロックを奪い合う。
+//! ブロックの得点はボールに最後に触れたプレイヤーのものとなる。
+
+use winit::event::VirtualKeyCode;
+
+use super::entities::paddle::{Paddle, Goal, PaddleInput, PADDLE_LINE};
+use super::level::{LevelDef, BrickDef, BrickType};
+use super::state::{BreakOutGameState, ExtraLifeRule};
+
+/// 各プレイヤーの残弾数
+pub const VERSUS_BALLS: u32 = 3;
+
+/// パドルの割り当て
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersusInput {
+    /// 2人ともキーボード(1P: AD W, 2P: ←→ ↓)
+    Keys, 
+    /// 1Pはマウス、2Pはキーボード(←→ ↓)
+    KeyboardMouse, 
+}
+
+/// 中央に置くブロックの配置
+pub fn level(disp_size: nalgebra::Vector2<f32>) -> LevelDef {
+    let (column, row) = (6, 20);
+    let brick_margin = [4., 4.];
+    let brick_size = [24., 12.];
+    let height = column as f32 * brick_size[1] + (column - 1) as f32 * brick_margin[1];
+    LevelDef {
+        name: "Versus".into(), 
+        column, 
+        row, 
+        margin_top: (disp_size.y - height) / 2., 
+        brick_margin, 
+        brick_size, 
+        bricks: (0..column).map(|y| (0..row).map(|x| {
+            // 上下どちらから見ても同じになるように中央ほど高得点にする
+            let depth = y.min(column - 1 - y);
+            Some(BrickDef {
+                score: 100 * (depth as u64 + 1), 
+                blk_type: BrickType::Normal, 
+                color: [
+                    0.4 + depth as f32 * 0.2, 
+                    x as f32 / row as f32, 
+                    1. - depth as f32 * 0.3, 
+                    1., 
+                ], 
+                script: None, 
+                shape: Default::default(), 
+                angle: 0., 
+                motion: None, 
+            })
+        }).collect()).collect(), 
+        extra_life: ExtraLifeRule { first: 0, every: 0, max_ball: VERSUS_BALLS }, 
+        scripts: Default::default(), 
+        row_motions: Default::default(), 
+        obstacles: Vec::new(), 
+        enemies: Vec::new(), 
+        boss: None, 
+    }
+}
+
+/// 上下のパドル
+pub fn paddles(disp_size: nalgebra::Vector2<f32>, input: VersusInput) -> Vec<Paddle> {
+    let p2_keys = PaddleInput::Keys {
+        left: VirtualKeyCode::Left, 
+        right: VirtualKeyCode::Right, 
+        shot: VirtualKeyCode::Down, 
+    };
+    let p1_input = match input {
+        VersusInput::Keys => PaddleInput::Keys {
+            left: VirtualKeyCode::A, 
+            right: VirtualKeyCode::D, 
+            shot: VirtualKeyCode::W, 
+        }, 
+        VersusInput::KeyboardMouse => PaddleInput::Mouse, 
+    };
+    vec![
+        Paddle::spawn([disp_size.x / 2., PADDLE_LINE].into(), [1., 1., 1., 1.])
+            .for_player(0, Goal::Bottom, p1_input), 
+        Paddle::spawn([disp_size.x / 2., disp_size.y - PADDLE_LINE].into(), super::coop::P2_COLOR)
+            .for_player(1, Goal::Top, p2_keys), 
+    ]
+}
+
+/// 勝者
+///
+/// 残弾が尽きたプレイヤーがいればその相手、いなければスコアの高い方となる。
+/// 引き分けの場合は`None`を返す。
+pub fn winner(state: &BreakOutGameState) -> Option<usize> {
+    let players = state.players();
+    if let Some(loser) = players.iter().position(|p| p.remain_ball == 0) {
+        return Some(1 - loser)
+    }
+    match players[0].score.cmp(&players[1].score) {
+        std::cmp::Ordering::Greater => Some(0), 
+        std::cmp::Ordering::Less => Some(1), 
+        std::cmp::Ordering::Equal => None, 
+    }
+}
@@ -17,6 +17,9 @@ pub trait RenderCommand: Sized + Send + Sync {
 
 /// コマンド用のレンダラのためのコマンド・キュー
 pub struct CommandRendererQueue<RC: RenderCommand> (Vec<Option<RC>>);
+impl<RC: RenderCommand> Default for CommandRendererQueue<RC> {
+    fn default() -> Self { Self(Vec::new()) }
+}
 impl<RC: RenderCommand> CommandRendererQueue<RC> {
     pub fn push<T: TryInto<RC>>(&mut self, comm: T) -> Result<(), T::Error> {
         self.0.push(Some(comm.try_into()?));
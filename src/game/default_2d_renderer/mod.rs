@@ -51,4 +51,31 @@ impl D2DRendererCtx {
             triangle: shape::triangle::TriangleRenderer::new(wgpu_ctx), 
         }
     }
+}
+
+/// 描画の流れに組み込む2Dレンダラ
+///
+/// 積んだ命令を次の描画で順に実行する。
+pub struct D2DRenderer {
+    ctx: D2DRendererCtx, 
+    queue: comm_renderer::CommandRendererQueue<D2DRendererComm>, 
+}
+impl D2DRenderer {
+    pub fn new(wgpu_ctx: &crate::gfx::WGContext) -> Self { Self {
+        ctx: D2DRendererCtx::new(wgpu_ctx), 
+        queue: comm_renderer::CommandRendererQueue::default(), 
+    }}
+    pub fn push(&mut self, comm: D2DRendererComm) {
+        let Ok(()) = self.queue.push(comm);
+    }
+}
+impl crate::gfx::WGRenderer for D2DRenderer {
+    fn rendering(
+        &mut self, 
+        output: &wgpu::SurfaceTexture, 
+        view: &wgpu::TextureView, 
+        ctx: &crate::gfx::WGContext, 
+    ) {
+        self.queue.rendering(&self.ctx, ctx, output, view)
+    }
 }
\ No newline at end of file
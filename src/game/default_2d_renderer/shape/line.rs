@@ -4,6 +4,10 @@ pub struct Line {
 	vertex_buffer: [super::Vertex; 2], 
 }
 impl Line {
+    /// クリップ座標の両端と色からの生成
+    pub fn new(points: [[f32; 2]; 2], color: [f32; 4]) -> Self { Self {
+        vertex_buffer: points.map(|p| super::Vertex::new(p, color)), 
+    }}
     pub fn rendering(
         self, 
         wgpu_ctx: &crate::gfx::WGContext, 
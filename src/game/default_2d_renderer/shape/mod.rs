@@ -12,6 +12,11 @@ pub struct Vertex {
 	pub color: VertexColor, 
 }
 impl Vertex {
+    /// クリップ座標の位置と色からの生成
+    pub fn new(pos: [f32; 2], color: [f32; 4]) -> Self { Self {
+        pos: VertexPos([pos[0], pos[1], 0., 1.]), 
+        color: VertexColor(color), 
+    }}
     const ATTRIBS: [wgpu::VertexAttribute; 2] = wgpu::vertex_attr_array![
         0 => Float32x4, 
         1 => Float32x4, 
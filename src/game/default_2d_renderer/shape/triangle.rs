@@ -20,6 +20,10 @@ pub struct Triangle {
 	vertex_buffer: [super::Vertex; 3], 
 }
 impl Triangle {
+    /// クリップ座標の頂点と色からの生成
+    pub fn new(points: [[f32; 2]; 3], color: [f32; 4]) -> Self { Self {
+        vertex_buffer: points.map(|p| super::Vertex::new(p, color)), 
+    }}
     pub fn rendering(
         self, 
         wgpu_ctx: &crate::gfx::WGContext, 
@@ -0,0 +1,221 @@
+//! 編集中のステージ
+
+use super::super::breakout::level::{BrickDef, BrickType, LevelDef};
+
+/// 取り消せる操作の数
+const UNDO_LIMIT: usize = 100;
+
+/// ブロックの色の一覧
+pub const PALETTE: [[f32; 4]; 8] = [
+    [1., 0.3, 0.3, 1.], 
+    [1., 0.6, 0.2, 1.], 
+    [1., 0.9, 0.2, 1.], 
+    [0.4, 1., 0.4, 1.], 
+    [0.3, 0.9, 1., 1.], 
+    [0.3, 0.4, 1., 1.], 
+    [0.8, 0.4, 1., 1.], 
+    [0.9, 0.9, 0.9, 1.], 
+];
+
+/// 編集の道具
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tool {
+    /// ブラシのブロックを置く
+    Paint, 
+    /// 置かれたブロックの色のみを変える
+    Recolor, 
+    /// ブロックを消す
+    Erase, 
+    /// 置かれたブロックをブラシに取り込む
+    Pick, 
+}
+impl Tool {
+    pub fn name(&self) -> &'static str { match self {
+        Self::Paint => "Paint", 
+        Self::Recolor => "Recolor", 
+        Self::Erase => "Erase", 
+        Self::Pick => "Pick", 
+    }}
+    pub fn next(&self) -> Self { match self {
+        Self::Paint => Self::Recolor, 
+        Self::Recolor => Self::Erase, 
+        Self::Erase => Self::Pick, 
+        Self::Pick => Self::Paint, 
+    }}
+}
+
+/// グリッドの設定項目
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GridParam {
+    Columns, 
+    Rows, 
+    MarginX, 
+    MarginY, 
+    Width, 
+    Height, 
+    Top, 
+}
+impl GridParam {
+    pub const ALL: [Self; 7] = [
+        Self::Columns, 
+        Self::Rows, 
+        Self::MarginX, 
+        Self::MarginY, 
+        Self::Width, 
+        Self::Height, 
+        Self::Top, 
+    ];
+    pub fn name(&self) -> &'static str { match self {
+        Self::Columns => "Lines", 
+        Self::Rows => "Bricks", 
+        Self::MarginX => "Gap X", 
+        Self::MarginY => "Gap Y", 
+        Self::Width => "Width", 
+        Self::Height => "Height", 
+        Self::Top => "Top", 
+    }}
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|p| p == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+    /// 現在の値
+    pub fn value(&self, level: &LevelDef) -> f32 { match self {
+        Self::Columns => level.column as f32, 
+        Self::Rows => level.row as f32, 
+        Self::MarginX => level.brick_margin[0], 
+        Self::MarginY => level.brick_margin[1], 
+        Self::Width => level.brick_size[0], 
+        Self::Height => level.brick_size[1], 
+        Self::Top => level.margin_top, 
+    }}
+}
+
+/// ブロックの種類の切り替え
+pub fn next_type(blk_type: BrickType) -> BrickType { match blk_type {
+    BrickType::Normal => BrickType::Upper, 
+    BrickType::Upper => BrickType::Top, 
+    BrickType::Top => BrickType::Solid, 
+    BrickType::Solid => BrickType::Normal, 
+}}
+
+/// 編集中のステージ
+pub struct EditorDoc {
+    level: LevelDef, 
+    undo: Vec<LevelDef>, 
+    redo: Vec<LevelDef>, 
+    /// 左右対称に編集するか
+    pub mirror: bool, 
+    /// 置くブロック
+    pub brush: BrickDef, 
+}
+impl EditorDoc {
+    pub fn new(level: LevelDef) -> Self { Self {
+        level, 
+        undo: Vec::new(), 
+        redo: Vec::new(), 
+        mirror: false, 
+        brush: BrickDef {
+            score: 100, 
+            blk_type: BrickType::Normal, 
+            color: PALETTE[0], 
+            script: None, 
+            shape: Default::default(), 
+            angle: 0., 
+            motion: None, 
+        }, 
+    }}
+    pub fn level(&self) -> &LevelDef { &self.level }
+    pub fn can_undo(&self) -> bool { !self.undo.is_empty() }
+    pub fn can_redo(&self) -> bool { !self.redo.is_empty() }
+
+    /// 取り消しの単位となる現在の状態の記録
+    pub fn checkpoint(&mut self) {
+        if self.undo.len() >= UNDO_LIMIT { self.undo.remove(0); }
+        self.undo.push(self.level.clone());
+        self.redo.clear();
+    }
+
+    /// 一連の操作の終了
+    ///
+    /// 何も変わらなかった場合は記録を取り下げる。
+    pub fn end_stroke(&mut self) {
+        if self.undo.last() == Some(&self.level) { self.undo.pop(); }
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(level) = self.undo.pop() else { return false };
+        self.redo.push(std::mem::replace(&mut self.level, level));
+        true
+    }
+    pub fn redo(&mut self) -> bool {
+        let Some(level) = self.redo.pop() else { return false };
+        self.undo.push(std::mem::replace(&mut self.level, level));
+        true
+    }
+
+    /// 左右対称の位置
+    pub fn mirrored(&self, pos: [u32; 2]) -> [u32; 2] {
+        [self.level.row.saturating_sub(1 + pos[0]), pos[1]]
+    }
+
+    /// 指定した位置のブロック
+    pub fn brick(&self, pos: [u32; 2]) -> Option<&BrickDef> {
+        self.level.bricks.get(pos[1] as usize)
+            .and_then(|r| r.get(pos[0] as usize))
+            .and_then(|b| b.as_ref())
+    }
+
+    /// 道具の適用
+    ///
+    /// 対称編集が有効な場合は反対側にも適用する。`Tool::Pick`はブラシのみを変える。
+    pub fn apply(&mut self, tool: Tool, pos: [u32; 2]) {
+        if tool == Tool::Pick {
+            if let Some(brick) = self.brick(pos) { self.brush = brick.clone() }
+            return
+        }
+        let mut targets = vec![pos];
+        if self.mirror { targets.push(self.mirrored(pos)) }
+        for [x, y] in targets {
+            let Some(cell) = self.level.bricks.get_mut(y as usize)
+                .and_then(|r| r.get_mut(x as usize)) else { continue };
+            match tool {
+                Tool::Paint => *cell = Some(self.brush.clone()), 
+                Tool::Recolor => if let Some(b) = cell.as_mut() { b.color = self.brush.color }, 
+                Tool::Erase => *cell = None, 
+                Tool::Pick => {}, 
+            }
+        }
+    }
+
+    /// グリッドの設定の変更
+    pub fn adjust(&mut self, param: GridParam, step: i32) {
+        self.checkpoint();
+        let level = &mut self.level;
+        let add = |v: f32, min: f32| (v + step as f32).max(min);
+        match param {
+            GridParam::Columns => {
+                level.column = (level.column as i32 + step).max(1) as u32;
+            }, 
+            GridParam::Rows => {
+                level.row = (level.row as i32 + step).max(1) as u32;
+            }, 
+            GridParam::MarginX => level.brick_margin[0] = add(level.brick_margin[0], 0.), 
+            GridParam::MarginY => level.brick_margin[1] = add(level.brick_margin[1], 0.), 
+            GridParam::Width => level.brick_size[0] = add(level.brick_size[0], 2.), 
+            GridParam::Height => level.brick_size[1] = add(level.brick_size[1], 2.), 
+            GridParam::Top => level.margin_top = add(level.margin_top, 0.), 
+        }
+        // 段数と1段あたりの数に配列を合わせる
+        let (column, row) = (level.column as usize, level.row as usize);
+        level.bricks.resize(column, Vec::new());
+        level.bricks.iter_mut().for_each(|r| r.resize(row, None));
+        self.end_stroke();
+    }
+
+    /// ステージ全体の差し替え
+    pub fn replace(&mut self, level: LevelDef) {
+        self.checkpoint();
+        self.level = level;
+        self.end_stroke();
+    }
+}
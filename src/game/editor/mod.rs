@@ -0,0 +1,405 @@
+//! ステージエディタ
+
+/// 編集中のステージ
+pub mod document;
+
+#[cfg(feature = "client")]
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+
+#[cfg(feature = "client")]
+use super::util::text_renderer::{
+    TextRenderer, 
+    TextRendererGMArc, 
+    entry::{
+        bound::TextBound, 
+        TextEntry, 
+        TextObj, 
+    }, 
+};
+#[cfg(feature = "client")]
+use super::breakout::obj_renderer::{
+    BreakOutRenderer, 
+    model::{AsInstance, Instance, RawInstArray, Shape}, 
+};
+#[cfg(feature = "client")]
+use super::breakout::level::{LevelDef, code};
+
+/// エディタで保存と読み込みをするファイル
+pub const EDITOR_FILE: &str = "./levels/custom.ron";
+
+/// 空いている位置の表示色
+#[cfg(feature = "client")]
+const EMPTY_COLOR: [f32; 4] = [1., 1., 1., 0.08];
+/// カーソルの下の位置の表示色
+#[cfg(feature = "client")]
+const HOVER_COLOR: [f32; 4] = [1., 1., 1., 0.35];
+/// 対称軸の表示色
+#[cfg(feature = "client")]
+const MIRROR_COLOR: [f32; 4] = [0.3, 0.9, 1., 0.4];
+
+/// エディタの画面上の表示物
+#[cfg(feature = "client")]
+struct EditorView<'a> {
+    doc: &'a document::EditorDoc, 
+    cursor: nalgebra::Point2<f32>, 
+    disp_size: nalgebra::Vector2<f32>, 
+}
+#[cfg(feature = "client")]
+impl AsInstance for EditorView<'_> {
+    fn as_instance(&self, instances: &mut RawInstArray) {
+        let level = self.doc.level();
+        let size = nalgebra::Vector2::from(level.brick_size);
+        let hover = level.cell_at(self.disp_size, self.cursor);
+        let hovered = hover.into_iter()
+            .chain(hover.filter(|_| self.doc.mirror).map(|p| self.doc.mirrored(p)))
+            .collect::<Vec<_>>();
+        for y in 0..level.column {
+            for x in 0..level.row {
+                let position = level.brick_center(self.disp_size, [x, y]);
+                let brick = self.doc.brick([x, y]);
+                instances.push(&Instance {
+                    position, 
+                    size, 
+                    angle: brick.map_or(0., |b| b.angle), 
+                    color: brick.map_or(EMPTY_COLOR, |b| b.color), 
+                    shape: brick.map_or(Shape::Rect, |b| b.shape), 
+                });
+                if hovered.contains(&[x, y]) {
+                    instances.push(&Instance { position, size, angle: 0., color: HOVER_COLOR, shape: Shape::Rect });
+                }
+            }
+        }
+        if self.doc.mirror {
+            instances.push(&Instance {
+                position: [self.disp_size.x / 2., self.disp_size.y / 2.].into(), 
+                size: [1., self.disp_size.y].into(), 
+                angle: 0., 
+                color: MIRROR_COLOR, 
+                shape: Shape::Rect, 
+            });
+        }
+        // ブラシの見本
+        instances.push(&Instance {
+            position: [608., 104.].into(), 
+            size, 
+            angle: self.doc.brush.angle, 
+            color: self.doc.brush.color, 
+            shape: self.doc.brush.shape, 
+        });
+        instances.push(&Instance {
+            position: self.cursor, 
+            size: [4., 4.].into(), 
+            angle: 0., 
+            color: [1., 1., 1., 1.], 
+            shape: Shape::Rect, 
+        });
+    }
+}
+
+/// ステージエディタの画面
+#[cfg(feature = "client")]
+pub struct LevelEditor {
+    renderer: BreakOutRenderer, 
+    text: TextRenderer, 
+    doc: document::EditorDoc, 
+    tool: document::Tool, 
+    param: document::GridParam, 
+    /// ゲーム座標でのカーソルの位置
+    cursor: nalgebra::Point2<f32>, 
+    /// 押しているマウスのボタンの道具
+    stroke: Option<document::Tool>, 
+    /// 最後に道具を適用した位置
+    last_cell: Option<[u32; 2]>, 
+    /// 直前の操作の結果
+    status: String, 
+    to_playtest: bool, 
+    to_save: bool, 
+    to_load: bool, 
+    to_export: bool, 
+    to_import: bool, 
+    returned: bool, 
+}
+#[cfg(feature = "client")]
+impl LevelEditor {
+    pub fn new(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: TextRendererGMArc, 
+    ) -> anyhow::Result<Self> {
+        let (level, status) = match LevelDef::load(EDITOR_FILE) {
+            Ok(level) => (level, format!("Loaded {EDITOR_FILE}")), 
+            Err(e) => {
+                log::info!("editor starts from the builtin level: {e}");
+                (LevelDef::builtin(), "New level".into())
+            }, 
+        };
+        let text = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "top".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 8.], 
+                        Vec::new(), 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries.insert(
+                    "bottom".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 560.], 
+                        vec![
+                            TextObj::new(
+                                " Left-click : Apply  Right-click : Erase  Tab : Tool  1-8 : Color \n", 
+                                14., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " T : Type  Q/E : Score  M : Mirror  Z/Y : Undo/Redo \n", 
+                                14., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " G : Grid item  Left/Right or Wheel : Adjust  S/L : Save/Load \n", 
+                                14., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " P : Playtest  C/V : Export/Import code  Backspace : Back ", 
+                                14., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        Ok(Self {
+            renderer: BreakOutRenderer::new(gfx_ctx)?, 
+            text, 
+            doc: document::EditorDoc::new(level), 
+            tool: document::Tool::Paint, 
+            param: document::GridParam::Columns, 
+            cursor: [
+                gfx_ctx.size.width as f32 / 2., 
+                gfx_ctx.size.height as f32 / 2., 
+            ].into(), 
+            stroke: None, 
+            last_cell: None, 
+            status, 
+            to_playtest: false, 
+            to_save: false, 
+            to_load: false, 
+            to_export: false, 
+            to_import: false, 
+            returned: false, 
+        })
+    }
+
+    /// 押しているボタンの道具をカーソルの位置に適用する
+    fn paint(&mut self, disp_size: nalgebra::Vector2<f32>) {
+        let Some(tool) = self.stroke else { return };
+        let cell = self.doc.level().cell_at(disp_size, self.cursor);
+        if cell.is_none() || cell == self.last_cell { return }
+        self.last_cell = cell;
+        if let Some(cell) = cell { self.doc.apply(tool, cell) }
+    }
+
+    /// 情報表示の更新
+    fn write_text(&mut self) {
+        let level = self.doc.level();
+        let brush = &self.doc.brush;
+        let mut text = vec![
+            TextObj::new(
+                format!(" {0} \n", level.name), 
+                20., 
+                [1., 1., 0., 1.], 
+            ), 
+            TextObj::new(
+                format!(
+                    " Tool : {0}  Brush : {1:?} / {2}  Mirror : {3} \n", 
+                    self.tool.name(), 
+                    brush.blk_type, 
+                    brush.score, 
+                    if self.doc.mirror { "On" } else { "Off" }, 
+                ), 
+                16., 
+                [1., 1., 1., 1.], 
+            ), 
+        ];
+        for param in document::GridParam::ALL {
+            let color = if param == self.param { [1., 1., 0., 1.] } else { [0.7, 0.7, 0.7, 1.] };
+            text.push(TextObj::new(
+                format!(" {0} {1} ", param.name(), param.value(level)), 
+                14., 
+                color, 
+            ));
+        }
+        text.push(TextObj::new(format!("\n {0} ", self.status), 14., [0.6, 1., 0.6, 1.]));
+        if let Some(entry) = self.text.get_entry_mut().get_mut("top") {
+            *entry.text_mut() = text;
+        }
+    }
+}
+#[cfg(feature = "client")]
+impl super::scene::GameScene for LevelEditor {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "ステージエディタ".into()
+    }
+
+    fn update(
+        &mut self, 
+        state: &mut super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::scene::SceneController> {
+        let disp_size = nalgebra::Vector2::new(
+            gfx_ctx.size.width as f32, 
+            gfx_ctx.size.height as f32, 
+        );
+        self.cursor.x = self.cursor.x.clamp(0., disp_size.x);
+        self.cursor.y = self.cursor.y.clamp(0., disp_size.y);
+        self.paint(disp_size);
+        if std::mem::take(&mut self.to_save) {
+            self.status = match self.doc.level().save(EDITOR_FILE) {
+                Ok(()) => format!("Saved to {EDITOR_FILE}"), 
+                Err(e) => format!("Save failed : {e}"), 
+            };
+        }
+        if std::mem::take(&mut self.to_load) {
+            self.status = match LevelDef::load(EDITOR_FILE) {
+                Ok(level) => {
+                    self.doc.replace(level);
+                    format!("Loaded {EDITOR_FILE}")
+                }, 
+                Err(e) => format!("Load failed : {e}"), 
+            };
+        }
+        if std::mem::take(&mut self.to_export) {
+            self.status = match code::export(self.doc.level()) {
+                Ok(_) => format!("Code written to {0}", code::share_path().display()), 
+                Err(e) => format!("Export failed : {e}"), 
+            };
+        }
+        if std::mem::take(&mut self.to_import) {
+            self.status = match code::import() {
+                Ok(level) => {
+                    let status = format!("Imported {0}", level.name);
+                    self.doc.replace(level);
+                    status
+                }, 
+                Err(e) => format!("Import failed : {e}"), 
+            };
+        }
+        self.write_text();
+        self.renderer.update(&EditorView {
+            doc: &self.doc, 
+            cursor: self.cursor, 
+            disp_size, 
+        });
+
+        if self.returned {
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::scene::SceneController::PopScene)
+        }
+        if std::mem::take(&mut self.to_playtest) {
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::scene::SceneController::NewScene(Box::new(
+                super::breakout::BreakOut::from_level(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    self.doc.level(), 
+                    super::highscore::GameMode::Normal, 
+                )?.with_playtest()
+            )))
+        }
+        Ok(super::scene::SceneController::NOp)
+    }
+
+    fn key_input(&mut self, keycode: VirtualKeyCode, elem_state: ElementState) {
+        if elem_state != ElementState::Pressed { return }
+        let key_color = [
+            VirtualKeyCode::Key1, 
+            VirtualKeyCode::Key2, 
+            VirtualKeyCode::Key3, 
+            VirtualKeyCode::Key4, 
+            VirtualKeyCode::Key5, 
+            VirtualKeyCode::Key6, 
+            VirtualKeyCode::Key7, 
+            VirtualKeyCode::Key8, 
+        ].iter().position(|k| *k == keycode);
+        if let Some(i) = key_color {
+            self.doc.brush.color = document::PALETTE[i];
+            return
+        }
+        match keycode {
+            VirtualKeyCode::Tab => self.tool = self.tool.next(), 
+            VirtualKeyCode::T => self.doc.brush.blk_type = document::next_type(self.doc.brush.blk_type), 
+            VirtualKeyCode::Q => self.doc.brush.score = self.doc.brush.score.saturating_sub(50), 
+            VirtualKeyCode::E => self.doc.brush.score += 50, 
+            VirtualKeyCode::M => self.doc.mirror = !self.doc.mirror, 
+            VirtualKeyCode::Z => self.status = if self.doc.undo() { "Undo" } else { "Nothing to undo" }.into(), 
+            VirtualKeyCode::Y => self.status = if self.doc.redo() { "Redo" } else { "Nothing to redo" }.into(), 
+            VirtualKeyCode::G => self.param = self.param.next(), 
+            VirtualKeyCode::Left => self.doc.adjust(self.param, -1), 
+            VirtualKeyCode::Right => self.doc.adjust(self.param, 1), 
+            VirtualKeyCode::S => self.to_save = true, 
+            VirtualKeyCode::L => self.to_load = true, 
+            VirtualKeyCode::P => self.to_playtest = true, 
+            VirtualKeyCode::C => self.to_export = true, 
+            VirtualKeyCode::V => self.to_import = true, 
+            VirtualKeyCode::Back => self.returned = true, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_button_input(&mut self, button: MouseButton, elem_state: ElementState) {
+        let tool = match button {
+            MouseButton::Left => self.tool, 
+            MouseButton::Right => document::Tool::Erase, 
+            _ => return, 
+        };
+        match elem_state {
+            ElementState::Pressed if self.stroke.is_none() => {
+                self.doc.checkpoint();
+                self.stroke = Some(tool);
+                self.last_cell = None;
+            }, 
+            ElementState::Released if self.stroke == Some(tool) => {
+                self.doc.end_stroke();
+                self.stroke = None;
+            }, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_wheel_input(&mut self, delta: MouseScrollDelta) {
+        let dy = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y, 
+            MouseScrollDelta::PixelDelta(p) => p.y as f32, 
+        };
+        if dy > 0. { self.doc.adjust(self.param, 1) } else if dy < 0. { self.doc.adjust(self.param, -1) }
+    }
+
+    fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
+        // 画面の座標は下向き、ゲームの座標は上向き
+        self.cursor += nalgebra::Vector2::new(delta.0.x, -delta.0.y);
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.renderer, 
+            &mut self.text, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
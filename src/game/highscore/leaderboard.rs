@@ -0,0 +1,224 @@
+//! ハイスコアの一覧画面
+
+use winit::event::{VirtualKeyCode, ElementState};
+
+use super::super::util::{
+    clear_renderer::ClearRenderer, 
+    text_renderer::{
+        TextRenderer, 
+        TextRendererGMArc, 
+        entry::{
+            bound::TextBound, 
+            TextEntry, 
+            TextObj, 
+        }, 
+    }, 
+};
+
+/// ハイスコアの一覧画面
+pub struct Leaderboard {
+    clear: ClearRenderer, 
+    renderer: TextRenderer, 
+    /// 表示中の表
+    table: usize, 
+    /// 強調表示する記録(表, 順位)
+    highlight: Option<(usize, usize)>, 
+    /// 表の切り替え方向
+    shift: isize, 
+    returned: bool, 
+}
+impl Leaderboard {
+    pub fn new(
+        text_glyph: TextRendererGMArc, 
+        store: &super::HighScoreStore, 
+        target: Option<&super::ScoreTarget>, 
+        rank: Option<usize>, 
+    ) -> anyhow::Result<Self> {
+        let table = target
+            .and_then(|t| store.table_index(t))
+            .unwrap_or(0);
+        let highlight = rank.map(|r| (table, r));
+        let renderer = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "center".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 320.], 
+                        Vec::new(), 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "bottom".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 608.], 
+                        vec![
+                            TextObj::new(
+                                " Left/Right : Change table ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " Enter : Back ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        Ok(Self {
+            clear: ClearRenderer::default(), 
+            renderer, 
+            table, 
+            highlight, 
+            shift: 0, 
+            returned: false, 
+        })
+    }
+
+    /// 表の内容をテキストに反映する
+    fn write_table(&mut self, store: &super::HighScoreStore) {
+        let tables = store.tables();
+        if !tables.is_empty() {
+            self.table = (
+                self.table as isize + self.shift
+            ).rem_euclid(tables.len() as isize) as usize;
+        }
+        self.shift = 0;
+
+        let mut text = vec![TextObj::new(
+            "HIGH SCORES\n\n", 
+            32., 
+            [1., 1., 0., 1.]
+        )];
+        match tables.get(self.table) {
+            None => text.push(TextObj::new(
+                "No records yet.", 
+                20., 
+                [1., 1., 1., 1.]
+            )), 
+            Some(table) => {
+                text.push(TextObj::new(
+                    format!(
+                        "< {0} / {1} >  ({2}/{3})\n\n", 
+                        table.level_name, 
+                        table.mode.name(), 
+                        self.table + 1, 
+                        tables.len(), 
+                    ), 
+                    20., 
+                    [1., 1., 1., 1.]
+                ));
+                for (rank, e) in table.entries.iter().enumerate() {
+                    let color = if self.highlight == Some((self.table, rank)) {
+                        [1., 1., 0., 1.]
+                    } else {
+                        [1., 1., 1., 1.]
+                    };
+                    text.push(TextObj::new(
+                        format!(
+                            "{0:>2}. {1:<10} {2:>8}  {3:<6} {4}\n", 
+                            rank + 1, 
+                            e.name, 
+                            e.score, 
+                            e.difficulity.name(), 
+                            e.date.format("%Y-%m-%d"), 
+                        ), 
+                        16., 
+                        color
+                    ));
+                    if let Some(detail) = e.detail.as_ref() {
+                        text.push(TextObj::new(
+                            format!("    {detail}\n"), 
+                            12., 
+                            color
+                        ));
+                    }
+                }
+            }, 
+        }
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("center") {
+            *entry.text_mut() = text;
+        }
+    }
+}
+impl super::super::scene::GameScene for Leaderboard {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "ハイスコア画面".into()
+    }
+
+    fn update(
+        &mut self, 
+        state: &mut super::super::state::GameState, 
+        _gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::super::scene::SceneController> {
+        self.write_table(&state.high_scores);
+        if self.returned {
+            sfx_ctx.play_resource("pause", |r| r);
+            Ok(super::super::scene::SceneController::PopScene)
+        } else {
+            Ok(super::super::scene::SceneController::NOp)
+        }
+    }
+
+    fn key_input(
+        &mut self, 
+        keycode: VirtualKeyCode, 
+        elem_state: ElementState
+    ) {
+        if elem_state != ElementState::Pressed { return }
+        match keycode {
+            VirtualKeyCode::Left | VirtualKeyCode::A => self.shift -= 1, 
+            VirtualKeyCode::Right | VirtualKeyCode::D => self.shift += 1, 
+            VirtualKeyCode::Return
+            | VirtualKeyCode::NumpadEnter
+            | VirtualKeyCode::Space
+            | VirtualKeyCode::O => self.returned = true, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_button_input(
+        &mut self, 
+        button: winit::event::MouseButton, 
+        elem_state: ElementState
+    ) {
+        if button == winit::event::MouseButton::Left
+            && elem_state == ElementState::Pressed
+        {
+            self.returned = true
+        }
+    }
+
+    fn mouse_wheel_input(&mut self, _delta: winit::event::MouseScrollDelta) {
+    }
+
+    fn mouse_motion_input(&mut self, _delta: crate::MouseMoveInput) {
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.clear, 
+            &mut self.renderer, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
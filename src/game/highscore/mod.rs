@@ -0,0 +1,280 @@
+//! ハイスコアの記録
+
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use super::breakout::{level::LevelDef, state::BreakOutDifficulity};
+use super::util::persist;
+
+/// 名前の入力画面
+#[cfg(feature = "client")]
+pub mod name_entry;
+
+/// ハイスコアの一覧画面
+#[cfg(feature = "client")]
+pub mod leaderboard;
+
+/// 1つの表に記録する件数
+pub const TABLE_LEN: usize = 10;
+
+/// 記録ファイルの形式のバージョン
+const STORE_VERSION: u32 = 1;
+
+/// ゲームモード
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    Normal, 
+    Campaign, 
+    Endless, 
+    Daily, 
+}
+impl GameMode {
+    pub fn name(&self) -> &'static str { match self {
+        Self::Normal => "Normal", 
+        Self::Campaign => "Campaign", 
+        Self::Endless => "Endless", 
+        Self::Daily => "Daily", 
+    }}
+}
+
+/// スコアの記録先
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreTarget {
+    pub mode: GameMode, 
+    pub level_name: String, 
+    pub level_hash: u64, 
+}
+impl ScoreTarget {
+    pub fn new(mode: GameMode, level: &LevelDef) -> Self { Self {
+        mode, 
+        level_name: level.name.clone(), 
+        level_hash: level.hash(), 
+    } }
+}
+
+/// ゲームモードごとの付帯的な記録
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ScoreDetail {
+    /// エンドレスモードで生き延びた行数と降下した距離
+    Endless { rows: u32, distance: u32 }, 
+}
+impl std::fmt::Display for ScoreDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { match self {
+        Self::Endless { rows, distance } => write!(f, "{rows} rows / {distance} px"), 
+    }}
+}
+
+/// 記録待ちのスコア
+#[derive(Clone, Debug)]
+pub struct PendingScore {
+    pub target: ScoreTarget, 
+    pub score: u64, 
+    pub difficulity: BreakOutDifficulity, 
+    pub detail: Option<ScoreDetail>, 
+}
+
+/// ハイスコアの1件
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreEntry {
+    pub name: String, 
+    pub score: u64, 
+    pub date: chrono::DateTime<chrono::Local>, 
+    pub difficulity: BreakOutDifficulity, 
+    pub level_hash: u64, 
+    #[serde(default)]
+    pub detail: Option<ScoreDetail>, 
+}
+
+/// ステージとゲームモードごとのハイスコア表
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HighScoreTable {
+    pub mode: GameMode, 
+    pub level_name: String, 
+    pub level_hash: u64, 
+    pub entries: Vec<ScoreEntry>, 
+}
+impl HighScoreTable {
+    fn is_target(&self, target: &ScoreTarget) -> bool {
+        self.mode == target.mode && self.level_hash == target.level_hash
+    }
+    fn qualifies(&self, score: u64) -> bool {
+        self.entries.len() < TABLE_LEN
+        || self.entries.last().is_none_or(|e| e.score < score)
+    }
+}
+
+/// ハイスコアの記録
+#[derive(Serialize, Deserialize)]
+pub struct HighScoreStore {
+    version: u32, 
+    tables: Vec<HighScoreTable>, 
+    #[serde(skip)]
+    path: PathBuf, 
+    /// 新しい形式のファイルを古い形式で上書きしないよう、保存を拒むか
+    #[serde(skip)]
+    read_only: bool, 
+}
+impl Default for HighScoreStore {
+    fn default() -> Self { Self {
+        version: STORE_VERSION, 
+        tables: Vec::new(), 
+        path: PathBuf::new(), 
+        read_only: false, 
+    }}
+}
+impl HighScoreStore {
+    /// ユーザーデータのディレクトリから読み込む
+    ///
+    /// 対応するより新しいバージョンのファイルは表示のみに用い、上書きしない。
+    pub fn load() -> Self {
+        Self::load_from(persist::data_dir().join("highscore.ron"))
+    }
+
+    /// 指定したパスから読み込む
+    fn load_from(path: PathBuf) -> Self {
+        let mut store: Self = persist::load_or_recover(&path);
+        if store.version > STORE_VERSION {
+            log::warn!(
+                "high score file version {0} is newer than supported; new scores will not be saved.", 
+                store.version
+            );
+            store.read_only = true;
+        } else {
+            store.version = STORE_VERSION;
+        }
+        store.path = path;
+        store
+    }
+
+    /// 記録の保存
+    pub fn save(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            anyhow::bail!(
+                "refusing to overwrite high score file version {0} (supported: {STORE_VERSION})", 
+                self.version
+            )
+        }
+        persist::save_atomic(&self.path, self)
+    }
+
+    pub fn tables(&self) -> &[HighScoreTable] { &self.tables }
+
+    pub fn table(&self, target: &ScoreTarget) -> Option<&HighScoreTable> {
+        self.tables.iter().find(|t| t.is_target(target))
+    }
+
+    pub fn table_index(&self, target: &ScoreTarget) -> Option<usize> {
+        self.tables.iter().position(|t| t.is_target(target))
+    }
+
+    /// スコアが表に載るかどうか
+    pub fn qualifies(&self, target: &ScoreTarget, score: u64) -> bool {
+        score != 0 && self.table(target).is_none_or(|t| t.qualifies(score))
+    }
+
+    /// スコアを記録し、表の中での順位を返す
+    pub fn insert(
+        &mut self, 
+        target: &ScoreTarget, 
+        entry: ScoreEntry, 
+    ) -> Option<usize> {
+        let table = match self.table_index(target) {
+            Some(i) => &mut self.tables[i], 
+            None => {
+                self.tables.push(HighScoreTable {
+                    mode: target.mode, 
+                    level_name: target.level_name.clone(), 
+                    level_hash: target.level_hash, 
+                    entries: Vec::new(), 
+                });
+                self.tables.last_mut().unwrap()
+            }, 
+        };
+        if !table.qualifies(entry.score) { return None }
+        let rank = table.entries.iter()
+            .position(|e| e.score < entry.score)
+            .unwrap_or(table.entries.len());
+        table.entries.insert(rank, entry);
+        table.entries.truncate(TABLE_LEN);
+        table.level_name = target.level_name.clone();
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// テストごとの一時ファイルのパス
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("breakout-highscore-{0}-{name}.ron", std::process::id()))
+    }
+
+    fn target(mode: GameMode) -> ScoreTarget { ScoreTarget {
+        mode, 
+        level_name: "Test".into(), 
+        level_hash: 42, 
+    }}
+
+    fn entry(name: &str, score: u64) -> ScoreEntry { ScoreEntry {
+        name: name.into(), 
+        score, 
+        date: chrono::Local::now(), 
+        difficulity: BreakOutDifficulity::Easy, 
+        level_hash: 42, 
+        detail: None, 
+    }}
+
+    #[test]
+    fn insert_keeps_descending_order() {
+        let mut store = HighScoreStore::default();
+        let target = target(GameMode::Normal);
+        assert_eq!(store.insert(&target, entry("B", 200)), Some(0));
+        assert_eq!(store.insert(&target, entry("A", 300)), Some(0));
+        assert_eq!(store.insert(&target, entry("C", 100)), Some(2));
+        // 同点は後から記録したものを下にする
+        assert_eq!(store.insert(&target, entry("D", 200)), Some(2));
+        let names = store.table(&target).unwrap().entries.iter()
+            .map(|e| e.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["A", "B", "D", "C"]);
+    }
+
+    #[test]
+    fn full_table_is_truncated() {
+        let mut store = HighScoreStore::default();
+        let target = target(GameMode::Normal);
+        for i in 1..=TABLE_LEN as u64 {
+            store.insert(&target, entry("X", i * 100));
+        }
+        assert!(!store.qualifies(&target, 100));
+        assert_eq!(store.insert(&target, entry("Low", 100)), None);
+        assert!(store.qualifies(&target, 150));
+        assert_eq!(store.insert(&target, entry("Mid", 150)), Some(TABLE_LEN - 1));
+        let entries = &store.table(&target).unwrap().entries;
+        assert_eq!(entries.len(), TABLE_LEN);
+        assert_eq!(entries.last().unwrap().score, 150);
+    }
+
+    #[test]
+    fn tables_are_separated_by_mode() {
+        let mut store = HighScoreStore::default();
+        assert!(!store.qualifies(&target(GameMode::Normal), 0));
+        store.insert(&target(GameMode::Normal), entry("N", 100));
+        store.insert(&target(GameMode::Daily), entry("D", 50));
+        assert_eq!(store.tables().len(), 2);
+        assert_eq!(store.table(&target(GameMode::Daily)).unwrap().entries[0].name, "D");
+    }
+
+    #[test]
+    fn newer_file_is_not_overwritten() {
+        let path = temp_path("newer");
+        let body = "(version: 99, tables: [], future_field: 1)";
+        std::fs::write(&path, body).unwrap();
+        let store = HighScoreStore::load_from(path.clone());
+        assert!(store.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), body);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+//! 名前の入力画面
+
+use winit::event::{VirtualKeyCode, ElementState};
+
+use super::super::util::{
+    clear_renderer::ClearRenderer, 
+    text_renderer::{
+        TextRenderer, 
+        TextRendererGMArc, 
+        entry::{
+            bound::TextBound, 
+            TextEntry, 
+            TextObj, 
+        }, 
+    }, 
+};
+
+/// 名前の最大文字数
+const NAME_LEN: usize = 10;
+
+/// キー入力を名前に使える文字へ変換する
+fn keycode_char(keycode: VirtualKeyCode) -> Option<char> {
+    use VirtualKeyCode::*;
+    let letters = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, 
+        N, O, P, Q, R, S, T, U, V, W, X, Y, Z, 
+    ];
+    let digits = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    if let Some(i) = letters.iter().position(|k| *k == keycode) {
+        Some((b'A' + i as u8) as char)
+    } else if let Some(i) = digits.iter().position(|k| *k == keycode) {
+        Some((b'0' + i as u8) as char)
+    } else if keycode == Minus {
+        Some('-')
+    } else {
+        None
+    }
+}
+
+/// 名前の入力画面
+pub struct NameEntry {
+    clear: ClearRenderer, 
+    renderer: TextRenderer, 
+    pending: super::PendingScore, 
+    name: String, 
+    confirmed: bool, 
+}
+impl NameEntry {
+    pub fn new(
+        text_glyph: TextRendererGMArc, 
+        pending: super::PendingScore, 
+    ) -> anyhow::Result<Self> {
+        let renderer = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "center".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 320.], 
+                        vec![
+                            TextObj::new(
+                                "NEW HIGH SCORE!\n\n", 
+                                32., 
+                                [1., 1., 0., 1.]
+                            ), 
+                            TextObj::new(
+                                format!(
+                                    "{0} / {1}\nScore : {2}\n{3}\n", 
+                                    pending.target.level_name, 
+                                    pending.target.mode.name(), 
+                                    pending.score, 
+                                    pending.detail.as_ref().map_or_else(String::new, |d| d.to_string()), 
+                                ), 
+                                20., 
+                                [1., 1., 1., 1.]
+                            ), 
+                            TextObj::new(
+                                "", 
+                                32., 
+                                [1., 1., 1., 1.]
+                            ), 
+                            TextObj::new(
+                                "\n\nA-Z 0-9 : Input  BackSpace : Delete\nEnter : OK", 
+                                16., 
+                                [1., 1., 1., 1.]
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        Ok(Self {
+            clear: ClearRenderer::default(), 
+            renderer, 
+            pending, 
+            name: String::new(), 
+            confirmed: false, 
+        })
+    }
+}
+impl super::super::scene::GameScene for NameEntry {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "名前入力画面".into()
+    }
+
+    fn update(
+        &mut self, 
+        state: &mut super::super::state::GameState, 
+        _gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::super::scene::SceneController> {
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("center") {
+            entry.text_mut()[2].text = format!(
+                "[{0:<1$}]", 
+                self.name, 
+                NAME_LEN, 
+            ).into();
+        }
+        if !self.confirmed {
+            return Ok(super::super::scene::SceneController::NOp)
+        }
+
+        sfx_ctx.play_resource("pause", |r| r);
+        let name = if self.name.is_empty() { "NO NAME".to_string() } else { self.name.clone() };
+        let rank = state.high_scores.insert(
+            &self.pending.target, 
+            super::ScoreEntry {
+                name, 
+                score: self.pending.score, 
+                date: chrono::Local::now(), 
+                difficulity: self.pending.difficulity, 
+                level_hash: self.pending.target.level_hash, 
+                detail: self.pending.detail.clone(), 
+            }, 
+        );
+        if let Err(e) = state.high_scores.save() {
+            log::error!("failed to save high scores: {e}");
+        }
+        Ok(super::super::scene::SceneController::ReplaceScene(Box::new(
+            super::leaderboard::Leaderboard::new(
+                state.font.clone(), 
+                &state.high_scores, 
+                Some(&self.pending.target), 
+                rank, 
+            )?
+        )))
+    }
+
+    fn key_input(
+        &mut self, 
+        keycode: VirtualKeyCode, 
+        elem_state: ElementState
+    ) {
+        if elem_state != ElementState::Pressed { return }
+        match keycode {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.confirmed = true, 
+            VirtualKeyCode::Back => { self.name.pop(); }, 
+            k => if let Some(c) = keycode_char(k) {
+                if self.name.len() < NAME_LEN { self.name.push(c) }
+            }, 
+        }
+    }
+
+    fn mouse_button_input(
+        &mut self, 
+        _button: winit::event::MouseButton, 
+        _elem_state: ElementState
+    ) {
+    }
+
+    fn mouse_wheel_input(&mut self, _delta: winit::event::MouseScrollDelta) {
+    }
+
+    fn mouse_motion_input(&mut self, _delta: crate::MouseMoveInput) {
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.clear, 
+            &mut self.renderer, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
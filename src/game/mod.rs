@@ -1,35 +1,61 @@
 //! ゲーム本体の実装
 
+#[cfg(feature = "client")]
 type PMutex<T> = parking_lot::Mutex<T>;
 
 /// ユーティリティ
 pub mod util;
 
 /// ゲーム本体の状態
+#[cfg(feature = "client")]
 pub mod state;
 
 /// シーンシステム
+#[cfg(feature = "client")]
 pub mod scene;
 
 /// ブロック崩しのメインシステム
 pub mod breakout;
 
 /// ポーズ画面
+#[cfg(feature = "client")]
 pub mod pause;
 
+/// タイトル画面
+#[cfg(feature = "client")]
+pub mod title;
+
+/// ステージセレクト画面
+#[cfg(feature = "client")]
+pub mod select;
+
+/// ハイスコアの記録
+pub mod highscore;
+
 /// コマンドを利用したレンダラ
+#[cfg(feature = "client")]
 pub mod comm_renderer;
 
 /// デフォルトの2Dレンダラ
+#[cfg(feature = "client")]
 pub mod default_2d_renderer;
 
+/// オンライン対戦
+pub mod net;
+
+/// ステージエディタ
+pub mod editor;
+
+#[cfg(feature = "client")]
 use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+#[cfg(feature = "client")]
 pub struct GameCtx {
     gfx_ctx: std::sync::Arc<PMutex<crate::gfx::WGContext>>, 
     scenes: scene::SceneCollector, 
     state: state::GameState, 
     exit: bool, 
 }
+#[cfg(feature = "client")]
 impl GameCtx {
     pub fn new(
         gfx_ctx: std::sync::Arc<PMutex<crate::gfx::WGContext>>, 
@@ -61,13 +87,25 @@ impl GameCtx {
         &mut self, 
         sfx_ctx: &crate::sfx::SfxModule, 
     ) -> anyhow::Result<scene::SceneUpdateResult> {
-        if self.exit { self.scenes.flush_all() }
+        if self.exit {
+            self.autosave();
+            self.scenes.flush_all()
+        }
         self.scenes.update(
             &mut self.state, 
             &self.gfx_ctx.lock(), 
             sfx_ctx, 
         )
     }
+    /// 進行中のゲームの自動保存
+    pub fn autosave(&mut self) {
+        let Some(data) = self.scenes.save_game() else { return };
+        match data.save() {
+            Ok(()) => log::info!("saved the game in progress."), 
+            Err(e) => log::error!("failed to save the game in progress: {e}"), 
+        }
+        self.state.suspended = Some(data);
+    }
     pub fn key_input(&mut self, keycode: VirtualKeyCode, elem_state: ElementState) {
         self.scenes.key_input(match keycode {
             a @ VirtualKeyCode::Escape => {
@@ -0,0 +1,82 @@
+//! 接続の確立
+//!
+//! 待ち受け側(ホスト)が1P、接続する側が2Pとなり、対戦の形式はホストが決める。
+
+use std::net::SocketAddr;
+
+use super::NetMode;
+use super::protocol::Packet;
+use super::transport::{Transport, MAX_PACKET};
+use super::rollback::NetPeer;
+
+/// 接続要求を再送するまでの呼び出し回数
+const RESEND_POLLS: u32 = 30;
+
+/// 接続の役割
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Host, 
+    Join, 
+}
+
+/// 接続の確立の途中
+pub struct Handshake {
+    transport: Option<Box<dyn Transport>>, 
+    role: Role, 
+    mode: Option<NetMode>, 
+    polls: u32, 
+}
+impl Handshake {
+    /// 接続を待ち受ける
+    pub fn host(transport: Box<dyn Transport>, mode: NetMode) -> Self { Self {
+        transport: Some(transport), 
+        role: Role::Host, 
+        mode: Some(mode), 
+        polls: 0, 
+    }}
+
+    /// 指定したアドレスに接続する
+    pub fn join(mut transport: Box<dyn Transport>, addr: SocketAddr) -> Self {
+        transport.set_peer(addr);
+        Self {
+            transport: Some(transport), 
+            role: Role::Join, 
+            mode: None, 
+            polls: 0, 
+        }
+    }
+
+    pub fn role(&self) -> Role { self.role }
+
+    /// 送受信を行い、接続が確立したら対戦相手との接続を返す
+    pub fn poll(&mut self) -> anyhow::Result<Option<NetPeer>> {
+        let Some(transport) = self.transport.as_mut() else {
+            anyhow::bail!("handshake already finished")
+        };
+        if self.role == Role::Join && self.polls.is_multiple_of(RESEND_POLLS) {
+            transport.send(&Packet::Hello.encode())?;
+        }
+        self.polls += 1;
+
+        let mut buf = [0; MAX_PACKET];
+        while let Some((len, from)) = transport.recv(&mut buf)? {
+            match (self.role, Packet::decode(&buf[..len])) {
+                (Role::Host, Some(Packet::Hello)) => {
+                    let mode = self.mode.unwrap();
+                    log::info!("peer connected: {from}");
+                    transport.set_peer(from);
+                    transport.send(&Packet::Welcome { mode }.encode())?;
+                    let transport = self.transport.take().unwrap();
+                    return Ok(Some(NetPeer::new(transport, mode, 0)))
+                }, 
+                (Role::Join, Some(Packet::Welcome { mode })) => {
+                    log::info!("connected to {from} ({0})", mode.name());
+                    let transport = self.transport.take().unwrap();
+                    return Ok(Some(NetPeer::new(transport, mode, 1)))
+                }, 
+                _ => {}, 
+            }
+        }
+        Ok(None)
+    }
+}
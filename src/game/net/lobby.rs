@@ -0,0 +1,345 @@
+//! 接続画面
+
+use std::net::SocketAddr;
+
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+
+use super::super::util::{
+    clear_renderer::ClearRenderer, 
+    text_renderer::{
+        TextRenderer, 
+        TextRendererGMArc, 
+        entry::{
+            bound::TextBound, 
+            TextEntry, 
+            TextObj, 
+        }, 
+    }, 
+};
+use super::NetMode;
+use super::handshake::{Handshake, Role};
+use super::transport::{self, UdpTransport};
+
+/// アドレスの既定値
+const DEFAULT_ADDR: &str = "127.0.0.1:7000";
+
+/// アドレスの最大文字数
+const ADDR_LEN: usize = 40;
+
+/// キー入力をアドレスに使える文字へ変換する
+fn keycode_char(keycode: VirtualKeyCode) -> Option<char> {
+    use VirtualKeyCode::*;
+    let letters = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, 
+        N, O, P, Q, R, S, T, U, V, W, X, Y, Z, 
+    ];
+    let digits = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    let numpad = [
+        Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, 
+        Numpad5, Numpad6, Numpad7, Numpad8, Numpad9, 
+    ];
+    if let Some(i) = letters.iter().position(|k| *k == keycode) {
+        Some((b'a' + i as u8) as char)
+    } else if let Some(i) = digits.iter().position(|k| *k == keycode) {
+        Some((b'0' + i as u8) as char)
+    } else if let Some(i) = numpad.iter().position(|k| *k == keycode) {
+        Some((b'0' + i as u8) as char)
+    } else { match keycode {
+        Period | NumpadDecimal => Some('.'), 
+        Colon | Semicolon => Some(':'), 
+        Minus => Some('-'), 
+        _ => None, 
+    }}
+}
+
+/// 接続画面の項目
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LobbyItem {
+    Role, 
+    Mode, 
+    Address, 
+    Start, 
+}
+impl LobbyItem {
+    const ALL: [Self; 4] = [Self::Role, Self::Mode, Self::Address, Self::Start];
+}
+
+/// 接続画面
+pub struct Lobby {
+    clear: ClearRenderer, 
+    renderer: TextRenderer, 
+    role: Role, 
+    mode: NetMode, 
+    addr: String, 
+    cursor: usize, 
+    shift: isize, 
+    toggle: bool, 
+    selected: bool, 
+    back: bool, 
+    /// 接続中の場合の確立の途中
+    handshake: Option<Handshake>, 
+    status: String, 
+}
+impl Lobby {
+    pub fn new(
+        text_glyph: TextRendererGMArc, 
+    ) -> anyhow::Result<Self> {
+        let renderer = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "title".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 160.], 
+                        vec![
+                            TextObj::new(
+                                "Online", 
+                                40., 
+                                [1., 1., 1., 1.]
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "menu".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 340.], 
+                        Vec::new(), 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "status".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 500.], 
+                        vec![
+                            TextObj::new(
+                                "", 
+                                20., 
+                                [1., 1., 0., 1.]
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "bottom".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 608.], 
+                        vec![
+                            TextObj::new(
+                                " Back : Return ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " Up/Down : Select  Left/Right : Change ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " Enter : OK ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        Ok(Self {
+            clear: ClearRenderer::default(), 
+            renderer, 
+            role: Role::Host, 
+            mode: NetMode::Versus, 
+            addr: DEFAULT_ADDR.into(), 
+            cursor: 0, 
+            shift: 0, 
+            toggle: false, 
+            selected: false, 
+            back: false, 
+            handshake: None, 
+            status: String::new(), 
+        })
+    }
+
+    fn label(&self, item: LobbyItem) -> String { match item {
+        LobbyItem::Role => match self.role {
+            Role::Host => "Role : Host".into(), 
+            Role::Join => "Role : Join".into(), 
+        }, 
+        LobbyItem::Mode => match self.role {
+            Role::Host => format!("Mode : {0}", self.mode.name()), 
+            Role::Join => "Mode : (Host decides)".into(), 
+        }, 
+        LobbyItem::Address => match self.role {
+            Role::Host => format!("Listen : {0}", self.addr), 
+            Role::Join => format!("Connect : {0}", self.addr), 
+        }, 
+        LobbyItem::Start => match self.handshake {
+            Some(_) => "Cancel".into(), 
+            None => "Start".into(), 
+        }, 
+    }}
+
+    /// 接続の開始
+    fn start(&mut self) -> anyhow::Result<()> {
+        let addr: SocketAddr = self.addr.parse()
+            .map_err(|e| anyhow::anyhow!("invalid address {0}: {e}", self.addr))?;
+        self.handshake = Some(match self.role {
+            Role::Host => {
+                let udp = UdpTransport::bind(addr)?;
+                self.status = format!("Waiting on {0} ...", udp.local_addr()?);
+                Handshake::host(transport::from_env(udp), self.mode)
+            }, 
+            Role::Join => {
+                let local = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let udp = UdpTransport::bind(local.parse()?)?;
+                self.status = format!("Connecting to {addr} ...");
+                Handshake::join(transport::from_env(udp), addr)
+            }, 
+        });
+        Ok(())
+    }
+}
+impl super::super::scene::GameScene for Lobby {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "接続画面".into()
+    }
+
+    fn update(
+        &mut self, 
+        state: &mut super::super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::super::scene::SceneController> {
+        if self.back {
+            self.back = false;
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::super::scene::SceneController::PopScene)
+        }
+        if self.shift != 0 || self.toggle {
+            sfx_ctx.play_resource("reflection", |r| r);
+        }
+        let len = LobbyItem::ALL.len() as isize;
+        self.cursor = (self.cursor as isize + self.shift).rem_euclid(len) as usize;
+        self.shift = 0;
+        let item = LobbyItem::ALL[self.cursor];
+        if std::mem::take(&mut self.toggle) && self.handshake.is_none() {
+            match item {
+                LobbyItem::Role => self.role = match self.role {
+                    Role::Host => Role::Join, 
+                    Role::Join => Role::Host, 
+                }, 
+                LobbyItem::Mode => self.mode = match self.mode {
+                    NetMode::Versus => NetMode::Coop, 
+                    NetMode::Coop => NetMode::Versus, 
+                }, 
+                _ => {}, 
+            }
+        }
+        if std::mem::take(&mut self.selected) && item == LobbyItem::Start {
+            sfx_ctx.play_resource("pause", |r| r);
+            if self.handshake.take().is_some() {
+                self.status = "Canceled".into();
+            } else if let Err(e) = self.start() {
+                log::warn!("failed to start connection: {e}");
+                self.status = format!("{e}");
+            }
+        }
+
+        if let Some(handshake) = self.handshake.as_mut() {
+            match handshake.poll() {
+                Ok(Some(peer)) => {
+                    self.handshake = None;
+                    return Ok(super::super::scene::SceneController::ReplaceScene(Box::new(
+                        super::play::NetPlay::new(gfx_ctx, state.font.clone(), peer)?
+                    )))
+                }, 
+                Ok(None) => {}, 
+                Err(e) => {
+                    log::warn!("connection failed: {e}");
+                    self.handshake = None;
+                    self.status = format!("{e}");
+                }, 
+            }
+        }
+
+        let labels = LobbyItem::ALL.iter()
+            .map(|i| self.label(*i))
+            .collect::<Vec<_>>();
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("menu") {
+            *entry.text_mut() = labels.into_iter()
+                .enumerate()
+                .map(|(i, label)| if i == self.cursor {
+                    TextObj::new(format!("> {label} <\n"), 24., [1., 1., 0., 1.])
+                } else {
+                    TextObj::new(format!("{label}\n"), 24., [1., 1., 1., 1.])
+                })
+                .collect();
+        }
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("status") {
+            entry.text_mut()[0].text = self.status.clone().into();
+        }
+        Ok(super::super::scene::SceneController::NOp)
+    }
+
+    fn key_input(&mut self, keycode: VirtualKeyCode, elem_state: ElementState) {
+        if elem_state != ElementState::Pressed { return }
+        let editing = LobbyItem::ALL[self.cursor] == LobbyItem::Address && self.handshake.is_none();
+        match keycode {
+            VirtualKeyCode::Up => self.shift -= 1, 
+            VirtualKeyCode::Down => self.shift += 1, 
+            VirtualKeyCode::Left | VirtualKeyCode::Right => self.toggle = true, 
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.selected = true, 
+            VirtualKeyCode::Back if editing => { self.addr.pop(); }, 
+            VirtualKeyCode::Back => self.back = true, 
+            k if editing => if let Some(c) = keycode_char(k) {
+                if self.addr.len() < ADDR_LEN { self.addr.push(c) }
+            }, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_button_input(&mut self, _button: MouseButton, _elem_state: ElementState) {
+    }
+
+    fn mouse_wheel_input(&mut self, _delta: MouseScrollDelta) {
+    }
+
+    fn mouse_motion_input(&mut self, _delta: crate::MouseMoveInput) {
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.clear, 
+            &mut self.renderer, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
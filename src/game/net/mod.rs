@@ -0,0 +1,50 @@
+//! UDPによるオンライン対戦
+//!
+//! 毎ティックの入力のみを交換し、相手の入力は直前の入力が続くものとして予測する。
+//! 予測が外れた場合は保存した状態まで巻き戻して再計算する(ロールバック)。
+
+/// 通信路
+pub mod transport;
+
+/// パケットの形式
+pub mod protocol;
+
+/// ロールバックによる同期
+pub mod rollback;
+
+/// 接続の確立
+pub mod handshake;
+
+/// 接続画面
+#[cfg(feature = "client")]
+pub mod lobby;
+
+/// オンライン対戦の画面
+#[cfg(feature = "client")]
+pub mod play;
+
+/// シミュレーションに用いる画面の大きさ
+///
+/// 双方で同じ結果になるよう、ウィンドウの大きさに関わらず固定する。
+pub const NET_DISP_SIZE: [f32; 2] = [640., 640.];
+
+/// 対戦の形式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetMode {
+    Versus, 
+    Coop, 
+}
+impl NetMode {
+    pub fn name(&self) -> &'static str { match self {
+        Self::Versus => "Versus", 
+        Self::Coop => "Co-op", 
+    }}
+}
+impl std::str::FromStr for NetMode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> { match s {
+        "versus" => Ok(Self::Versus), 
+        "coop" => Ok(Self::Coop), 
+        _ => anyhow::bail!("unknown mode: {s}"), 
+    }}
+}
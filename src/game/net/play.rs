@@ -0,0 +1,182 @@
+//! オンライン対戦の画面
+
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+
+use super::super::breakout::{
+    entities::paddle::PaddleMoveFlag, 
+    obj_renderer::BreakOutRenderer, 
+    text_renderer::BreakOutGameTextRenderer, 
+    state::{GameState as PlayState, LivesRule}, 
+    versus, 
+};
+use super::NetMode;
+use super::rollback::NetPeer;
+
+/// 状態の不一致の表示色
+const DESYNC_COLOR: [f32; 4] = [1., 0.4, 0.4, 1.];
+
+/// オンライン対戦の画面
+pub struct NetPlay {
+    text: BreakOutGameTextRenderer, 
+    renderer: BreakOutRenderer, 
+    peer: NetPeer, 
+    /// 自分のパドルの操作
+    input: PaddleMoveFlag, 
+    /// 通信の失敗
+    error: Option<String>, 
+    to_back: bool, 
+}
+impl NetPlay {
+    pub fn new(
+        gfx_ctx: &crate::gfx::WGContext, 
+        text_glyph: super::super::util::text_renderer::TextRendererGMArc, 
+        peer: NetPeer, 
+    ) -> anyhow::Result<Self> {
+        let mut text = BreakOutGameTextRenderer::new(text_glyph)?;
+        if let Some(entry) = text.entry_mut("top") {
+            entry.text_mut()[0].text = format!(
+                " Online {0} ({1}P) ", 
+                peer.mode().name(), 
+                peer.session().local() + 1, 
+            ).into();
+        }
+        if let Some(entry) = text.entry_mut("bottom") {
+            entry.text_mut()[1].text = " AD or Cursor : Move ".into();
+            entry.text_mut()[2].text = " Space or Left-click : Shoot ".into();
+        }
+        Ok(Self {
+            text, 
+            renderer: BreakOutRenderer::new(gfx_ctx)?, 
+            peer, 
+            input: PaddleMoveFlag::default(), 
+            error: None, 
+            to_back: false, 
+        })
+    }
+
+    /// 対戦が終わったか
+    fn finished(&self) -> bool {
+        self.peer.session().sim().state.game_state() != PlayState::Yes
+    }
+}
+impl super::super::scene::GameScene for NetPlay {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "オンライン対戦".into()
+    }
+
+    fn update(
+        &mut self, 
+        _state: &mut super::super::state::GameState, 
+        _gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::super::scene::SceneController> {
+        let stopped = self.error.is_some() || self.peer.timed_out();
+        if !stopped {
+            // 終了後も相手が入力を受け取れるように送受信は続ける
+            let local = if self.finished() { None } else { Some(self.input) };
+            if let Err(e) = self.peer.poll(local, sfx_ctx) {
+                log::error!("network error: {e}");
+                self.error = Some(format!("{e}"));
+            }
+            self.input.move_delta = 0.;
+        }
+
+        let session = self.peer.session();
+        let sim = session.sim();
+        self.renderer.update(&sim.entities);
+        let finished = self.finished();
+        if let Some(entry) = self.text.entry_mut("top") {
+            let players = sim.state.players();
+            entry.text_mut()[1].text = match self.peer.mode() {
+                NetMode::Versus => players.iter()
+                    .enumerate()
+                    .map(|(i, p)| format!(" {0}P : {1} ({2}) ", i + 1, p.score, p.remain_ball))
+                    .collect::<String>()
+                    .into(), 
+                NetMode::Coop => format!(
+                    " Score : {0} / Balls : {1} ", 
+                    *sim.state.score.lock(), 
+                    sim.state.remain_ball(), 
+                ).into(), 
+            };
+            entry.text_mut()[2].text = match (finished, sim.state.lives_rule()) {
+                (false, _) => "".into(), 
+                (true, LivesRule::PerPlayer) => match versus::winner(&sim.state) {
+                    Some(p) => format!(" {0}P Wins! ", p + 1).into(), 
+                    None => " Draw ".into(), 
+                }, 
+                (true, LivesRule::Shared) if sim.entities.remain_brick() == 0 => " Game Clear!! ".into(), 
+                (true, LivesRule::Shared) => " Game Over! ".into(), 
+            };
+            entry.text_mut()[3].text = if let Some(e) = self.error.as_ref() {
+                format!(" Error : {e} ").into()
+            } else if self.peer.timed_out() {
+                " Connection lost ".into()
+            } else if !session.can_advance() {
+                " Waiting... ".into()
+            } else {
+                "".into()
+            };
+            if session.desynced() {
+                entry.text_mut()[3].text = " Desync! ".into();
+                entry.text_mut()[3].color = DESYNC_COLOR;
+            }
+        }
+        if let Some(entry) = self.text.entry_mut("timer") {
+            let stats = session.stats();
+            entry.text_mut()[0].text = format!(
+                " Tick : {0} / {1}  Rollbacks : {2} (max {3}) ", 
+                session.tick(), 
+                session.confirmed(), 
+                stats.rollbacks, 
+                stats.max_depth, 
+            ).into();
+        }
+        let can_leave = finished || stopped || self.peer.timed_out();
+        if let Some(entry) = self.text.entry_mut("bottom") {
+            entry.text_mut()[3].text = if can_leave { " Enter : Return ".into() } else { "".into() };
+        }
+        if can_leave && std::mem::take(&mut self.to_back) {
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::super::scene::SceneController::PopScene)
+        }
+        Ok(super::super::scene::SceneController::NOp)
+    }
+
+    fn key_input(&mut self, keycode: VirtualKeyCode, elem_state: ElementState) {
+        let pressed = elem_state == ElementState::Pressed;
+        match keycode {
+            VirtualKeyCode::A | VirtualKeyCode::Left => self.input.move_left = pressed, 
+            VirtualKeyCode::D | VirtualKeyCode::Right => self.input.move_right = pressed, 
+            VirtualKeyCode::Space => self.input.ball_shot = pressed, 
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.to_back = pressed, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_button_input(&mut self, button: MouseButton, elem_state: ElementState) {
+        if button == MouseButton::Left {
+            self.input.ball_shot = elem_state == ElementState::Pressed;
+        }
+    }
+
+    fn mouse_wheel_input(&mut self, _delta: MouseScrollDelta) {
+    }
+
+    fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
+        self.input.move_delta += delta.0.x;
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.renderer, 
+            &mut self.text, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
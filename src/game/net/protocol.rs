@@ -0,0 +1,180 @@
+//! パケットの形式
+//!
+//! 全てのパケットは`MAGIC`と種類を表す1バイトから始まり、数値はリトルエンディアンとする。
+
+use super::super::breakout::entities::paddle::PaddleMoveFlag;
+use super::NetMode;
+
+/// パケットの先頭
+const MAGIC: [u8; 2] = *b"BO";
+
+/// 1つのパケットに含める入力の最大数
+pub const MAX_INPUTS: usize = 64;
+
+/// パケット
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    /// 接続の要求
+    Hello, 
+    /// 接続の受け入れ
+    Welcome { mode: NetMode }, 
+    /// 入力
+    Inputs {
+        /// 相手から受け取り済みの入力のティック数
+        ack: u32, 
+        /// `inputs[0]`のティック
+        start: u32, 
+        inputs: Vec<PaddleMoveFlag>, 
+        /// 確定した状態のティックとハッシュ値
+        checksum: Option<(u32, u64)>, 
+    }, 
+}
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        match self {
+            Self::Hello => buf.push(0), 
+            Self::Welcome { mode } => {
+                buf.push(1);
+                buf.push(match mode {
+                    NetMode::Versus => 0, 
+                    NetMode::Coop => 1, 
+                });
+            }, 
+            Self::Inputs { ack, start, inputs, checksum } => {
+                buf.push(2);
+                buf.extend(ack.to_le_bytes());
+                buf.extend(start.to_le_bytes());
+                let (tick, sum) = checksum.unwrap_or((u32::MAX, 0));
+                buf.extend(tick.to_le_bytes());
+                buf.extend(sum.to_le_bytes());
+                buf.push(inputs.len().min(MAX_INPUTS) as u8);
+                for input in inputs.iter().take(MAX_INPUTS) {
+                    buf.push(
+                        input.move_left as u8
+                        | (input.move_right as u8) << 1
+                        | (input.ball_shot as u8) << 2
+                    );
+                    buf.extend(input.move_delta.to_le_bytes());
+                }
+            }, 
+        }
+        buf
+    }
+
+    /// パケットの読み取り
+    ///
+    /// 形式が正しくない場合は`None`を返す。
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let mut r = Reader(buf);
+        if r.take(2)? != MAGIC { return None }
+        match r.u8()? {
+            0 => Some(Self::Hello), 
+            1 => Some(Self::Welcome { mode: match r.u8()? {
+                0 => NetMode::Versus, 
+                1 => NetMode::Coop, 
+                _ => return None, 
+            }}), 
+            2 => {
+                let ack = r.u32()?;
+                let start = r.u32()?;
+                let tick = r.u32()?;
+                let sum = r.u64()?;
+                let count = r.u8()? as usize;
+                let inputs = (0..count).map(|_| {
+                    let flags = r.u8()?;
+                    Some(PaddleMoveFlag {
+                        move_left: flags & 1 != 0, 
+                        move_right: flags & 2 != 0, 
+                        ball_shot: flags & 4 != 0, 
+                        move_delta: f32::from_le_bytes(r.take(4)?.try_into().ok()?), 
+                    })
+                }).collect::<Option<Vec<_>>>()?;
+                Some(Self::Inputs {
+                    ack, 
+                    start, 
+                    inputs, 
+                    checksum: (tick != u32::MAX).then_some((tick, sum)), 
+                })
+            }, 
+            _ => None, 
+        }
+    }
+}
+
+/// バイト列の読み取り
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len { return None }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Some(head)
+    }
+    fn u8(&mut self) -> Option<u8> { self.take(1).map(|b| b[0]) }
+    fn u32(&mut self) -> Option<u32> { self.take(4)?.try_into().ok().map(u32::from_le_bytes) }
+    fn u64(&mut self) -> Option<u64> { self.take(8)?.try_into().ok().map(u64::from_le_bytes) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples() -> Vec<Packet> {
+        vec![
+            Packet::Hello, 
+            Packet::Welcome { mode: NetMode::Versus }, 
+            Packet::Welcome { mode: NetMode::Coop }, 
+            Packet::Inputs { ack: 0, start: 0, inputs: Vec::new(), checksum: None }, 
+            Packet::Inputs {
+                ack: 12, 
+                start: 7, 
+                inputs: vec![
+                    PaddleMoveFlag { move_left: true, move_right: false, move_delta: 0., ball_shot: false }, 
+                    PaddleMoveFlag { move_left: false, move_right: true, move_delta: -3.5, ball_shot: true }, 
+                ], 
+                checksum: Some((5, 0x0123_4567_89ab_cdef)), 
+            }, 
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        for packet in samples() {
+            assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+        }
+    }
+
+    #[test]
+    fn truncated_buffer_is_rejected() {
+        for packet in samples() {
+            let buf = packet.encode();
+            for len in 0..buf.len() {
+                assert_eq!(Packet::decode(&buf[..len]), None, "{packet:?} truncated to {len} bytes");
+            }
+        }
+    }
+
+    #[test]
+    fn inputs_are_capped() {
+        let packet = Packet::Inputs {
+            ack: 0, 
+            start: 0, 
+            inputs: vec![PaddleMoveFlag::default(); MAX_INPUTS + 10], 
+            checksum: None, 
+        };
+        let Some(Packet::Inputs { inputs, .. }) = Packet::decode(&packet.encode()) else {
+            panic!("capped packet should decode")
+        };
+        assert_eq!(inputs.len(), MAX_INPUTS);
+    }
+
+    #[test]
+    fn bad_magic_and_kind_are_rejected() {
+        let mut buf = Packet::Hello.encode();
+        buf[0] = b'X';
+        assert_eq!(Packet::decode(&buf), None);
+        assert_eq!(Packet::decode(&[MAGIC[0], MAGIC[1], 9]), None);
+        assert_eq!(Packet::decode(&[MAGIC[0], MAGIC[1], 1, 2]), None);
+    }
+}
@@ -0,0 +1,425 @@
+//! ロールバックによる同期
+
+use std::collections::VecDeque;
+
+use super::super::breakout::{
+    entities::{BreakOutEntities, paddle::PaddleMoveFlag}, 
+    level::{BrickFeature, LevelDef}, 
+    state::{BreakOutGameState, LivesRule}, 
+    versus, 
+    coop, 
+};
+use super::{NetMode, NET_DISP_SIZE};
+use super::protocol::{Packet, MAX_INPUTS};
+use super::transport::{Transport, MAX_PACKET};
+
+/// 巻き戻せる最大のティック数
+///
+/// 相手の入力がこれ以上遅れている場合はシミュレーションを止めて待つ。
+pub const MAX_ROLLBACK: u32 = 12;
+
+/// 相手からの受信が途絶えたとみなすまでの呼び出し回数
+pub const TIMEOUT_POLLS: u32 = 300;
+
+/// 保存したハッシュ値の数
+const CHECKSUM_HISTORY: usize = 64;
+
+/// 巻き戻しの対象となる状態
+#[derive(Clone)]
+pub struct Simulation {
+    pub entities: BreakOutEntities<BrickFeature>, 
+    pub state: BreakOutGameState, 
+}
+impl Simulation {
+    pub fn new(mode: NetMode) -> Self {
+        let disp_size = NET_DISP_SIZE.into();
+        let level = match mode {
+            NetMode::Versus => versus::level(disp_size), 
+            NetMode::Coop => LevelDef::builtin(), 
+        };
+        let mut entities = BreakOutEntities::new(level.spawn_param(), disp_size);
+        let mut state = BreakOutGameState::new();
+        state.set_extra_life(level.extra_life);
+        match mode {
+            NetMode::Versus => {
+                state.set_players(2, LivesRule::PerPlayer, versus::VERSUS_BALLS);
+                entities.set_paddles(versus::paddles(disp_size, versus::VersusInput::Keys));
+            }, 
+            NetMode::Coop => {
+                state.set_players(2, LivesRule::Shared, coop::COOP_BALLS);
+                entities.set_paddles(coop::paddles(disp_size));
+            }, 
+        }
+        Self { entities, state }
+    }
+
+    /// 1ティックの計算
+    pub fn step(&mut self, inputs: [PaddleMoveFlag; 2], sfx_ctx: &dyn crate::sfx::SfxSink) {
+        for (player, input) in inputs.into_iter().enumerate() {
+            self.entities.set_input(player, input);
+        }
+        self.entities.update(NET_DISP_SIZE.into(), &mut self.state, sfx_ctx);
+        self.state.award_extra_life();
+    }
+
+    /// 状態の一致の確認用のハッシュ値
+    pub fn checksum(&self) -> u64 {
+        let mut bytes = self.entities.checksum().to_le_bytes().to_vec();
+        bytes.extend(self.state.score.lock().to_le_bytes());
+        bytes.extend(self.state.remain_ball().to_le_bytes());
+        for p in self.state.players() {
+            bytes.extend(p.score.to_le_bytes());
+            bytes.extend(p.remain_ball.to_le_bytes());
+        }
+        super::super::breakout::level::fnv1a(&bytes)
+    }
+}
+
+/// ロールバックの状況
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RollbackStats {
+    /// 巻き戻した回数
+    pub rollbacks: u64, 
+    /// 再計算したティック数の合計
+    pub resimulated: u64, 
+    /// 一度に巻き戻した最大のティック数
+    pub max_depth: u32, 
+    /// 相手を待って止まったティック数
+    pub stalls: u64, 
+}
+
+/// ロールバックによる同期
+pub struct RollbackSession {
+    /// 自分のプレイヤー番号
+    local: usize, 
+    /// 現在の状態
+    sim: Simulation, 
+    /// 計算済みのティック数
+    tick: u32, 
+    /// 保持している入力の最初のティック
+    base: u32, 
+    /// 自分の入力
+    local_inputs: Vec<PaddleMoveFlag>, 
+    /// 受け取った相手の入力
+    remote_inputs: Vec<Option<PaddleMoveFlag>>, 
+    /// 計算に用いた相手の入力(予測を含む)
+    used_remote: Vec<PaddleMoveFlag>, 
+    /// 相手の入力が全て揃っているティック数
+    confirmed: u32, 
+    /// 各ティックを計算する前の状態
+    snapshots: VecDeque<(u32, Simulation)>, 
+    /// 相手が受け取り済みの自分の入力のティック数
+    peer_ack: u32, 
+    /// 確定した状態のハッシュ値
+    checksums: VecDeque<(u32, u64)>, 
+    /// 相手との状態の不一致を検出したか
+    desynced: bool, 
+    stats: RollbackStats, 
+}
+impl RollbackSession {
+    pub fn new(mode: NetMode, local: usize) -> Self { Self {
+        local, 
+        sim: Simulation::new(mode), 
+        tick: 0, 
+        base: 0, 
+        local_inputs: Vec::new(), 
+        remote_inputs: Vec::new(), 
+        used_remote: Vec::new(), 
+        confirmed: 0, 
+        snapshots: VecDeque::new(), 
+        peer_ack: 0, 
+        checksums: VecDeque::new(), 
+        desynced: false, 
+        stats: RollbackStats::default(), 
+    }}
+
+    pub fn sim(&self) -> &Simulation { &self.sim }
+    pub fn local(&self) -> usize { self.local }
+    pub fn tick(&self) -> u32 { self.tick }
+    pub fn confirmed(&self) -> u32 { self.confirmed }
+    pub fn desynced(&self) -> bool { self.desynced }
+    pub fn stats(&self) -> RollbackStats { self.stats }
+
+    /// 相手の入力の予測
+    ///
+    /// 受け取っていない場合は最後に受け取った入力が続くものとする。
+    fn predict(&self, tick: u32) -> PaddleMoveFlag {
+        if let Some(Some(input)) = self.remote_inputs.get((tick - self.base) as usize) { return *input }
+        self.confirmed.checked_sub(1)
+            .and_then(|t| self.remote_inputs[(t - self.base) as usize])
+            .unwrap_or_default()
+    }
+
+    /// プレイヤー番号順の入力
+    fn inputs(&self, local: PaddleMoveFlag, remote: PaddleMoveFlag) -> [PaddleMoveFlag; 2] {
+        if self.local == 0 { [local, remote] } else { [remote, local] }
+    }
+
+    /// 相手の入力の反映
+    pub fn receive(
+        &mut self, 
+        ack: u32, 
+        start: u32, 
+        inputs: &[PaddleMoveFlag], 
+        checksum: Option<(u32, u64)>, 
+    ) {
+        self.peer_ack = self.peer_ack.max(ack);
+        let mut rollback_from = None;
+        for (i, input) in inputs.iter().enumerate() {
+            let t = start + i as u32;
+            // 破棄済みのティックは確定しているので無視する
+            if t < self.base { continue }
+            let index = (t - self.base) as usize;
+            if self.remote_inputs.len() <= index { self.remote_inputs.resize(index + 1, None) }
+            if self.remote_inputs[index].is_some() { continue }
+            self.remote_inputs[index] = Some(*input);
+            if t < self.tick && self.used_remote[index] != *input {
+                rollback_from = Some(rollback_from.map_or(t, |f: u32| f.min(t)));
+            }
+        }
+        while self.remote_inputs.get((self.confirmed - self.base) as usize).is_some_and(|i| i.is_some()) {
+            self.confirmed += 1;
+        }
+        if let Some(from) = rollback_from { self.rollback(from) }
+        self.record_checksum();
+        self.prune_inputs();
+        if let Some((tick, sum)) = checksum {
+            if self.checksums.iter().any(|(t, s)| *t == tick && *s != sum) {
+                if !self.desynced { log::error!("desync detected at tick {tick}") }
+                self.desynced = true;
+            }
+        }
+    }
+
+    /// 指定したティックまで巻き戻して再計算する
+    fn rollback(&mut self, from: u32) {
+        let Some(index) = self.snapshots.iter().position(|(t, _)| *t == from) else {
+            log::error!("no snapshot for tick {from}");
+            self.desynced = true;
+            return
+        };
+        self.snapshots.truncate(index + 1);
+        self.sim = self.snapshots[index].1.clone();
+        self.snapshots.pop_back();
+        let depth = self.tick - from;
+        self.stats.rollbacks += 1;
+        self.stats.resimulated += depth as u64;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        for t in from..self.tick {
+            self.snapshots.push_back((t, self.sim.clone()));
+            let remote = self.predict(t);
+            let index = (t - self.base) as usize;
+            self.used_remote[index] = remote;
+            let inputs = self.inputs(self.local_inputs[index], remote);
+            self.sim.step(inputs, &crate::sfx::MutedSfx);
+        }
+    }
+
+    /// 確定した状態のハッシュ値の記録と古い状態の破棄
+    fn record_checksum(&mut self) {
+        // 確定したティックより前の状態には戻らないので、ハッシュ値のみ残す
+        while self.snapshots.front().is_some_and(|(t, _)| *t < self.confirmed) {
+            let (t, snapshot) = self.snapshots.pop_front().unwrap();
+            self.checksums.push_back((t, snapshot.checksum()));
+            if self.checksums.len() > CHECKSUM_HISTORY { self.checksums.pop_front(); }
+        }
+    }
+
+    /// 古い入力の破棄
+    ///
+    /// 確定したティックから`MAX_ROLLBACK`より前には巻き戻さないため、その入力は捨てる。
+    /// ただし相手が受け取っていない自分の入力は再送のために残す。
+    fn prune_inputs(&mut self) {
+        let keep = self.confirmed.saturating_sub(MAX_ROLLBACK)
+            .min(self.peer_ack)
+            .min(self.tick);
+        if keep <= self.base { return }
+        let count = (keep - self.base) as usize;
+        self.local_inputs.drain(..count);
+        self.remote_inputs.drain(..count);
+        self.used_remote.drain(..count);
+        self.base = keep;
+    }
+
+    /// 次のティックを計算できるか
+    pub fn can_advance(&self) -> bool {
+        self.tick < self.confirmed + MAX_ROLLBACK
+    }
+
+    /// 自分の入力で1ティック進める
+    ///
+    /// 相手の入力を待つ必要がある場合は何もせず`false`を返す。
+    pub fn advance(&mut self, local: PaddleMoveFlag, sfx_ctx: &dyn crate::sfx::SfxSink) -> bool {
+        if !self.can_advance() {
+            self.stats.stalls += 1;
+            return false
+        }
+        self.snapshots.push_back((self.tick, self.sim.clone()));
+        self.local_inputs.push(local);
+        let remote = self.predict(self.tick);
+        self.used_remote.push(remote);
+        let inputs = self.inputs(local, remote);
+        self.sim.step(inputs, sfx_ctx);
+        self.tick += 1;
+        true
+    }
+
+    /// 送信するパケット
+    pub fn packet(&self) -> Packet {
+        let start = self.peer_ack.min(self.tick);
+        let end = self.tick.min(start + MAX_INPUTS as u32);
+        Packet::Inputs {
+            ack: self.confirmed, 
+            start, 
+            inputs: self.local_inputs[(start - self.base) as usize..(end - self.base) as usize].to_vec(), 
+            checksum: self.checksums.back().copied(), 
+        }
+    }
+}
+
+/// 通信路と同期を組み合わせた対戦相手との接続
+pub struct NetPeer {
+    transport: Box<dyn Transport>, 
+    session: RollbackSession, 
+    mode: NetMode, 
+    /// 最後に受信してからの呼び出し回数
+    idle: u32, 
+}
+impl NetPeer {
+    pub fn new(transport: Box<dyn Transport>, mode: NetMode, local: usize) -> Self { Self {
+        transport, 
+        session: RollbackSession::new(mode, local), 
+        mode, 
+        idle: 0, 
+    }}
+    pub fn session(&self) -> &RollbackSession { &self.session }
+    pub fn mode(&self) -> NetMode { self.mode }
+    /// 相手からの受信が途絶えたか
+    pub fn timed_out(&self) -> bool { self.idle >= TIMEOUT_POLLS }
+
+    /// 受信、1ティックの計算、送信を行う
+    ///
+    /// `local`が`None`の場合は計算を進めずに送受信のみ行う。
+    pub fn poll(
+        &mut self, 
+        local: Option<PaddleMoveFlag>, 
+        sfx_ctx: &dyn crate::sfx::SfxSink, 
+    ) -> anyhow::Result<()> {
+        let mut buf = [0; MAX_PACKET];
+        self.idle = self.idle.saturating_add(1);
+        while let Some((len, _)) = self.transport.recv(&mut buf)? {
+            match Packet::decode(&buf[..len]) {
+                Some(Packet::Inputs { ack, start, inputs, checksum }) => {
+                    self.idle = 0;
+                    self.session.receive(ack, start, &inputs, checksum);
+                }, 
+                // 相手が接続の完了を受け取れていない場合は再送する
+                Some(Packet::Hello) => {
+                    self.idle = 0;
+                    self.transport.send(&Packet::Welcome { mode: self.mode }.encode())?;
+                }, 
+                Some(Packet::Welcome { .. }) => self.idle = 0, 
+                None => log::warn!("invalid packet ({len} bytes)"), 
+            }
+        }
+        if let Some(local) = local {
+            self.session.advance(local, sfx_ctx);
+        }
+        self.transport.send(&self.session.packet().encode())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use super::super::transport::{ShimTransport, UdpTransport};
+
+    /// 計算するティック数
+    const TICKS: u32 = 300;
+
+    /// 片道の遅延
+    const LATENCY: Duration = Duration::from_millis(20);
+
+    /// パケットロス率
+    const LOSS: f32 = 0.1;
+
+    /// 打ち切るまでの時間
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// 予測が外れるよう一定の間隔で変える入力
+    fn scripted_input(player: usize, tick: u32) -> PaddleMoveFlag {
+        let phase = (tick / 15 + player as u32 * 7) % 3;
+        PaddleMoveFlag {
+            move_left: phase == 0, 
+            move_right: phase == 1, 
+            move_delta: 0., 
+            ball_shot: tick % 60 < 2, 
+        }
+    }
+
+    fn shim(seed: u64) -> (ShimTransport<UdpTransport>, std::net::SocketAddr) {
+        let udp = UdpTransport::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = udp.local_addr().unwrap();
+        (ShimTransport::new(udp, LATENCY, LOSS, seed), addr)
+    }
+
+    #[test]
+    fn sessions_agree_over_lossy_link() {
+        for mode in [NetMode::Versus, NetMode::Coop] {
+            let (mut host, host_addr) = shim(1);
+            let (mut join, join_addr) = shim(2);
+            host.set_peer(join_addr);
+            join.set_peer(host_addr);
+            let mut peers = [
+                NetPeer::new(Box::new(host), mode, 0), 
+                NetPeer::new(Box::new(join), mode, 1), 
+            ];
+            let started = Instant::now();
+            while !peers.iter().all(|p| p.session().tick() >= TICKS && p.session().confirmed() >= TICKS) {
+                assert!(started.elapsed() < TIMEOUT, "{0} did not finish in time", mode.name());
+                for peer in peers.iter_mut() {
+                    let session = peer.session();
+                    let local = (session.tick() < TICKS)
+                        .then(|| scripted_input(session.local(), session.tick()));
+                    peer.poll(local, &crate::sfx::MutedSfx).unwrap();
+                }
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            let [host, join] = &peers;
+            assert!(!host.session().desynced() && !join.session().desynced());
+            assert!(host.session().stats().rollbacks > 0, "the link should force rollbacks");
+            assert_eq!(host.session().sim().checksum(), join.session().sim().checksum());
+        }
+    }
+
+    #[test]
+    fn old_inputs_are_pruned() {
+        let mut sessions = [
+            RollbackSession::new(NetMode::Versus, 0), 
+            RollbackSession::new(NetMode::Versus, 1), 
+        ];
+        for tick in 0..TICKS * 2 {
+            for (player, session) in sessions.iter_mut().enumerate() {
+                assert!(session.advance(scripted_input(player, tick), &crate::sfx::MutedSfx));
+            }
+            // 相手の入力を1ティック遅れで届け、予測の外れによる巻き戻しを起こす
+            let packets = sessions.each_ref().map(|s| s.packet());
+            for (session, packet) in sessions.iter_mut().zip(packets.into_iter().rev()) {
+                let Packet::Inputs { ack, start, inputs, checksum } = packet else { unreachable!() };
+                session.receive(ack, start, &inputs, checksum);
+            }
+        }
+        for session in sessions.iter() {
+            let kept = (session.tick() - session.base) as usize;
+            assert!(kept <= 2 * MAX_ROLLBACK as usize, "{kept} inputs kept");
+            assert_eq!(session.local_inputs.len(), kept);
+            assert_eq!(session.used_remote.len(), kept);
+            assert!(session.remote_inputs.len() <= kept + 1);
+            assert!(!session.desynced());
+        }
+        assert_eq!(sessions[0].sim().checksum(), sessions[1].sim().checksum());
+    }
+}
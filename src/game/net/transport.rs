@@ -0,0 +1,131 @@
+//! 通信路
+
+use std::{
+    collections::VecDeque, 
+    net::{SocketAddr, UdpSocket}, 
+    time::{Duration, Instant}, 
+};
+
+use rand::{Rng, SeedableRng};
+
+/// パケットの最大長
+pub const MAX_PACKET: usize = 1200;
+
+/// パケットの送受信
+pub trait Transport {
+    /// 相手へのパケットの送信
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()>;
+    /// パケットの受信
+    ///
+    /// 受信したパケットが無い場合は`None`を返す。
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<Option<(usize, SocketAddr)>>;
+    /// 送信先の設定
+    fn set_peer(&mut self, peer: SocketAddr);
+}
+
+/// UDPによる通信路
+pub struct UdpTransport {
+    socket: UdpSocket, 
+    peer: Option<SocketAddr>, 
+}
+impl UdpTransport {
+    /// ノンブロッキングのソケットの作成
+    pub fn bind(addr: SocketAddr) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer: None })
+    }
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> { self.socket.local_addr() }
+}
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        let Some(peer) = self.peer else { return Ok(()) };
+        match self.socket.send_to(packet, peer) {
+            Ok(_) => Ok(()), 
+            // 相手がまだ待ち受けていない場合などは届かなかったものとして扱う
+            Err(e) if matches!(
+                e.kind(), 
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::ConnectionRefused
+            ) => Ok(()), 
+            Err(e) => Err(e), 
+        }
+    }
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<Option<(usize, SocketAddr)>> {
+        loop {
+            match self.socket.recv_from(buf) {
+                // 接続済みの場合は相手以外からのパケットを無視する
+                Ok((_, from)) if self.peer.is_some_and(|p| p != from) => continue, 
+                Ok(r) => return Ok(Some(r)), 
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None), 
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => continue, 
+                Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => continue, 
+                Err(e) => return Err(e), 
+            }
+        }
+    }
+    fn set_peer(&mut self, peer: SocketAddr) { self.peer = Some(peer) }
+}
+
+/// 遅延とパケットロスを模擬する通信路
+///
+/// 送信するパケットを一定の確率で破棄し、残りを指定した時間だけ遅らせて送る。
+pub struct ShimTransport<T: Transport> {
+    inner: T, 
+    latency: Duration, 
+    loss: f32, 
+    rng: rand_pcg::Pcg32, 
+    queue: VecDeque<(Instant, Vec<u8>)>, 
+    /// 破棄したパケットの数
+    dropped: u64, 
+}
+impl<T: Transport> ShimTransport<T> {
+    pub fn new(inner: T, latency: Duration, loss: f32, seed: u64) -> Self { Self {
+        inner, 
+        latency, 
+        loss, 
+        rng: rand_pcg::Pcg32::seed_from_u64(seed), 
+        queue: VecDeque::new(), 
+        dropped: 0, 
+    }}
+    pub fn dropped(&self) -> u64 { self.dropped }
+
+    /// 送信時刻を過ぎたパケットの送信
+    fn flush(&mut self) -> std::io::Result<()> {
+        let now = Instant::now();
+        while self.queue.front().is_some_and(|(t, _)| *t <= now) {
+            let (_, packet) = self.queue.pop_front().unwrap();
+            self.inner.send(&packet)?;
+        }
+        Ok(())
+    }
+}
+impl<T: Transport> Transport for ShimTransport<T> {
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        if self.rng.gen::<f32>() < self.loss {
+            self.dropped += 1;
+        } else {
+            self.queue.push_back((Instant::now() + self.latency, packet.to_vec()));
+        }
+        self.flush()
+    }
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<Option<(usize, SocketAddr)>> {
+        self.flush()?;
+        self.inner.recv(buf)
+    }
+    fn set_peer(&mut self, peer: SocketAddr) { self.inner.set_peer(peer) }
+}
+
+/// 環境変数`BREAKOUT_NET_SHIM`(`遅延[ms],ロス率`)が設定されていれば模擬する通信路で包む
+pub fn from_env(inner: UdpTransport) -> Box<dyn Transport> {
+    let shim = std::env::var("BREAKOUT_NET_SHIM").ok().and_then(|v| {
+        let (latency, loss) = v.split_once(',')?;
+        Some((latency.trim().parse::<u64>().ok()?, loss.trim().parse::<f32>().ok()?))
+    });
+    match shim {
+        Some((latency, loss)) => {
+            log::info!("network shim enabled: latency {latency} ms, loss {loss}");
+            Box::new(ShimTransport::new(inner, Duration::from_millis(latency), loss, latency))
+        }, 
+        None => Box::new(inner), 
+    }
+}
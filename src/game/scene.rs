@@ -21,6 +21,8 @@ pub trait GameScene {
         gfx_ctx: &crate::gfx::WGContext, 
         rendering_chain: crate::gfx::RenderingChain
     ) -> crate::gfx::RenderingChain;
+    /// 中断時に保存するゲームの状態
+    fn save_game(&self) -> Option<super::breakout::save::SaveData> { None }
 }
 
 pub struct SceneCollector (VecDeque<Box<dyn GameScene>>);
@@ -48,6 +50,10 @@ impl SceneCollector {
     pub fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
         self.0.back_mut().map(|s| s.mouse_motion_input(delta));
     }
+    /// 積まれたシーンのうち最も手前のもののセーブデータ
+    pub fn save_game(&self) -> Option<super::breakout::save::SaveData> {
+        self.0.iter().rev().find_map(|s| s.save_game())
+    }
     pub fn flush_all(&mut self) {
         self.0.clear()
     }
@@ -76,6 +82,13 @@ impl SceneCollector {
                     self.0.push_back(ns);
                     Ok(SceneUpdateResult::Updated(None))
                 },
+                SceneController::ReplaceScene(ns) => {
+                    let bk = self.0.pop_back();
+                    log::info!("scene collector replaced back scene.");
+                    log::debug!("replaced scene: {0:?}, new scene name: {1}", bk.as_ref().map(|b| b.name()), ns.name());
+                    self.0.push_back(ns);
+                    Ok(SceneUpdateResult::Updated(bk))
+                },
                 SceneController::RefleshScene(ns) => {
                     log::info!("scene collector refleshed.");
                     log::debug!("new scene name: {0}", ns.name());
@@ -117,6 +130,8 @@ pub enum SceneController {
     NewScene(Box<dyn GameScene>), 
     /// スタックに積まれたシーンを除去し、新しいシーンをプッシュする
     RefleshScene(Box<dyn GameScene>), 
+    /// スタックの末端のシーンを新しいシーンに置き換える
+    ReplaceScene(Box<dyn GameScene>), 
     /// スタックの末端のシーンをポップする
     PopScene, 
 }
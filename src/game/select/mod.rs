@@ -1,6 +1,247 @@
 //! ステージセレクト画面
 
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+
+use super::util::{
+    clear_renderer::ClearRenderer, 
+    text_renderer::{
+        TextRenderer, 
+        TextRendererGMArc, 
+        entry::{
+            bound::TextBound, 
+            TextEntry, 
+            TextObj, 
+        }, 
+    }, 
+};
+use super::breakout::level::{
+    LevelDef, 
+    code, 
+    validate::{self, LevelReport}, 
+};
+use super::breakout::{
+    campaign::CampaignRun, 
+    pack::{self, LevelPack}, 
+    state::BreakOutGameState, 
+};
+
+/// ステージを探すディレクトリ
+pub const LEVEL_DIR: &str = "./levels";
+
+/// 一度に表示するステージの数
+const VISIBLE_ITEMS: usize = 10;
+
+/// 選択できるステージ
+struct StageItem {
+    level: LevelDef, 
+    /// 読み込み元の表示
+    source: String, 
+    report: LevelReport, 
+}
+impl StageItem {
+    fn new(level: LevelDef, source: impl Into<String>) -> Self { Self {
+        report: validate::validate(&level), 
+        level, 
+        source: source.into(), 
+    }}
+}
+
+/// ステージセレクト画面
+///
+/// パックを先に、続けて単独のステージを並べる。
 pub struct StageSelect {
+    clear: ClearRenderer, 
+    renderer: TextRenderer, 
+    packs: Vec<LevelPack>, 
+    items: Vec<StageItem>, 
+    cursor: usize, 
+    shift: isize, 
+    /// 直前の操作の結果
+    status: String, 
+    selected: bool, 
+    to_export: bool, 
+    to_import: bool, 
+    returned: bool, 
+}
+impl StageSelect {
+    pub fn new(
+        text_glyph: TextRendererGMArc, 
+    ) -> anyhow::Result<Self> {
+        let renderer = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "top".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 16.], 
+                        vec![
+                            TextObj::new(
+                                " Select Stage \n", 
+                                32., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries.insert(
+                    "menu".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 80.], 
+                        Vec::new(), 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries.insert(
+                    "bottom".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 584.], 
+                        vec![
+                            TextObj::new(
+                                " WS or Wheel : Select  Enter or Left-click : Play \n", 
+                                18., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " C/V : Export/Import code  Backspace : Back ", 
+                                18., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        Ok(Self {
+            clear: ClearRenderer::default(), 
+            renderer, 
+            packs: pack::scan(), 
+            items: Self::scan(), 
+            cursor: 0, 
+            shift: 0, 
+            status: format!("Imported codes are read from {0}", code::share_path().display()), 
+            selected: false, 
+            to_export: false, 
+            to_import: false, 
+            returned: false, 
+        })
+    }
+
+    /// 組み込みのステージと`LEVEL_DIR`のステージの一覧
+    ///
+    /// キャンペーンの定義など、ステージとして読めないファイルは除く。
+    fn scan() -> Vec<StageItem> {
+        let mut items = vec![StageItem::new(LevelDef::builtin(), "builtin")];
+        let mut paths = match std::fs::read_dir(LEVEL_DIR) {
+            Ok(dir) => dir
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|e| e == "ron"))
+                .collect::<Vec<_>>(), 
+            Err(e) => {
+                log::warn!("failed to read {LEVEL_DIR}: {e}");
+                Vec::new()
+            }, 
+        };
+        paths.sort();
+        for path in paths {
+            match LevelDef::load(&path) {
+                Ok(level) => items.push(StageItem::new(
+                    level, 
+                    path.file_name().unwrap_or_default().to_string_lossy(), 
+                )), 
+                Err(e) => log::debug!("skipped {0}: {e}", path.display()), 
+            }
+        }
+        items
+    }
+
+    /// 共有コードの取り込み
+    ///
+    /// エラーのあるステージは遊べないため取り込まない。
+    fn import(&mut self) -> String {
+        let level = match code::import() {
+            Ok(level) => level, 
+            Err(e) => return format!("Import failed : {e}"), 
+        };
+        let item = StageItem::new(level, "code");
+        if let Some(issue) = item.report.issues.iter()
+            .find(|i| i.severity() == validate::Severity::Error)
+        {
+            return format!("Import rejected : {issue}")
+        }
+        let status = format!("Imported {0}", item.level.name);
+        self.items.push(item);
+        self.cursor = self.len() - 1;
+        status
+    }
+
+    fn len(&self) -> usize { self.packs.len() + self.items.len() }
+
+    /// 選択中のパック
+    fn pack(&self) -> Option<&LevelPack> { self.packs.get(self.cursor) }
+
+    /// 選択中の単独のステージ
+    fn item(&self) -> Option<&StageItem> {
+        self.cursor.checked_sub(self.packs.len()).and_then(|i| self.items.get(i))
+    }
+
+    /// 一覧の表示の更新
+    fn write_text(&mut self, state: &super::state::GameState) {
+        let start = self.cursor.saturating_sub(VISIBLE_ITEMS / 2)
+            .min(self.len().saturating_sub(VISIBLE_ITEMS));
+        let labels = self.packs.iter()
+            .map(|p| match state.campaign_progress.get(p.campaign.hash()) {
+                Some(progress) => format!("[Pack] {0} (Stage {1})", p.name, progress.stage + 1), 
+                None => format!("[Pack] {0}", p.name), 
+            })
+            .chain(self.items.iter().map(|i| i.level.name.clone()));
+        let mut text = labels
+            .enumerate()
+            .skip(start)
+            .take(VISIBLE_ITEMS)
+            .map(|(i, label)| if i == self.cursor {
+                TextObj::new(format!("> {label} \n"), 22., [1., 1., 0., 1.])
+            } else {
+                TextObj::new(format!("  {label} \n"), 22., [1., 1., 1., 1.])
+            })
+            .collect::<Vec<_>>();
+        if let Some(pack) = self.pack() {
+            text.push(TextObj::new(
+                format!(
+                    "\n by {0}  {1} levels \n {2} \n", 
+                    pack.author, 
+                    pack.campaign.stages.len(), 
+                    pack.description, 
+                ), 
+                16., 
+                [0.7, 0.7, 0.7, 1.], 
+            ));
+        }
+        if let Some(item) = self.item() {
+            let stats = &item.report.stats;
+            text.push(TextObj::new(
+                format!(
+                    "\n {0}  Bricks {1}  Score {2}  Difficulty {3} \n", 
+                    item.source, 
+                    stats.clearable, 
+                    stats.total_score, 
+                    stats.rating(), 
+                ), 
+                16., 
+                [0.7, 0.7, 0.7, 1.], 
+            ));
+        }
+        text.push(TextObj::new(format!(" {0} ", self.status), 14., [0.6, 1., 0.6, 1.]));
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("menu") {
+            *entry.text_mut() = text;
+        }
+    }
 }
 impl super::scene::GameScene for StageSelect {
     fn name(&self) -> std::borrow::Cow<'static, str> {
@@ -13,36 +254,113 @@ impl super::scene::GameScene for StageSelect {
         gfx_ctx: &crate::gfx::WGContext, 
         sfx_ctx: &crate::sfx::SfxModule, 
     ) -> anyhow::Result<super::scene::SceneController> {
-        todo!()
+        if self.shift != 0 {
+            sfx_ctx.play_resource("reflection", |r| r);
+        }
+        let len = self.len() as isize;
+        self.cursor = (self.cursor as isize + self.shift).rem_euclid(len) as usize;
+        self.shift = 0;
+
+        if std::mem::take(&mut self.to_export) {
+            self.status = match self.item().map(|i| code::export(&i.level)) {
+                Some(Ok(_)) => format!("Code written to {0}", code::share_path().display()), 
+                Some(Err(e)) => format!("Export failed : {e}"), 
+                None => "Only single levels can be exported".into(), 
+            };
+        }
+        if std::mem::take(&mut self.to_import) {
+            self.status = self.import();
+        }
+        self.write_text(state);
+
+        if self.returned {
+            sfx_ctx.play_resource("pause", |r| r);
+            return Ok(super::scene::SceneController::PopScene)
+        }
+        if !std::mem::take(&mut self.selected) {
+            return Ok(super::scene::SceneController::NOp)
+        }
+        sfx_ctx.play_resource("pause", |r| r);
+        if let Some(pack) = self.pack() {
+            // パックは記録された進行状況から再開する
+            pack.register_sounds(sfx_ctx);
+            let (stage, resumed) = match state.campaign_progress.get(pack.campaign.hash()) {
+                Some(progress) => (
+                    progress.stage, 
+                    BreakOutGameState::resume(progress.score, progress.remain_ball), 
+                ), 
+                None => (0, BreakOutGameState::new()), 
+            };
+            return Ok(super::scene::SceneController::NewScene(Box::new(
+                super::breakout::BreakOut::from_campaign(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    CampaignRun::new(std::sync::Arc::clone(&pack.campaign), stage), 
+                    resumed, 
+                )?
+            )))
+        }
+        let Some(item) = self.item() else { return Ok(super::scene::SceneController::NOp) };
+        Ok(super::scene::SceneController::NewScene(Box::new(
+            super::breakout::BreakOut::from_level(
+                gfx_ctx, 
+                state.font.clone(), 
+                &item.level, 
+                super::highscore::GameMode::Normal, 
+            )?
+        )))
     }
 
     fn key_input(
         &mut self, 
-        keycode: winit::event::VirtualKeyCode, 
-        elem_state: winit::event::ElementState
+        keycode: VirtualKeyCode, 
+        elem_state: ElementState
     ) {
-        todo!()
+        if elem_state != ElementState::Pressed { return }
+        match keycode {
+            VirtualKeyCode::W | VirtualKeyCode::Up => self.shift -= 1, 
+            VirtualKeyCode::S | VirtualKeyCode::Down => self.shift += 1, 
+            VirtualKeyCode::Return
+            | VirtualKeyCode::NumpadEnter
+            | VirtualKeyCode::Space => self.selected = true, 
+            VirtualKeyCode::C => self.to_export = true, 
+            VirtualKeyCode::V => self.to_import = true, 
+            VirtualKeyCode::Back => self.returned = true, 
+            _ => {}, 
+        }
     }
 
     fn mouse_button_input(
         &mut self, 
-        button: winit::event::MouseButton, 
-        elem_state: winit::event::ElementState
+        button: MouseButton, 
+        elem_state: ElementState
     ) {
+        if button == MouseButton::Left && elem_state == ElementState::Pressed {
+            self.selected = true
+        }
     }
 
-    fn mouse_wheel_input(&mut self, delta: winit::event::MouseScrollDelta) {
+    fn mouse_wheel_input(&mut self, delta: MouseScrollDelta) {
+        let dy = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y, 
+            MouseScrollDelta::PixelDelta(p) => p.y as f32, 
+        };
+        if dy > 0. { self.shift -= 1 } else if dy < 0. { self.shift += 1 }
     }
 
-    fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
+    fn mouse_motion_input(&mut self, _delta: crate::MouseMoveInput) {
     }
 
     fn rendering(
         &mut self, 
-        state: &mut super::state::GameState, 
+        _state: &mut super::state::GameState, 
         gfx_ctx: &crate::gfx::WGContext, 
         rendering_chain: crate::gfx::RenderingChain
     ) -> crate::gfx::RenderingChain {
-        todo!()
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.clear, 
+            &mut self.renderer, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
     }
-}
\ No newline at end of file
+}
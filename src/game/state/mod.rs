@@ -6,6 +6,18 @@ use super::util::text_renderer::TextRendererGMArc;
 
 pub struct GameState {
     pub font: TextRendererGMArc, 
+    /// ハイスコアの記録
+    pub high_scores: super::highscore::HighScoreStore, 
+    /// キャンペーンの進行状況
+    pub campaign_progress: super::breakout::campaign::CampaignProgressStore, 
+    /// 日替わりステージへの挑戦の記録
+    pub daily: super::breakout::daily::DailyStore, 
+    /// タイムアタックの記録
+    pub time_attack: super::breakout::time_attack::TimeAttackStore, 
+    /// ステージごとの自己ベストのゴースト
+    pub ghosts: super::breakout::ghost::GhostStore, 
+    /// 中断したゲームのセーブデータ
+    pub suspended: Option<super::breakout::save::SaveData>, 
 }
 impl GameState {
     pub(super) fn new(
@@ -19,8 +31,20 @@ impl GameState {
             gfx_ctx, 
             ttf_bytes
         )?;
+        let high_scores = super::highscore::HighScoreStore::load();
+        let campaign_progress = super::breakout::campaign::CampaignProgressStore::load();
+        let daily = super::breakout::daily::DailyStore::load();
+        let time_attack = super::breakout::time_attack::TimeAttackStore::load();
+        let ghosts = super::breakout::ghost::GhostStore::load();
+        let suspended = super::breakout::save::load();
         Ok(Self {
             font, 
+            high_scores, 
+            campaign_progress, 
+            daily, 
+            time_attack, 
+            ghosts, 
+            suspended, 
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,454 @@
+//! タイトル画面(メニュー)
+
+use std::sync::Arc;
+
+use winit::event::{VirtualKeyCode, ElementState, MouseButton, MouseScrollDelta};
+
+use super::util::{
+    clear_renderer::ClearRenderer, 
+    text_renderer::{
+        TextRenderer, 
+        TextRendererGMArc, 
+        entry::{
+            bound::TextBound, 
+            TextEntry, 
+            TextObj, 
+        }, 
+    }, 
+};
+
+use super::breakout::campaign::{CampaignDef, CampaignRun, DEFAULT_CAMPAIGN};
+use super::breakout::daily::DailyChallenge;
+use super::breakout::time_attack::TimeAttackRun;
+use super::breakout::versus::VersusInput;
+use super::breakout::autopilot::{Autopilot, AutopilotSkill};
+
+/// デモプレイを始めるまでの無操作のフレーム数
+const ATTRACT_IDLE_FRAMES: u32 = 20 * 60;
+
+/// 一度に表示するメニューの項目の数
+///
+/// タイトルと下部の操作説明に重ならないよう、残りはスクロールして表示する。
+const VISIBLE_ITEMS: usize = 7;
+
+/// 隠れた項目があることを示す色
+const MORE_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.];
+
+/// メニューの項目
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TitleMenu {
+    /// 中断したゲームを再開する
+    Resume, 
+    Start, 
+    /// ステージを選んで遊ぶ
+    Select, 
+    /// 巻き戻しのできる練習
+    Practice, 
+    Campaign, 
+    /// 記録されたステージからキャンペーンを再開する
+    Continue(usize), 
+    TimeAttack, 
+    Endless, 
+    /// 日替わりステージ(記録に残るか, 連続日数)
+    Daily(bool, u32), 
+    Versus(VersusInput), 
+    Coop, 
+    Online, 
+    WatchAi, 
+    Editor, 
+    Leaderboard, 
+}
+impl TitleMenu {
+    fn label(&self) -> String { match self {
+        Self::Resume => "Continue Saved Game".into(), 
+        Self::Start => "Start".into(), 
+        Self::Select => "Select Stage".into(), 
+        Self::Practice => "Practice".into(), 
+        Self::Campaign => "Campaign".into(), 
+        Self::Continue(stage) => format!("Continue (Stage {0})", stage + 1), 
+        Self::TimeAttack => "Time Attack".into(), 
+        Self::Endless => "Endless".into(), 
+        Self::Daily(true, streak) => format!("Daily Challenge (Streak {streak})"), 
+        Self::Daily(false, streak) => format!("Daily Practice (Streak {streak})"), 
+        Self::Versus(VersusInput::Keys) => "Versus (Keyboard)".into(), 
+        Self::Versus(VersusInput::KeyboardMouse) => "Versus (Mouse / Keyboard)".into(), 
+        Self::Coop => "Co-op".into(), 
+        Self::Online => "Online".into(), 
+        Self::WatchAi => "Watch AI".into(), 
+        Self::Editor => "Level Editor".into(), 
+        Self::Leaderboard => "High Scores".into(), 
+    }}
+}
+
+/// タイトル画面
+pub struct Title {
+    clear: ClearRenderer, 
+    renderer: TextRenderer, 
+    campaign: Option<Arc<CampaignDef>>, 
+    cursor: usize, 
+    shift: isize, 
+    selected: bool, 
+    /// 無操作のフレーム数
+    idle: u32, 
+}
+impl Title {
+    pub fn new(
+        text_glyph: TextRendererGMArc, 
+    ) -> anyhow::Result<Self> {
+        let renderer = TextRenderer::new(
+            Some({
+                let mut entries = hashbrown::HashMap::new();
+                entries.insert(
+                    "title".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 200.], 
+                        vec![
+                            TextObj::new(
+                                "BreakOut", 
+                                48., 
+                                [1., 1., 1., 1.]
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "menu".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [320., 400.], 
+                        Vec::new(), 
+                        wgpu_glyph::Layout::Wrap {
+                            line_breaker: wgpu_glyph::BuiltInLineBreaker::default(), 
+                            h_align: wgpu_glyph::HorizontalAlign::Center, 
+                            v_align: wgpu_glyph::VerticalAlign::Center
+                        }
+                    )
+                );
+                entries.insert(
+                    "bottom".into(), 
+                    TextEntry::new(
+                        TextBound::DispSize, 
+                        [16., 608.], 
+                        vec![
+                            TextObj::new(
+                                " Esc to Exit ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " WS or Wheel : Select ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                            TextObj::new(
+                                " Enter or Left-click : OK ", 
+                                20., 
+                                [1., 1., 1., 1.], 
+                            ), 
+                        ], 
+                        wgpu_glyph::Layout::default(), 
+                    )
+                );
+                entries
+            }), 
+            text_glyph, 
+        )?;
+        let campaign = match CampaignDef::load(DEFAULT_CAMPAIGN) {
+            Ok(c) => Some(Arc::new(c)), 
+            Err(e) => {
+                log::warn!("failed to load campaign: {e}");
+                None
+            }, 
+        };
+        Ok(Self {
+            clear: ClearRenderer::default(), 
+            renderer, 
+            campaign, 
+            cursor: 0, 
+            shift: 0, 
+            selected: false, 
+            idle: 0, 
+        })
+    }
+}
+impl Title {
+    /// 現在選択できるメニューの項目
+    fn items(&self, state: &super::state::GameState) -> Vec<TitleMenu> {
+        let mut items = Vec::new();
+        if state.suspended.is_some() {
+            items.push(TitleMenu::Resume);
+        }
+        items.push(TitleMenu::Start);
+        items.push(TitleMenu::Select);
+        items.push(TitleMenu::Practice);
+        if let Some(campaign) = self.campaign.as_ref() {
+            items.push(TitleMenu::Campaign);
+            if let Some(progress) = state.campaign_progress.get(campaign.hash()) {
+                items.push(TitleMenu::Continue(progress.stage));
+            }
+            items.push(TitleMenu::TimeAttack);
+        }
+        items.push(TitleMenu::Endless);
+        let today = chrono::Local::now().date_naive();
+        items.push(TitleMenu::Daily(
+            !state.daily.attempted(today), 
+            state.daily.streak(today), 
+        ));
+        items.push(TitleMenu::Versus(VersusInput::Keys));
+        items.push(TitleMenu::Versus(VersusInput::KeyboardMouse));
+        items.push(TitleMenu::Coop);
+        items.push(TitleMenu::Online);
+        items.push(TitleMenu::WatchAi);
+        items.push(TitleMenu::Editor);
+        items.push(TitleMenu::Leaderboard);
+        items
+    }
+}
+impl super::scene::GameScene for Title {
+    fn name(&self) -> std::borrow::Cow<'static, str> {
+        "タイトル画面".into()
+    }
+
+    fn update(
+        &mut self, 
+        state: &mut super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        sfx_ctx: &crate::sfx::SfxModule, 
+    ) -> anyhow::Result<super::scene::SceneController> {
+        let items = self.items(state);
+        let len = items.len() as isize;
+        if self.shift != 0 {
+            sfx_ctx.play_resource("reflection", |r| r);
+        }
+        self.cursor = (self.cursor as isize + self.shift).rem_euclid(len) as usize;
+        self.shift = 0;
+
+        if let Some(entry) = self.renderer.get_entry_mut().get_mut("menu") {
+            let start = self.cursor.saturating_sub(VISIBLE_ITEMS / 2)
+                .min(items.len().saturating_sub(VISIBLE_ITEMS));
+            let end = (start + VISIBLE_ITEMS).min(items.len());
+            // 表示の高さが変わらないよう、隠れた項目が無い側も空行を置く
+            let more = |hidden: bool, mark: &str| TextObj::new(
+                if hidden { format!("{mark}\n") } else { " \n".into() }, 
+                16., 
+                MORE_COLOR, 
+            );
+            *entry.text_mut() = std::iter::once(more(start != 0, "..."))
+                .chain(items[start..end].iter()
+                    .enumerate()
+                    .map(|(i, item)| if start + i == self.cursor {
+                        TextObj::new(format!("> {0} <\n", item.label()), 24., [1., 1., 0., 1.])
+                    } else {
+                        TextObj::new(format!("{0}\n", item.label()), 24., [1., 1., 1., 1.])
+                    }))
+                .chain(std::iter::once(more(end != items.len(), "...")))
+                .collect();
+        }
+
+        self.idle += 1;
+        if self.idle >= ATTRACT_IDLE_FRAMES {
+            // 生成したステージをコンピュータが遊ぶデモプレイ
+            self.idle = 0;
+            let seed = chrono::Local::now().timestamp() as u64;
+            let level = super::breakout::level::generator::generate(seed, &Default::default());
+            return Ok(super::scene::SceneController::NewScene(Box::new(
+                super::breakout::BreakOut::from_level(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    &level, 
+                    super::highscore::GameMode::Normal, 
+                )?.with_autopilot(Autopilot::new(0, AutopilotSkill::Hard, seed), true)
+            )))
+        }
+
+        if !self.selected {
+            return Ok(super::scene::SceneController::NOp)
+        }
+        self.selected = false;
+        sfx_ctx.play_resource("pause", |r| r);
+        Ok(super::scene::SceneController::NewScene(
+            match items[self.cursor] {
+                TitleMenu::Resume => {
+                    let data = state.suspended.as_ref().unwrap();
+                    // 組み込みのキャンペーンでなければパックから探す
+                    let campaign = match data.mode.campaign_hash() {
+                        Some(hash) if self.campaign.as_ref().map(|c| c.hash()) != Some(hash) => {
+                            let pack = super::breakout::pack::scan().into_iter()
+                                .find(|p| p.campaign.hash() == hash);
+                            if let Some(pack) = pack.as_ref() { pack.register_sounds(sfx_ctx) }
+                            pack.map(|p| p.campaign)
+                        }, 
+                        _ => self.campaign.clone(), 
+                    };
+                    match super::breakout::BreakOut::from_save(
+                        gfx_ctx, 
+                        state.font.clone(), 
+                        data, 
+                        campaign.as_ref(), 
+                    ) {
+                        Ok(breakout) => {
+                            // 同じ状態から何度もやり直せないよう、再開できた時点で消去する
+                            state.suspended = None;
+                            super::breakout::save::remove();
+                            Box::new(breakout)
+                        }, 
+                        Err(e) => {
+                            // 再開できなかった場合はセーブデータを残す
+                            log::error!("failed to resume the saved game: {e}");
+                            return Ok(super::scene::SceneController::NOp)
+                        }, 
+                    }
+                }, 
+                TitleMenu::Start => Box::new(super::breakout::BreakOut::from_level(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    &super::breakout::level::LevelDef::builtin(), 
+                    super::highscore::GameMode::Normal, 
+                )?), 
+                TitleMenu::Select => Box::new(super::select::StageSelect::new(
+                    state.font.clone(), 
+                )?), 
+                TitleMenu::Practice => Box::new(super::breakout::BreakOut::from_level(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    &super::breakout::level::LevelDef::builtin(), 
+                    super::highscore::GameMode::Normal, 
+                )?.with_practice()), 
+                TitleMenu::Campaign => Box::new(super::breakout::BreakOut::from_campaign(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    CampaignRun::new(Arc::clone(self.campaign.as_ref().unwrap()), 0), 
+                    super::breakout::state::BreakOutGameState::new(), 
+                )?), 
+                TitleMenu::Continue(stage) => {
+                    let campaign = self.campaign.as_ref().unwrap();
+                    let progress = state.campaign_progress.get(campaign.hash()).unwrap();
+                    let resumed = super::breakout::state::BreakOutGameState::resume(
+                        progress.score, 
+                        progress.remain_ball, 
+                    );
+                    Box::new(super::breakout::BreakOut::from_campaign(
+                        gfx_ctx, 
+                        state.font.clone(), 
+                        CampaignRun::new(Arc::clone(campaign), stage), 
+                        resumed, 
+                    )?)
+                }, 
+                TitleMenu::TimeAttack => Box::new(super::breakout::BreakOut::from_time_attack(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    TimeAttackRun::new(Arc::clone(self.campaign.as_ref().unwrap())), 
+                    super::breakout::state::BreakOutGameState::new(), 
+                )?), 
+                TitleMenu::Endless => Box::new(super::breakout::BreakOut::from_endless(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    chrono::Local::now().timestamp() as u64, 
+                    super::breakout::endless::EndlessParam::default(), 
+                )?), 
+                TitleMenu::Daily(..) => {
+                    let daily = DailyChallenge::today();
+                    // 開始した時点で挑戦したものとして記録する
+                    let scored = state.daily.attempt(daily.date);
+                    if scored {
+                        if let Err(e) = state.daily.save() {
+                            log::error!("failed to save daily record: {e}");
+                        }
+                    }
+                    Box::new(super::breakout::BreakOut::from_daily(
+                        gfx_ctx, 
+                        state.font.clone(), 
+                        &daily, 
+                        scored, 
+                    )?)
+                }, 
+                TitleMenu::Versus(input) => Box::new(super::breakout::BreakOut::from_versus(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    input, 
+                )?), 
+                TitleMenu::Coop => Box::new(super::breakout::BreakOut::from_coop(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    &super::breakout::level::LevelDef::builtin(), 
+                )?), 
+                TitleMenu::Online => Box::new(super::net::lobby::Lobby::new(
+                    state.font.clone(), 
+                )?), 
+                TitleMenu::WatchAi => Box::new(super::breakout::BreakOut::from_level(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                    &super::breakout::level::LevelDef::builtin(), 
+                    super::highscore::GameMode::Normal, 
+                )?.with_autopilot(Autopilot::new(
+                    0, 
+                    AutopilotSkill::default(), 
+                    chrono::Local::now().timestamp() as u64, 
+                ), false)), 
+                TitleMenu::Editor => Box::new(super::editor::LevelEditor::new(
+                    gfx_ctx, 
+                    state.font.clone(), 
+                )?), 
+                TitleMenu::Leaderboard => Box::new(super::highscore::leaderboard::Leaderboard::new(
+                    state.font.clone(), 
+                    &state.high_scores, 
+                    None, 
+                    None, 
+                )?), 
+            }
+        ))
+    }
+
+    fn key_input(&mut self, keycode: VirtualKeyCode, elem_state: ElementState) {
+        self.idle = 0;
+        if elem_state != ElementState::Pressed { return }
+        match keycode {
+            VirtualKeyCode::W | VirtualKeyCode::Up => self.shift -= 1, 
+            VirtualKeyCode::S | VirtualKeyCode::Down => self.shift += 1, 
+            VirtualKeyCode::Return
+            | VirtualKeyCode::NumpadEnter
+            | VirtualKeyCode::Space => self.selected = true, 
+            _ => {}, 
+        }
+    }
+
+    fn mouse_button_input(&mut self, button: MouseButton, elem_state: ElementState) {
+        self.idle = 0;
+        if button == MouseButton::Left && elem_state == ElementState::Pressed {
+            self.selected = true
+        }
+    }
+
+    fn mouse_wheel_input(&mut self, delta: MouseScrollDelta) {
+        self.idle = 0;
+        let dy = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y, 
+            MouseScrollDelta::PixelDelta(p) => p.y as f32, 
+        };
+        if dy > 0. { self.shift -= 1 } else if dy < 0. { self.shift += 1 }
+    }
+
+    fn mouse_motion_input(&mut self, delta: crate::MouseMoveInput) {
+        // 移動量は毎フレーム届くため、動いた場合のみ操作とみなす
+        if delta.0 != nalgebra::Vector2::zeros() { self.idle = 0 }
+    }
+
+    fn rendering(
+        &mut self, 
+        _state: &mut super::state::GameState, 
+        gfx_ctx: &crate::gfx::WGContext, 
+        rendering_chain: crate::gfx::RenderingChain
+    ) -> crate::gfx::RenderingChain {
+        let r: [&mut dyn crate::gfx::WGRenderer; 2] = [
+            &mut self.clear, 
+            &mut self.renderer, 
+        ];
+        rendering_chain.rendering(gfx_ctx, r)
+    }
+}
@@ -0,0 +1,43 @@
+//! 画面消去専用のレンダラ
+
+/// 画面を単色で塗りつぶすだけのレンダラ
+pub struct ClearRenderer {
+    color: wgpu::Color, 
+}
+impl ClearRenderer {
+    pub fn new(color: wgpu::Color) -> Self { Self {
+        color, 
+    } }
+}
+impl Default for ClearRenderer {
+    fn default() -> Self { Self::new(wgpu::Color::BLACK) }
+}
+impl crate::gfx::WGRenderer for ClearRenderer {
+    fn rendering(
+        &mut self, 
+        _output: &wgpu::SurfaceTexture, 
+        view: &wgpu::TextureView, 
+        ctx: &crate::gfx::WGContext, 
+    ) {
+        let mut enc = ctx.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Clear encoder")
+            }
+        );
+        {
+            let _ = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Clear pass"), 
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view, 
+                    resolve_target: None, 
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.color), 
+                        store: true, 
+                    }, 
+                })], 
+                depth_stencil_attachment: None, 
+            });
+        }
+        ctx.queue.submit(std::iter::once(enc.finish()));
+    }
+}
@@ -1,4 +1,12 @@
 //! ユーティリティ
 
 /// テキスト専用のレンダラ
-pub mod text_renderer;
\ No newline at end of file
+#[cfg(feature = "client")]
+pub mod text_renderer;
+
+/// 画面消去専用のレンダラ
+#[cfg(feature = "client")]
+pub mod clear_renderer;
+
+/// ユーザーデータの永続化
+pub mod persist;
\ No newline at end of file
@@ -0,0 +1,153 @@
+//! ユーザーデータの永続化
+
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// ユーザーデータの保存先ディレクトリ
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("breakout")
+}
+
+/// 一時ファイルのパス
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".tmp");
+    p.into()
+}
+
+/// バックアップファイルのパス
+fn backup_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".bak");
+    p.into()
+}
+
+/// データを原子的に書き込む
+///
+/// 一時ファイルに書き出して同期した後、旧ファイルをバックアップに退避し、
+/// 一時ファイルを本来のパスへリネームする。
+pub fn save_atomic<T: Serialize>(
+    path: impl AsRef<Path>, 
+    value: &T, 
+) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let body = ron::ser::to_string_pretty(
+        value, 
+        ron::ser::PrettyConfig::default()
+    )?;
+
+    let tmp = tmp_path(path);
+    {
+        let mut fp = std::fs::File::create(&tmp)?;
+        fp.write_all(body.as_bytes())?;
+        fp.sync_all()?;
+    }
+    if path.exists() {
+        std::fs::rename(path, backup_path(path))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// ファイルを読み取ってデシリアライズする
+fn load<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Option<T>> {
+    match std::fs::read_to_string(path) {
+        Ok(body) => Ok(Some(ron::from_str(&body)?)), 
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None), 
+        Err(e) => Err(e.into()), 
+    }
+}
+
+/// データを読み込む
+///
+/// 本体が壊れていた場合は退避した上でバックアップからの復旧を試み、
+/// それも叶わなければ既定値を返す。
+pub fn load_or_recover<T: DeserializeOwned + Default>(
+    path: impl AsRef<Path>, 
+) -> T {
+    let path = path.as_ref();
+    match load(path) {
+        Ok(Some(v)) => return v, 
+        Ok(None) => {}, 
+        Err(e) => {
+            log::warn!("failed to load {0}: {e}", path.display());
+            let mut corrupt = path.as_os_str().to_owned();
+            corrupt.push(format!(
+                ".corrupt-{0}", 
+                chrono::Local::now().format("%Y%m%d%H%M%S")
+            ));
+            if let Err(e) = std::fs::rename(path, &corrupt) {
+                log::warn!("failed to move corrupted file: {e}");
+            }
+        }, 
+    }
+    let backup = backup_path(path);
+    match load(&backup) {
+        Ok(Some(v)) => {
+            log::info!("recovered from backup: {0}", backup.display());
+            v
+        }, 
+        Ok(None) => T::default(), 
+        Err(e) => {
+            log::warn!("failed to load backup {0}: {e}", backup.display());
+            T::default()
+        }, 
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// テストごとの空の一時ディレクトリ
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("breakout-persist-{0}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_keeps_previous_as_backup() {
+        let dir = temp_dir("backup");
+        let path = dir.join("data.ron");
+        save_atomic(&path, &vec![1u32]).unwrap();
+        save_atomic(&path, &vec![1u32, 2]).unwrap();
+        assert_eq!(load::<Vec<u32>>(&path).unwrap(), Some(vec![1, 2]));
+        assert_eq!(load::<Vec<u32>>(&backup_path(&path)).unwrap(), Some(vec![1]));
+        assert!(!tmp_path(&path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_file_is_recovered_from_backup() {
+        let dir = temp_dir("recover");
+        let path = dir.join("data.ron");
+        save_atomic(&path, &vec![7u32]).unwrap();
+        save_atomic(&path, &vec![7u32, 8]).unwrap();
+        std::fs::write(&path, "(not ron").unwrap();
+        assert_eq!(load_or_recover::<Vec<u32>>(&path), vec![7]);
+        // 壊れたファイルは消さずに退避する
+        let corrupt = std::fs::read_dir(&dir).unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("data.ron.corrupt-"))
+            .count();
+        assert_eq!(corrupt, 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_file_loads_default() {
+        let dir = temp_dir("missing");
+        assert_eq!(load_or_recover::<Vec<u32>>(dir.join("data.ron")), Vec::<u32>::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
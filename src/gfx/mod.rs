@@ -22,7 +22,7 @@ pub struct WGContext {
 }
 impl WGContext {
     /// コンテキストの初期化
-    pub(super) async fn new(window: &Window) -> anyhow::Result<Self> {
+    pub async fn new(window: &Window) -> anyhow::Result<Self> {
         // コンテキストの初期化
         let size = window.inner_size();
         let instance = Instance::new(Backends::all());
@@ -62,7 +62,7 @@ impl WGContext {
     }
 
     /// 描画領域のリサイズ
-    pub(super) fn resize(
+    pub fn resize(
         &mut self, 
         new_size: PhysicalSize<u32>
     ) { if new_size.width > 0 && new_size.height > 0 {
@@ -73,12 +73,12 @@ impl WGContext {
     }}
     
     /// コンフィグの再読取り
-    pub(super) fn re_configure(&mut self) {
+    pub fn re_configure(&mut self) {
         self.surface.configure(&self.device, &self.config);
     }
 
     /// 描画処理
-    pub(super) fn rendering(
+    pub fn rendering(
         &self, 
     ) -> Result<RenderingChain, wgpu::SurfaceError> 
     {
@@ -0,0 +1,34 @@
+//! ブロック崩し
+
+/// グラフィクス
+#[cfg(feature = "client")]
+pub mod gfx;
+
+/// サウンドエフェクト
+pub mod sfx;
+
+/// ゲーム本体の実装
+pub mod game;
+
+/// マウスの移動率入力のバッファ
+#[derive(Default)]
+pub struct MouseMoveBuffer {
+    move_vol: nalgebra::Vector2<f32>, 
+}
+impl MouseMoveBuffer {
+    pub fn new() -> Self { Self {
+        move_vol: Default::default()
+    } }
+    pub fn input(&mut self, move_vol: nalgebra::Vector2<f32>) {
+        self.move_vol += move_vol
+    }
+    pub fn finalize(&mut self) -> MouseMoveInput {
+        let input = MouseMoveInput(self.move_vol);
+        self.move_vol = Default::default();
+        input
+    }
+}
+
+/// マウスの移動率データ
+#[derive(Clone, Copy, Debug)]
+pub struct MouseMoveInput(pub nalgebra::Vector2<f32>);
@@ -1,6 +1,5 @@
 use std::sync::Arc;
 
-use parking_lot::Mutex;
 use winit::{
     event_loop::EventLoop, 
     window::WindowBuilder, 
@@ -12,81 +11,10 @@ use winit::{
     }, platform::run_return::EventLoopExtRunReturn
 };
 
-/// グラフィクス
-pub mod gfx;
+use breakout::{gfx, sfx, game, MouseMoveBuffer};
 
-/// サウンドエフェクト
-pub mod sfx;
-
-/// ゲーム本体の実装
-pub mod game;
-
-/// マウスの移動率入力のバッファ
-pub struct MouseMoveBuffer {
-    move_vol: nalgebra::Vector2<f32>, 
-}
-impl MouseMoveBuffer {
-    pub fn new() -> Self { Self {
-        move_vol: Default::default()
-    } }
-    pub fn input(&mut self, move_vol: nalgebra::Vector2<f32>) {
-        self.move_vol += move_vol
-    }
-    pub fn finalize(&mut self) -> MouseMoveInput {
-        let input = MouseMoveInput(self.move_vol);
-        self.move_vol = Default::default();
-        input
-    }
-}
-
-/// ブロックの機能
-pub struct BrickFeature {
-    score: u64, 
-    blk_type: BrickType, 
-}
-impl BrickFeature {
-    pub fn new(
-        score: u64, 
-        blk_type: BrickType, 
-    ) -> Self { Self {
-        score,
-        blk_type,
-    } }
-}
-impl game::breakout::entities::brick::brick::BrickFeature for BrickFeature {
-    fn hitted_process(
-        &self, 
-        state: &mut game::breakout::state::BreakOutGameState, 
-    ) {
-        *state.score.lock() += self.score;
-        match self.blk_type {
-            BrickType::Normal => {},
-            BrickType::Upper => match state.difficulity {
-                ref mut a @ game::breakout::state::BreakOutDifficulity::Easy => {
-                    *a = game::breakout::state::BreakOutDifficulity::Normal
-                },
-                _ => {}
-            },
-            BrickType::Top => match state.difficulity {
-                game::breakout::state::BreakOutDifficulity::Hard => {},
-                ref mut a @ _ => {
-                    *a = game::breakout::state::BreakOutDifficulity::Hard
-                }
-            },
-        }
-    }
-}
-
-/// ブロックの種類
-pub enum BrickType {
-    Normal, 
-    Upper, 
-    Top, 
-}
-
-/// マウスの移動率データ
-#[derive(Clone, Copy, Debug)]
-pub struct MouseMoveInput(pub nalgebra::Vector2<f32>);
+/// コマンドラインのサブコマンド
+mod cli;
 
 /// コンテキストのスポーン及び実行
 async fn run() -> anyhow::Result<i32> {
@@ -100,7 +28,7 @@ async fn run() -> anyhow::Result<i32> {
         parking_lot::Mutex::new(gfx::WGContext::new(&window).await?)
     );
 
-    let mut sfx_ctx = sfx::SfxModule::new(0.063)?;
+    let sfx_ctx = sfx::SfxModule::new(0.063)?;
     sfx_ctx.add_resource(
         "pause", 
         rodio::Decoder::new_mp3(
@@ -128,39 +56,9 @@ async fn run() -> anyhow::Result<i32> {
 
     let mut game_ctx = game::GameCtx::new(
         Arc::clone(&wgpu_ctx), 
-        |ctx, state| {
-            Ok(Box::new(game::breakout::BreakOut::new(
-                ctx, 
+        |_ctx, state| {
+            Ok(Box::new(game::title::Title::new(
                 state.font.clone(), 
-                game::breakout::entities::brick::BrickSpawnParam {
-                    column: 5,
-                    row: 24,
-                    margin_top: 32.,
-                    brick_margin: [2., 4.],
-                    brick_size: [24., 12.],
-                    spawn_f: Arc::new(Mutex::new(|
-                        pos: [u32; 2], 
-                        blk_pos, 
-                        blk_size, 
-                    | {
-                        Some(game::breakout::entities::brick::Brick::spawn(
-                            BrickFeature::new(
-                                100 * (pos[1] as u64 + 1), 
-                                if pos[1] >= 4 { BrickType::Top }
-                                else if pos[1] >= 3 { BrickType::Upper }
-                                else { BrickType::Normal }, 
-                            ), 
-                            blk_pos, 
-                            blk_size, 
-                            [
-                                1. - pos[1] as f32 * (1. / 5.), 
-                                pos[0] as f32 * (1. / 24.), 
-                                pos[1] as f32 * (1. / 5.), 
-                                1.
-                            ], 
-                        ))
-                    })),
-                }, 
             )?))
         }
     )?;
@@ -173,7 +71,10 @@ async fn run() -> anyhow::Result<i32> {
             window_id, 
             ref event 
         } if window_id == window.id() => match event {
-            WindowEvent::CloseRequested => ctl.set_exit(), 
+            WindowEvent::CloseRequested => {
+                game_ctx.autosave();
+                ctl.set_exit()
+            }, 
             WindowEvent::Resized(new_size) => wgpu_ctx.lock().resize(*new_size), 
             WindowEvent::KeyboardInput { 
                 input: KeyboardInput {
@@ -295,6 +196,12 @@ fn main() -> anyhow::Result<()> {
     // ログの初期化
     fern_init()?;
 
+    // サブコマンドの実行
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(code) = cli::run(&args)? {
+        std::process::exit(code);
+    }
+
     // コンテキストの実行
     let result = pollster::block_on(run())?;
     log::info!("process result: {result}");
@@ -1,8 +1,11 @@
 //! サウンドエフェクトモジュール
 
-use std::{sync::Arc, borrow::Cow};
+#[cfg(feature = "client")]
+use std::{sync::Arc, borrow::Cow, time::Duration};
 
+#[cfg(feature = "client")]
 use parking_lot::RwLock;
+#[cfg(feature = "client")]
 use rodio::{
     dynamic_mixer::{
         mixer, 
@@ -12,14 +15,17 @@ use rodio::{
     Source, 
     OutputStreamHandle, 
     Sink, 
-    source::{Zero, Buffered}, 
+    source::{Zero, Buffered, SineWave}, 
     Decoder, 
 };
+#[cfg(feature = "client")]
 use hashbrown::HashMap;
 
 /// サウンドエフェクトモジュール
+#[cfg(feature = "client")]
 #[derive(Clone)]
 pub struct SfxModule(Arc<RwLock<SfxModuleInner>>);
+#[cfg(feature = "client")]
 impl SfxModule {
     pub fn new(volume: f32) -> anyhow::Result<Self> {
         Ok(Self(Arc::new(RwLock::new(
@@ -33,12 +39,16 @@ impl SfxModule {
         self.0.read().play(src)
     }
     pub fn add_resource<T: Into<Cow<'static, str>>> (
-        &mut self, 
+        &self, 
         name: T, 
         file: Decoder<std::fs::File>
     ) -> Option<Buffered<Decoder<std::fs::File>>> {
         self.0.write().add_resource(name, file)
     }
+    /// 登録済みの効果音か
+    pub fn has_resource(&self, name: &str) -> bool {
+        self.0.read().res_mngr.resources.contains_key(name)
+    }
     pub fn play_resource<T: Source<Item = S> + Send + 'static, S: rodio::Sample> (
         &self, 
         name: &str, 
@@ -46,9 +56,77 @@ impl SfxModule {
     ) -> bool {
         self.0.read().play_resource(name, f)
     }
+    /// (周波数[Hz], 長さ[ms])の並びを順に鳴らす
+    pub fn play_tones(&self, tones: &[(f32, u64)]) {
+        let inner = self.0.read();
+        let mut offset = 0;
+        for (freq, length) in tones.iter().copied() {
+            inner.play(
+                SineWave::new(freq)
+                    .take_duration(Duration::from_millis(length))
+                    .amplify(0.5)
+                    .delay(Duration::from_millis(offset))
+            );
+            offset += length;
+        }
+    }
+}
+
+/// 効果音の出力先
+///
+/// ロールバック時の再計算などで音を鳴らしたくない場合は`MutedSfx`を渡す。
+pub trait SfxSink {
+    /// 登録済みの効果音を鳴らす
+    fn play_effect(&self, name: &str);
+}
+#[cfg(feature = "client")]
+impl SfxSink for SfxModule {
+    fn play_effect(&self, name: &str) {
+        self.play_resource(name, |r| r);
+    }
+}
+
+/// 何も鳴らさない効果音の出力先
+pub struct MutedSfx;
+impl SfxSink for MutedSfx {
+    fn play_effect(&self, _name: &str) {}
+}
+
+/// 効果音の差し替え
+///
+/// 効果音の名前と、代わりに鳴らす登録済みの効果音の名前の対応。
+#[derive(Clone, Debug, Default)]
+pub struct SfxTheme(hashbrown::HashMap<String, String>);
+impl SfxTheme {
+    pub fn insert(&mut self, name: impl Into<String>, key: impl Into<String>) {
+        self.0.insert(name.into(), key.into());
+    }
+    /// 実際に鳴らす効果音の名前
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.0.get(name).map_or(name, |k| k.as_str())
+    }
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+/// 効果音を差し替えて鳴らす出力先
+pub struct ThemedSfx<'a> {
+    sink: &'a dyn SfxSink, 
+    theme: Option<&'a SfxTheme>, 
+}
+impl<'a> ThemedSfx<'a> {
+    pub fn new(sink: &'a dyn SfxSink, theme: Option<&'a SfxTheme>) -> Self { Self {
+        sink, 
+        theme, 
+    }}
+}
+impl SfxSink for ThemedSfx<'_> {
+    fn play_effect(&self, name: &str) {
+        self.sink.play_effect(self.theme.map_or(name, |t| t.resolve(name)))
+    }
 }
 
 /// サウンドエフェクトモジュールの内部型
+#[cfg(feature = "client")]
 struct SfxModuleInner {
     _stream: OutputStream, 
     _stream_handle: OutputStreamHandle, 
@@ -56,6 +134,7 @@ struct SfxModuleInner {
     mixer_ctrl: Arc<DynamicMixerController<f32>>, 
     res_mngr: SfxResourceMngrInner, 
 }
+#[cfg(feature = "client")]
 impl SfxModuleInner {
     pub fn new(volume: f32) -> anyhow::Result<Self> {
         let (
@@ -104,12 +183,14 @@ impl SfxModuleInner {
 }
 
 /// サウンドエフェクトの音声データのマネージャの内部型
+#[cfg(feature = "client")]
 struct SfxResourceMngrInner {
     resources: HashMap<
         Cow<'static, str>, 
         Buffered<Decoder<std::fs::File>>, 
     >
 }
+#[cfg(feature = "client")]
 impl SfxResourceMngrInner {
     fn new() -> Self { Self { resources: Default::default() } }
     fn add<T: Into<Cow<'static, str>>>(